@@ -1,19 +1,46 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+
 use anyhow::anyhow;
 use log::debug;
 use log::info;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
 use starpls_bazel::client::BazelClient;
 use starpls_bazel::client::BazelInfo;
+use starpls_bazel::decode_builtins;
+use starpls_bazel::encode_builtins;
 use starpls_bazel::Builtins;
 
+use crate::get_version;
 use crate::server::load_bazel_build_language;
 
+/// Bump this whenever the format of the cached files changes.
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// Files in the workspace folder that affect the Bazel configuration. The cache is keyed by
+/// their contents, along with the workspace folder, the Bazel executable and its version.
+const CACHE_KEY_FILES: &[&str] = &["MODULE.bazel", ".bazelrc", ".bazelversion"];
+
+const CACHED_CONTEXT_FILE: &str = "context.json";
+const CACHED_RULES_FILE: &str = "rules.pb";
+
 /// Contains information about the current Bazel configuration as fetched from
 /// various `bazel info` commands.
-#[derive(Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub(crate) struct BazelContext {
     pub(crate) info: BazelInfo,
     pub(crate) rules: Builtins,
     pub(crate) bzlmod_enabled: bool,
+    /// Repository mappings fetched with `bazel mod dump_repo_mapping`, keyed by the canonical
+    /// name of the repository they apply to. This only contains the main repository's mapping.
+    pub(crate) repo_mappings: HashMap<String, HashMap<String, String>>,
 }
 
 impl BazelContext {
@@ -55,11 +82,14 @@ impl BazelContext {
         info!("bzlmod_enabled = {}", bzlmod_enabled);
 
         // If bzlmod is enabled, we also need to check if the `bazel mod dump_repo_mapping` command is supported.
+        // The main repository's mapping is kept around, since most loads are resolved with it.
+        let mut repo_mappings = HashMap::new();
         if bzlmod_enabled {
             debug!("checking for `bazel mod dump_repo_mapping` capability");
-            client
+            let mapping = client
                 .dump_repo_mapping("")
                 .map_err(|err| anyhow!("failed to run `bazel mod dump_repo_mapping`: {}", err))?;
+            repo_mappings.insert(String::new(), mapping);
         }
 
         debug!("fetching builtin rules via `bazel info build-language`");
@@ -70,6 +100,163 @@ impl BazelContext {
             info,
             rules,
             bzlmod_enabled,
+            repo_mappings,
         })
     }
 }
+
+#[derive(Serialize, Deserialize)]
+struct CachedBazelContext {
+    info: BazelInfo,
+    bzlmod_enabled: bool,
+    repo_mappings: HashMap<String, HashMap<String, String>>,
+}
+
+/// An on-disk cache for the [`BazelContext`] of a workspace folder, so that the server doesn't
+/// have to wait for `bazel info` on startup. Since the cache key can't account for everything
+/// that affects the Bazel configuration, e.g. flags from imported `.bazelrc` files, cached
+/// contexts are meant to be refreshed in the background.
+#[derive(Clone, Debug)]
+pub(crate) struct BazelCache {
+    dir: PathBuf,
+}
+
+impl BazelCache {
+    /// Returns the cache for the given workspace folder and Bazel executable, or `None` if there
+    /// is no cache directory, e.g. because `$HOME` isn't set, or if the Bazel version couldn't be
+    /// determined.
+    pub(crate) fn new(
+        folder: &Path,
+        bazel_path: &str,
+        bazel_client: &dyn BazelClient,
+    ) -> Option<Self> {
+        let bazel_version = match bazel_client.version() {
+            Ok(version) => version,
+            Err(err) => {
+                debug!("failed to determine the Bazel version: {}", err);
+                return None;
+            }
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_FORMAT_VERSION.to_le_bytes());
+        hash_key_part(&mut hasher, Some(get_version().as_bytes()));
+        hash_key_part(&mut hasher, Some(bazel_version.as_bytes()));
+        hash_key_part(&mut hasher, Some(bazel_path.as_bytes()));
+        hash_key_part(&mut hasher, Some(folder.as_os_str().as_encoded_bytes()));
+        for file in CACHE_KEY_FILES {
+            hash_key_part(&mut hasher, fs::read(folder.join(file)).ok().as_deref());
+        }
+
+        let key = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        Some(Self {
+            dir: cache_dir()?.join("bazel").join(key),
+        })
+    }
+
+    pub(crate) fn load(&self) -> anyhow::Result<BazelContext> {
+        let cached: CachedBazelContext =
+            serde_json::from_slice(&fs::read(self.dir.join(CACHED_CONTEXT_FILE))?)?;
+        let rules = decode_builtins(&fs::read(self.dir.join(CACHED_RULES_FILE))?)?;
+        Ok(BazelContext {
+            info: cached.info,
+            rules,
+            bzlmod_enabled: cached.bzlmod_enabled,
+            repo_mappings: cached.repo_mappings,
+        })
+    }
+
+    pub(crate) fn store(&self, bazel_cx: &BazelContext) -> anyhow::Result<()> {
+        let cached = CachedBazelContext {
+            info: bazel_cx.info.clone(),
+            bzlmod_enabled: bazel_cx.bzlmod_enabled,
+            repo_mappings: bazel_cx.repo_mappings.clone(),
+        };
+        fs::create_dir_all(&self.dir)?;
+        write_atomic(
+            &self.dir.join(CACHED_RULES_FILE),
+            &encode_builtins(&bazel_cx.rules),
+        )?;
+        write_atomic(
+            &self.dir.join(CACHED_CONTEXT_FILE),
+            &serde_json::to_vec(&cached)?,
+        )
+    }
+}
+
+/// Adds a part of the cache key to the given hasher. Parts are prefixed with their lengths, so
+/// that different sets of parts never produce the same input.
+fn hash_key_part(hasher: &mut Sha256, part: Option<&[u8]>) {
+    match part {
+        Some(part) => {
+            hasher.update([1]);
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        None => hasher.update([0]),
+    }
+}
+
+/// Writes the given file through a temporary file, so that other servers never read a partially
+/// written file.
+fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension(format!("tmp{}", process::id()));
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .map(|dir| dir.join("starpls"))
+}
+
+#[cfg(test)]
+mod tests {
+    use starpls_bazel::builtin::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_store_and_load() {
+        let cache = BazelCache {
+            dir: env::temp_dir().join(format!("starpls-bazel-cache-{}", process::id())),
+        };
+        let bazel_cx = BazelContext {
+            info: BazelInfo {
+                output_base: PathBuf::from("/output_base"),
+                release: "release 7.1.0".to_string(),
+                starlark_semantics: "StarlarkSemantics{enable_bzlmod=true}".to_string(),
+                workspace: PathBuf::from("/workspace"),
+                workspace_name: Some("_main".to_string()),
+            },
+            rules: Builtins {
+                global: vec![Value {
+                    name: "cc_library".to_string(),
+                    doc: "A C++ library.".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            bzlmod_enabled: true,
+            repo_mappings: HashMap::from([(
+                String::new(),
+                HashMap::from([("foo".to_string(), "foo~".to_string())]),
+            )]),
+        };
+
+        assert!(cache.load().is_err());
+        cache.store(&bazel_cx).unwrap();
+        let loaded = cache.load();
+        fs::remove_dir_all(&cache.dir).unwrap();
+        assert_eq!(loaded.unwrap(), bazel_cx);
+    }
+}
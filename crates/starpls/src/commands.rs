@@ -1,9 +1,10 @@
 use clap::Args;
 
 pub(crate) mod check;
+pub(crate) mod format;
 pub(crate) mod server;
 
-#[derive(Args, Default)]
+#[derive(Args, Clone, Default, PartialEq)]
 pub(crate) struct InferenceOptions {
     /// Infer attributes on a rule implementation function's context parameter.
     #[clap(long = "experimental_infer_ctx_attributes", default_value_t = false)]
@@ -12,4 +13,8 @@ pub(crate) struct InferenceOptions {
     /// Use code-flow analysis during typechecking.
     #[clap(long = "experimental_use_code_flow_analysis", default_value_t = false)]
     pub(crate) use_code_flow_analysis: bool,
+
+    /// Report loaded symbols that are never accessed.
+    #[clap(long = "experimental_report_unused_loads", default_value_t = false)]
+    pub(crate) report_unused_loads: bool,
 }
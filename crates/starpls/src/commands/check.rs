@@ -75,6 +75,7 @@ impl CheckCommand {
             starpls_ide::InferenceOptions {
                 infer_ctx_attributes: self.inference_options.infer_ctx_attributes,
                 use_code_flow_analysis: self.inference_options.use_code_flow_analysis,
+                report_unused_loads: self.inference_options.report_unused_loads,
                 ..Default::default()
             },
         );
@@ -137,7 +138,7 @@ fn diagnostic_to_message<'a>(
     )
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
+pub(crate) fn is_hidden(entry: &DirEntry) -> bool {
     entry
         .file_name()
         .to_str()
@@ -148,6 +149,20 @@ fn is_hidden(entry: &DirEntry) -> bool {
         .unwrap_or(false)
 }
 
+/// Walks the given directory, skipping hidden entries as well as entries matching any of the
+/// given ignore patterns.
+pub(crate) fn walk_dir<'a>(
+    path: impl AsRef<Path>,
+    ignore_patterns: &'a [String],
+) -> impl Iterator<Item = walkdir::Result<DirEntry>> + 'a {
+    WalkDir::new(path).into_iter().filter_entry(move |e| {
+        !is_hidden(e)
+            && !ignore_patterns
+                .iter()
+                .any(|pat| e.file_name().to_str().map(|s| s == pat).unwrap_or(false))
+    })
+}
+
 impl Checker {
     fn new(
         analysis: Analysis,
@@ -167,12 +182,7 @@ impl Checker {
         let mut change = Change::default();
 
         for path in paths {
-            for entry in WalkDir::new(&path).into_iter().filter_entry(|e| {
-                !is_hidden(e)
-                    && !ignore_patterns
-                        .iter()
-                        .any(|pat| e.file_name().to_str().map(|s| s == pat).unwrap_or(false))
-            }) {
+            for entry in walk_dir(&path, &ignore_patterns) {
                 let entry = entry?;
                 if entry.file_type().is_file() {
                     let is_explicit = entry.path().as_os_str().to_str() == Some(path.as_str());
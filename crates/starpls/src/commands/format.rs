@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::Args;
+use starpls_bazel::client::BazelCLI;
+use starpls_bazel::client::BazelClient;
+use starpls_common::Dialect;
+use starpls_ide::format_source;
+use starpls_ide::FormatMode;
+
+use crate::commands::check::walk_dir;
+use crate::document;
+
+#[derive(Args, Default)]
+pub(crate) struct FormatCommand {
+    /// Paths to format.
+    pub(crate) paths: Vec<String>,
+
+    /// Report files that aren't formatted instead of rewriting them, exiting with a non-zero
+    /// status if there are any.
+    #[clap(long = "check", default_value_t = false)]
+    pub(crate) check: bool,
+
+    /// Specify patterns of files/directories to ignore.
+    #[clap(long = "ignore_pattern")]
+    pub(crate) ignore_patterns: Vec<String>,
+
+    #[clap(long = "ext")]
+    pub(crate) extensions: Vec<String>,
+}
+
+impl FormatCommand {
+    pub(crate) fn run(self) -> anyhow::Result<()> {
+        // Determine the file kinds relative to the workspace root, which may differ from the
+        // current directory.
+        let workspace = BazelCLI::default()
+            .info()
+            .map_err(|err| anyhow!("failed to run `bazel info`: {}", err))?
+            .workspace;
+
+        // Strip off the leading "." from each of the specified extensions.
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|ext| match ext.strip_prefix('.') {
+                Some(ext) => ext,
+                None => ext,
+            })
+            .chain(["star", "sky"])
+            .collect::<Vec<_>>();
+
+        let mut num_unformatted = 0;
+        let mut num_errors = 0;
+        for path in &self.paths {
+            for entry in walk_dir(path, &self.ignore_patterns) {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let canonical_path = entry.path().canonicalize()?;
+                let Some(mode) = format_mode_for_path(&workspace, &canonical_path, &extensions)
+                else {
+                    continue;
+                };
+
+                let contents = fs::read_to_string(entry.path())?;
+                let Some(formatted) = format_source(&contents, mode) else {
+                    anstream::eprintln!(
+                        "error: failed to format {}: file contains syntax errors",
+                        entry.path().display()
+                    );
+                    num_errors += 1;
+                    continue;
+                };
+                if formatted == contents {
+                    continue;
+                }
+
+                if self.check {
+                    anstream::println!("{}", entry.path().display());
+                    num_unformatted += 1;
+                } else {
+                    fs::write(entry.path(), formatted).map_err(|err| {
+                        anyhow!("failed to write {}: {}", entry.path().display(), err)
+                    })?;
+                }
+            }
+        }
+
+        if num_unformatted > 0 {
+            anstream::eprintln!("{} files need formatting", num_unformatted);
+        }
+        if num_errors > 0 || num_unformatted > 0 {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
+
+fn format_mode_for_path(workspace: &Path, path: &Path, extensions: &[&str]) -> Option<FormatMode> {
+    let (dialect, api_context) =
+        document::dialect_and_api_context_for_workspace_path(workspace, path)?;
+
+    // Only format files that match any of the file extensions passed via the command line.
+    // This always includes ".star" and ".sky" files.
+    if dialect == Dialect::Standard
+        && !path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| extensions.contains(&ext))
+            .unwrap_or(false)
+    {
+        return None;
+    }
+
+    Some(FormatMode::from_api_context(api_context))
+}
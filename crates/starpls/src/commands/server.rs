@@ -1,23 +1,44 @@
 use clap::Args;
 use log::info;
 use lsp_server::Connection;
+use lsp_types::CallHierarchyServerCapability;
+use lsp_types::CodeActionKind;
+use lsp_types::CodeActionOptions;
+use lsp_types::CodeActionProviderCapability;
+use lsp_types::CodeLensOptions;
 use lsp_types::CompletionOptions;
+use lsp_types::DiagnosticOptions;
+use lsp_types::DiagnosticServerCapabilities;
+use lsp_types::DocumentLinkOptions;
+use lsp_types::ExecuteCommandOptions;
+use lsp_types::FoldingRangeProviderCapability;
 use lsp_types::HoverProviderCapability;
+use lsp_types::ImplementationProviderCapability;
 use lsp_types::OneOf;
+use lsp_types::RenameOptions;
+use lsp_types::SelectionRangeProviderCapability;
+use lsp_types::SemanticTokensFullOptions;
+use lsp_types::SemanticTokensOptions;
+use lsp_types::SemanticTokensServerCapabilities;
 use lsp_types::ServerCapabilities;
 use lsp_types::SignatureHelpOptions;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
+use lsp_types::TypeDefinitionProviderCapability;
+use lsp_types::WorkspaceFoldersServerCapabilities;
+use lsp_types::WorkspaceServerCapabilities;
 
 use crate::commands::InferenceOptions;
 use crate::event_loop;
+use crate::extensions::COPY_LABEL_COMMAND;
 use crate::get_version;
 use crate::make_trigger_characters;
+use crate::semantic_tokens::semantic_tokens_legend;
 
 const COMPLETION_TRIGGER_CHARACTERS: &[char] = &['.', '"', '\'', '/', ':', '@'];
 const SIGNATURE_HELP_TRIGGER_CHARACTERS: &[char] = &['(', ',', ')'];
 
-#[derive(Args, Default)]
+#[derive(Args, Clone, Default)]
 pub(crate) struct ServerCommand {
     /// Path to the Bazel binary.
     #[clap(long = "bazel_path")]
@@ -30,6 +51,14 @@ pub(crate) struct ServerCommand {
     )]
     pub(crate) enable_label_completions: bool,
 
+    /// Analyze all Starlark files in the workspace in the background, reporting diagnostics
+    /// for files that aren't open in the editor.
+    #[clap(
+        long = "experimental_enable_workspace_diagnostics",
+        default_value_t = false
+    )]
+    pub(crate) enable_workspace_diagnostics: bool,
+
     /// After receiving an edit event, the amount of time in milliseconds
     /// the server will wait for additional events before running analysis
     #[clap(long = "analysis_debounce_interval", default_value_t = 250)]
@@ -49,14 +78,59 @@ impl ServerCommand {
         // Initialize the connection with server capabilities. For now, this consists
         // only of `TextDocumentSyncKind.Full`.
         let server_capabilities = serde_json::to_value(ServerCapabilities {
+            call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![
+                    CodeActionKind::QUICKFIX,
+                    CodeActionKind::SOURCE_FIX_ALL,
+                ]),
+                ..Default::default()
+            })),
+            code_lens_provider: Some(CodeLensOptions {
+                resolve_provider: Some(false),
+            }),
             completion_provider: Some(CompletionOptions {
                 trigger_characters: Some(make_trigger_characters(COMPLETION_TRIGGER_CHARACTERS)),
+                resolve_provider: Some(true),
                 ..Default::default()
             }),
             definition_provider: Some(OneOf::Left(true)),
+            diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                identifier: Some("starpls".to_string()),
+                inter_file_dependencies: true,
+                workspace_diagnostics: true,
+                ..Default::default()
+            })),
+            document_formatting_provider: Some(OneOf::Left(true)),
+            document_highlight_provider: Some(OneOf::Left(true)),
+            document_link_provider: Some(DocumentLinkOptions {
+                resolve_provider: Some(false),
+                work_done_progress_options: Default::default(),
+            }),
+            document_range_formatting_provider: Some(OneOf::Left(true)),
             document_symbol_provider: Some(OneOf::Left(true)),
+            execute_command_provider: Some(ExecuteCommandOptions {
+                commands: vec![COPY_LABEL_COMMAND.to_string()],
+                ..Default::default()
+            }),
+            folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
+            inlay_hint_provider: Some(OneOf::Left(true)),
             references_provider: Some(OneOf::Left(true)),
+            rename_provider: Some(OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                work_done_progress_options: Default::default(),
+            })),
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+            semantic_tokens_provider: Some(
+                SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                    legend: semantic_tokens_legend(),
+                    range: Some(true),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                    ..Default::default()
+                }),
+            ),
             signature_help_provider: Some(SignatureHelpOptions {
                 trigger_characters: Some(make_trigger_characters(
                     SIGNATURE_HELP_TRIGGER_CHARACTERS,
@@ -66,6 +140,15 @@ impl ServerCommand {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(
                 TextDocumentSyncKind::INCREMENTAL,
             )),
+            type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+            workspace: Some(WorkspaceServerCapabilities {
+                workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                    supported: Some(true),
+                    change_notifications: Some(OneOf::Left(true)),
+                }),
+                file_operations: None,
+            }),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
             ..Default::default()
         })?;
         let initialize_params =
@@ -1,13 +1,45 @@
 use lsp_types::ClientCapabilities;
+use serde::Deserialize;
+use starpls_ide::InferenceOptions;
 
 use crate::commands::server::ServerCommand;
 
-#[derive(Default)]
+/// The section requested with `workspace/configuration`, and the key that settings may be nested
+/// under in `initializationOptions` and `workspace/didChangeConfiguration`.
+pub(crate) const CONFIGURATION_SECTION: &str = "starpls";
+
+#[derive(Clone, Default)]
 pub(crate) struct ServerConfig {
     pub(crate) args: ServerCommand,
     pub(crate) caps: ClientCapabilities,
 }
 
+/// Settings provided by the client at runtime. Each setting overrides the corresponding
+/// command-line flag, and settings that aren't specified leave the current value unchanged.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ClientSettings {
+    pub(crate) bazel_path: Option<String>,
+    pub(crate) enable_label_completions: Option<bool>,
+    pub(crate) enable_workspace_diagnostics: Option<bool>,
+    pub(crate) analysis_debounce_interval: Option<u64>,
+    pub(crate) infer_ctx_attributes: Option<bool>,
+    pub(crate) use_code_flow_analysis: Option<bool>,
+    pub(crate) report_unused_loads: Option<bool>,
+}
+
+impl ClientSettings {
+    pub(crate) fn from_json(mut value: serde_json::Value) -> anyhow::Result<Self> {
+        if let Some(section) = value.get_mut(CONFIGURATION_SECTION) {
+            value = section.take();
+        }
+        if value.is_null() {
+            return Ok(Default::default());
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
 macro_rules! try_or_default {
     ($expr:expr) => {
         (|| $expr)().unwrap_or_default()
@@ -15,6 +47,46 @@ macro_rules! try_or_default {
 }
 
 impl ServerConfig {
+    pub(crate) fn apply_settings(&mut self, settings: ClientSettings) {
+        if let Some(bazel_path) = settings.bazel_path {
+            self.args.bazel_path = Some(bazel_path);
+        }
+        if let Some(enable_label_completions) = settings.enable_label_completions {
+            self.args.enable_label_completions = enable_label_completions;
+        }
+        if let Some(enable_workspace_diagnostics) = settings.enable_workspace_diagnostics {
+            self.args.enable_workspace_diagnostics = enable_workspace_diagnostics;
+        }
+        if let Some(analysis_debounce_interval) = settings.analysis_debounce_interval {
+            self.args.analysis_debounce_interval = analysis_debounce_interval;
+        }
+        if let Some(infer_ctx_attributes) = settings.infer_ctx_attributes {
+            self.args.inference_options.infer_ctx_attributes = infer_ctx_attributes;
+        }
+        if let Some(use_code_flow_analysis) = settings.use_code_flow_analysis {
+            self.args.inference_options.use_code_flow_analysis = use_code_flow_analysis;
+        }
+        if let Some(report_unused_loads) = settings.report_unused_loads {
+            self.args.inference_options.report_unused_loads = report_unused_loads;
+        }
+    }
+
+    pub(crate) fn inference_options(&self) -> InferenceOptions {
+        InferenceOptions {
+            infer_ctx_attributes: self.args.inference_options.infer_ctx_attributes,
+            use_code_flow_analysis: self.args.inference_options.use_code_flow_analysis,
+            report_unused_loads: self.args.inference_options.report_unused_loads,
+            ..Default::default()
+        }
+    }
+
+    /// Whether all files in the workspace are analyzed in the background to publish their
+    /// diagnostics. Clients that pull diagnostics request them with `workspace/diagnostic`
+    /// instead.
+    pub(crate) fn should_analyze_workspace(&self) -> bool {
+        self.args.enable_workspace_diagnostics && !self.has_pull_diagnostics_support()
+    }
+
     pub(crate) fn has_text_document_definition_link_support(&self) -> bool {
         try_or_default!(self.caps.text_document.as_ref()?.definition?.link_support)
     }
@@ -31,4 +103,58 @@ impl ServerConfig {
                 .insert_replace_support
         )
     }
+
+    pub(crate) fn has_snippet_support(&self) -> bool {
+        try_or_default!(
+            self.caps
+                .text_document
+                .as_ref()?
+                .completion
+                .as_ref()?
+                .completion_item
+                .as_ref()?
+                .snippet_support
+        )
+    }
+
+    pub(crate) fn has_pull_diagnostics_support(&self) -> bool {
+        try_or_default!(Some(self.caps.text_document.as_ref()?.diagnostic.is_some()))
+    }
+
+    pub(crate) fn has_diagnostic_refresh_support(&self) -> bool {
+        try_or_default!(
+            self.caps
+                .workspace
+                .as_ref()?
+                .diagnostic
+                .as_ref()?
+                .refresh_support
+        )
+    }
+
+    pub(crate) fn has_configuration_support(&self) -> bool {
+        try_or_default!(self.caps.workspace.as_ref()?.configuration)
+    }
+
+    pub(crate) fn has_did_change_configuration_dynamic_registration_support(&self) -> bool {
+        try_or_default!(
+            self.caps
+                .workspace
+                .as_ref()?
+                .did_change_configuration
+                .as_ref()?
+                .dynamic_registration
+        )
+    }
+
+    pub(crate) fn has_did_change_watched_files_dynamic_registration_support(&self) -> bool {
+        try_or_default!(
+            self.caps
+                .workspace
+                .as_ref()?
+                .did_change_watched_files
+                .as_ref()?
+                .dynamic_registration
+        )
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use anyhow::anyhow;
@@ -8,12 +9,30 @@ use starpls_common::Diagnostic;
 use starpls_common::DiagnosticTag;
 use starpls_common::FileId;
 use starpls_common::Severity;
+use starpls_ide::CallHierarchyItem;
+use starpls_ide::CodeAction;
+use starpls_ide::CodeActionKind;
+use starpls_ide::CodeLens;
+use starpls_ide::CodeLensKind;
+use starpls_ide::DocumentHighlight;
+use starpls_ide::DocumentHighlightKind;
+use starpls_ide::DocumentLink;
+use starpls_ide::DocumentLinkTarget;
 use starpls_ide::DocumentSymbol;
+use starpls_ide::FoldingRange;
+use starpls_ide::FoldingRangeKind;
+use starpls_ide::InlayHint;
+use starpls_ide::InlayHintKind;
+use starpls_ide::SourceChange;
 use starpls_ide::SymbolKind;
 use starpls_ide::SymbolTag;
+use starpls_ide::TextEdit;
+use starpls_ide::WorkspaceSymbol;
 use starpls_syntax::TextRange;
 use starpls_syntax::TextSize;
 
+use crate::extensions::CopyLabelArgs;
+use crate::extensions::COPY_LABEL_COMMAND;
 use crate::server::ServerSnapshot;
 
 pub(crate) fn path_buf_from_url(url: &lsp_types::Url) -> anyhow::Result<PathBuf> {
@@ -49,17 +68,20 @@ pub(crate) fn lsp_range_from_text_range(
     text_range: TextRange,
     line_index: &LineIndex,
 ) -> Option<lsp_types::Range> {
-    let start = line_index.to_wide(WideEncoding::Utf16, line_index.line_col(text_range.start()))?;
-    let end = line_index.to_wide(WideEncoding::Utf16, line_index.line_col(text_range.end()))?;
     Some(lsp_types::Range {
-        start: lsp_types::Position {
-            line: start.line,
-            character: start.col,
-        },
-        end: lsp_types::Position {
-            line: end.line,
-            character: end.col,
-        },
+        start: lsp_position_from_text_size(text_range.start(), line_index)?,
+        end: lsp_position_from_text_size(text_range.end(), line_index)?,
+    })
+}
+
+pub(crate) fn lsp_position_from_text_size(
+    text_size: TextSize,
+    line_index: &LineIndex,
+) -> Option<lsp_types::Position> {
+    let pos = line_index.to_wide(WideEncoding::Utf16, line_index.line_col(text_size))?;
+    Some(lsp_types::Position {
+        line: pos.line,
+        character: pos.col,
     })
 }
 
@@ -87,6 +109,155 @@ pub(crate) fn text_size_from_lsp_position(
     Ok(line_index.offset(line_col))
 }
 
+pub(crate) fn lsp_workspace_edit_from_native(
+    snapshot: &ServerSnapshot,
+    change: SourceChange,
+) -> anyhow::Result<lsp_types::WorkspaceEdit> {
+    let mut changes = HashMap::new();
+    for (file_id, edits) in change.file_edits {
+        let line_index = match snapshot.analysis_snapshot.line_index(file_id)? {
+            Some(line_index) => line_index,
+            None => continue,
+        };
+        let uri = match lsp_types::Url::from_file_path(
+            snapshot.document_manager.read().lookup_by_file_id(file_id),
+        ) {
+            Ok(uri) => uri,
+            Err(_) => continue,
+        };
+        changes.insert(
+            uri,
+            edits
+                .into_iter()
+                .filter_map(|edit| lsp_text_edit_from_native(edit, line_index))
+                .collect(),
+        );
+    }
+    Ok(lsp_types::WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    })
+}
+
+pub(crate) fn lsp_call_hierarchy_item_from_native(
+    snapshot: &ServerSnapshot,
+    item: CallHierarchyItem,
+) -> anyhow::Result<Option<lsp_types::CallHierarchyItem>> {
+    let line_index = match snapshot.analysis_snapshot.line_index(item.file_id)? {
+        Some(line_index) => line_index,
+        None => return Ok(None),
+    };
+    let path = snapshot
+        .document_manager
+        .read()
+        .lookup_by_file_id(item.file_id);
+
+    // Items for the top level of a file are named after the file itself.
+    let name = match item.name {
+        Some(name) => name,
+        None => match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => return Ok(None),
+        },
+    };
+    let uri = match lsp_types::Url::from_file_path(path) {
+        Ok(uri) => uri,
+        Err(_) => return Ok(None),
+    };
+    let (range, selection_range) = match (
+        lsp_range_from_text_range(item.range, line_index),
+        lsp_range_from_text_range(item.selection_range, line_index),
+    ) {
+        (Some(range), Some(selection_range)) => (range, selection_range),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(lsp_types::CallHierarchyItem {
+        name,
+        kind: lsp_symbol_kind_from_native(item.kind),
+        tags: None,
+        detail: None,
+        uri,
+        range,
+        selection_range,
+        data: None,
+    }))
+}
+
+pub(crate) fn lsp_text_edit_from_native(
+    edit: TextEdit,
+    line_index: &LineIndex,
+) -> Option<lsp_types::TextEdit> {
+    Some(lsp_types::TextEdit {
+        range: lsp_range_from_text_range(edit.range, line_index)?,
+        new_text: edit.new_text,
+    })
+}
+
+pub(crate) fn lsp_code_action_from_native(
+    snapshot: &ServerSnapshot,
+    action: CodeAction,
+    line_index: &LineIndex,
+) -> anyhow::Result<lsp_types::CodeAction> {
+    Ok(lsp_types::CodeAction {
+        title: action.title,
+        kind: Some(match action.kind {
+            CodeActionKind::QuickFix => lsp_types::CodeActionKind::QUICKFIX,
+            CodeActionKind::SourceFixAll => lsp_types::CodeActionKind::SOURCE_FIX_ALL,
+        }),
+        diagnostics: action
+            .diagnostic
+            .and_then(|diagnostic| lsp_diagnostic_from_native(diagnostic, line_index))
+            .map(|diagnostic| vec![diagnostic]),
+        edit: Some(lsp_workspace_edit_from_native(snapshot, action.change)?),
+        command: None,
+        is_preferred: Some(action.is_preferred),
+        disabled: None,
+        data: None,
+    })
+}
+
+pub(crate) fn lsp_code_lens_from_native(
+    uri: &lsp_types::Url,
+    lens: CodeLens,
+    line_index: &LineIndex,
+) -> Option<lsp_types::CodeLens> {
+    let command = match lens.kind {
+        CodeLensKind::Target {
+            rule,
+            name,
+            references,
+        } => lsp_types::Command {
+            title: format!("{} | {}", rule, pluralize(references, "reference")),
+            command: COPY_LABEL_COMMAND.to_string(),
+            arguments: Some(vec![serde_json::to_value(CopyLabelArgs {
+                uri: uri.clone(),
+                name,
+            })
+            .ok()?]),
+        },
+        // Clicking this lens doesn't do anything, so it has an empty command.
+        CodeLensKind::ExportedFunction { loaders, .. } => lsp_types::Command {
+            title: pluralize(loaders, "loader"),
+            command: String::new(),
+            arguments: None,
+        },
+    };
+    Some(lsp_types::CodeLens {
+        range: lsp_range_from_text_range(lens.range, line_index)?,
+        command: Some(command),
+        data: None,
+    })
+}
+
+fn pluralize(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
 fn lsp_severity_from_native(severity: Severity) -> lsp_types::DiagnosticSeverity {
     match severity {
         Severity::Error => lsp_types::DiagnosticSeverity::ERROR,
@@ -94,6 +265,33 @@ fn lsp_severity_from_native(severity: Severity) -> lsp_types::DiagnosticSeverity
     }
 }
 
+pub(crate) fn lsp_document_link_from_native(
+    snapshot: &ServerSnapshot,
+    link: DocumentLink,
+    line_index: &LineIndex,
+) -> Option<lsp_types::DocumentLink> {
+    let target = match link.target {
+        DocumentLinkTarget::Path(path) => lsp_types::Url::from_file_path(path).ok()?,
+        DocumentLinkTarget::Target { file_id, range } => {
+            let path = snapshot.document_manager.read().lookup_by_file_id(file_id);
+            let mut url = lsp_types::Url::from_file_path(path).ok()?;
+
+            // Link to the line declaring the target, which most clients support through the
+            // `#L<line>` fragment.
+            let target_line_index = snapshot.analysis_snapshot.line_index(file_id).ok()??;
+            let range = lsp_range_from_text_range(range, target_line_index)?;
+            url.set_fragment(Some(&format!("L{}", range.start.line + 1)));
+            url
+        }
+    };
+    Some(lsp_types::DocumentLink {
+        range: lsp_range_from_text_range(link.range, line_index)?,
+        target: Some(target),
+        tooltip: link.tooltip,
+        data: None,
+    })
+}
+
 #[allow(deprecated)]
 pub(crate) fn lsp_document_symbol_from_native(
     DocumentSymbol {
@@ -110,34 +308,7 @@ pub(crate) fn lsp_document_symbol_from_native(
     Some(lsp_types::DocumentSymbol {
         name,
         detail,
-        kind: match kind {
-            SymbolKind::File => lsp_types::SymbolKind::FILE,
-            SymbolKind::Module => lsp_types::SymbolKind::MODULE,
-            SymbolKind::Namespace => lsp_types::SymbolKind::NAMESPACE,
-            SymbolKind::Package => lsp_types::SymbolKind::PACKAGE,
-            SymbolKind::Class => lsp_types::SymbolKind::CLASS,
-            SymbolKind::Method => lsp_types::SymbolKind::METHOD,
-            SymbolKind::Property => lsp_types::SymbolKind::PROPERTY,
-            SymbolKind::Field => lsp_types::SymbolKind::FIELD,
-            SymbolKind::Constructor => lsp_types::SymbolKind::CONSTRUCTOR,
-            SymbolKind::Enum => lsp_types::SymbolKind::ENUM,
-            SymbolKind::Interface => lsp_types::SymbolKind::INTERFACE,
-            SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
-            SymbolKind::Variable => lsp_types::SymbolKind::VARIABLE,
-            SymbolKind::Constant => lsp_types::SymbolKind::CONSTANT,
-            SymbolKind::String => lsp_types::SymbolKind::STRING,
-            SymbolKind::Number => lsp_types::SymbolKind::NUMBER,
-            SymbolKind::Boolean => lsp_types::SymbolKind::BOOLEAN,
-            SymbolKind::Array => lsp_types::SymbolKind::ARRAY,
-            SymbolKind::Object => lsp_types::SymbolKind::OBJECT,
-            SymbolKind::Key => lsp_types::SymbolKind::KEY,
-            SymbolKind::Null => lsp_types::SymbolKind::NULL,
-            SymbolKind::EnumMember => lsp_types::SymbolKind::ENUM_MEMBER,
-            SymbolKind::Struct => lsp_types::SymbolKind::STRUCT,
-            SymbolKind::Event => lsp_types::SymbolKind::EVENT,
-            SymbolKind::Operator => lsp_types::SymbolKind::OPERATOR,
-            SymbolKind::TypeParameter => lsp_types::SymbolKind::TYPE_PARAMETER,
-        },
+        kind: lsp_symbol_kind_from_native(kind),
         tags: tags.map(|tags| {
             tags.into_iter()
                 .map(|tag| match tag {
@@ -156,3 +327,108 @@ pub(crate) fn lsp_document_symbol_from_native(
         deprecated: None,
     })
 }
+
+#[allow(deprecated)]
+pub(crate) fn lsp_symbol_information_from_native(
+    WorkspaceSymbol {
+        name,
+        kind,
+        container_name,
+        range,
+        ..
+    }: WorkspaceSymbol,
+    uri: lsp_types::Url,
+    line_index: &LineIndex,
+) -> Option<lsp_types::SymbolInformation> {
+    Some(lsp_types::SymbolInformation {
+        name,
+        kind: lsp_symbol_kind_from_native(kind),
+        tags: None,
+        deprecated: None,
+        location: lsp_types::Location {
+            uri,
+            range: lsp_range_from_text_range(range, line_index)?,
+        },
+        container_name,
+    })
+}
+
+fn lsp_symbol_kind_from_native(kind: SymbolKind) -> lsp_types::SymbolKind {
+    match kind {
+        SymbolKind::File => lsp_types::SymbolKind::FILE,
+        SymbolKind::Module => lsp_types::SymbolKind::MODULE,
+        SymbolKind::Namespace => lsp_types::SymbolKind::NAMESPACE,
+        SymbolKind::Package => lsp_types::SymbolKind::PACKAGE,
+        SymbolKind::Class => lsp_types::SymbolKind::CLASS,
+        SymbolKind::Method => lsp_types::SymbolKind::METHOD,
+        SymbolKind::Property => lsp_types::SymbolKind::PROPERTY,
+        SymbolKind::Field => lsp_types::SymbolKind::FIELD,
+        SymbolKind::Constructor => lsp_types::SymbolKind::CONSTRUCTOR,
+        SymbolKind::Enum => lsp_types::SymbolKind::ENUM,
+        SymbolKind::Interface => lsp_types::SymbolKind::INTERFACE,
+        SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+        SymbolKind::Variable => lsp_types::SymbolKind::VARIABLE,
+        SymbolKind::Constant => lsp_types::SymbolKind::CONSTANT,
+        SymbolKind::String => lsp_types::SymbolKind::STRING,
+        SymbolKind::Number => lsp_types::SymbolKind::NUMBER,
+        SymbolKind::Boolean => lsp_types::SymbolKind::BOOLEAN,
+        SymbolKind::Array => lsp_types::SymbolKind::ARRAY,
+        SymbolKind::Object => lsp_types::SymbolKind::OBJECT,
+        SymbolKind::Key => lsp_types::SymbolKind::KEY,
+        SymbolKind::Null => lsp_types::SymbolKind::NULL,
+        SymbolKind::EnumMember => lsp_types::SymbolKind::ENUM_MEMBER,
+        SymbolKind::Struct => lsp_types::SymbolKind::STRUCT,
+        SymbolKind::Event => lsp_types::SymbolKind::EVENT,
+        SymbolKind::Operator => lsp_types::SymbolKind::OPERATOR,
+        SymbolKind::TypeParameter => lsp_types::SymbolKind::TYPE_PARAMETER,
+    }
+}
+
+pub(crate) fn lsp_document_highlight_from_native(
+    highlight: DocumentHighlight,
+    line_index: &LineIndex,
+) -> Option<lsp_types::DocumentHighlight> {
+    Some(lsp_types::DocumentHighlight {
+        range: lsp_range_from_text_range(highlight.range, line_index)?,
+        kind: Some(match highlight.kind {
+            DocumentHighlightKind::Read => lsp_types::DocumentHighlightKind::READ,
+            DocumentHighlightKind::Write => lsp_types::DocumentHighlightKind::WRITE,
+        }),
+    })
+}
+
+pub(crate) fn lsp_folding_range_from_native(
+    fold: FoldingRange,
+    line_index: &LineIndex,
+) -> Option<lsp_types::FoldingRange> {
+    let range = lsp_range_from_text_range(fold.range, line_index)?;
+    (range.start.line < range.end.line).then(|| lsp_types::FoldingRange {
+        start_line: range.start.line,
+        end_line: range.end.line,
+        kind: Some(match fold.kind {
+            FoldingRangeKind::Comment => lsp_types::FoldingRangeKind::Comment,
+            FoldingRangeKind::Imports => lsp_types::FoldingRangeKind::Imports,
+            FoldingRangeKind::Region => lsp_types::FoldingRangeKind::Region,
+        }),
+        ..Default::default()
+    })
+}
+
+pub(crate) fn lsp_inlay_hint_from_native(
+    hint: InlayHint,
+    line_index: &LineIndex,
+) -> Option<lsp_types::InlayHint> {
+    Some(lsp_types::InlayHint {
+        position: lsp_position_from_text_size(hint.position, line_index)?,
+        label: lsp_types::InlayHintLabel::String(hint.label),
+        kind: Some(match hint.kind {
+            InlayHintKind::Type => lsp_types::InlayHintKind::TYPE,
+            InlayHintKind::Parameter => lsp_types::InlayHintKind::PARAMETER,
+        }),
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(hint.pad_left),
+        padding_right: Some(hint.pad_right),
+        data: None,
+    })
+}
@@ -1,8 +1,15 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::mem;
 
 use lsp_types::Diagnostic;
 use starpls_common::FileId;
+use starpls_ide::Cancellable;
+
+use crate::convert;
+use crate::server::ServerSnapshot;
 
 #[derive(Default)]
 pub(crate) struct DiagnosticsManager {
@@ -29,6 +36,19 @@ impl DiagnosticsManager {
         self.files_with_changed_diagnostics.push(file_id);
     }
 
+    /// Removes the diagnostics of the files for which `f` returns `false`, returning the IDs of
+    /// those files.
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(FileId) -> bool) -> Vec<FileId> {
+        let mut removed_file_ids = Vec::new();
+        self.diagnostics.retain(|file_id, _| {
+            f(*file_id) || {
+                removed_file_ids.push(*file_id);
+                false
+            }
+        });
+        removed_file_ids
+    }
+
     pub(crate) fn take_changes(&mut self) -> Vec<FileId> {
         mem::take(&mut self.files_with_changed_diagnostics)
     }
@@ -41,9 +61,153 @@ impl DiagnosticsManager {
     }
 }
 
+pub(crate) fn collect_diagnostics(
+    snapshot: &ServerSnapshot,
+    file_id: FileId,
+) -> Cancellable<Option<Vec<lsp_types::Diagnostic>>> {
+    let line_index = match snapshot.analysis_snapshot.line_index(file_id)? {
+        Some(line_index) => line_index,
+        None => return Ok(None),
+    };
+
+    // Get the diagnostics for the current path.
+    let diagnostics = snapshot.analysis_snapshot.diagnostics(file_id)?;
+
+    // Convert the diagnostics. This includes translating text offsets into `(line, column)` format.
+    Ok(Some(
+        diagnostics
+            .into_iter()
+            .flat_map(|diagnostic| convert::lsp_diagnostic_from_native(diagnostic, line_index))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// The diagnostics reported for a file when using pull diagnostics.
+#[derive(Debug, PartialEq)]
+pub(crate) enum PulledDiagnostics {
+    /// The diagnostics haven't changed since the client's previous result.
+    Unchanged(String),
+    Full(String, Vec<Diagnostic>),
+}
+
+/// Determines the diagnostics to report for a file when using pull diagnostics, given the
+/// revision of the analysis and the ID of the client's previous result, if any.
+///
+/// Result IDs consist of the analysis revision and a hash of the diagnostics. If the analysis
+/// hasn't changed since the previous result, the file is reported as unchanged without
+/// computing its diagnostics. Otherwise, it's only reported as unchanged if its diagnostics
+/// turn out to be the same.
+pub(crate) fn pull_diagnostics(
+    revision: u64,
+    previous_result_id: Option<&str>,
+    compute_diagnostics: impl FnOnce() -> Cancellable<Option<Vec<Diagnostic>>>,
+) -> Cancellable<Option<PulledDiagnostics>> {
+    let previous = previous_result_id.and_then(|result_id| result_id.split_once(':'));
+    if let (Some(result_id), Some((previous_revision, _))) = (previous_result_id, previous) {
+        if previous_revision == revision.to_string() {
+            return Ok(Some(PulledDiagnostics::Unchanged(result_id.to_string())));
+        }
+    }
+
+    let diagnostics = match compute_diagnostics()? {
+        Some(diagnostics) => diagnostics,
+        None => return Ok(None),
+    };
+    let hash = diagnostics_hash(&diagnostics);
+    let result_id = format!("{}:{}", revision, hash);
+    Ok(Some(match previous {
+        Some((_, previous_hash)) if previous_hash == hash => {
+            PulledDiagnostics::Unchanged(result_id)
+        }
+        _ => PulledDiagnostics::Full(result_id, diagnostics),
+    }))
+}
+
+fn diagnostics_hash(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    if let Ok(serialized) = serde_json::to_string(diagnostics) {
+        serialized.hash(&mut hasher);
+    }
+    format!("{:x}", hasher.finish())
+}
+
 fn is_diagnostic_equal(left: &Diagnostic, right: &Diagnostic) -> bool {
     left.source == right.source
         && left.severity == right.severity
         && left.range == right.range
         && left.message == right.message
 }
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+    use lsp_types::Range;
+
+    use super::*;
+
+    fn diagnostic(message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(0, 0), Position::new(0, 1)),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn pull(
+        revision: u64,
+        previous_result_id: Option<&str>,
+        diagnostics: Vec<Diagnostic>,
+    ) -> (PulledDiagnostics, bool) {
+        let mut is_computed = false;
+        let pulled = pull_diagnostics(revision, previous_result_id, || {
+            is_computed = true;
+            Ok(Some(diagnostics))
+        })
+        .unwrap()
+        .unwrap();
+        (pulled, is_computed)
+    }
+
+    fn result_id(pulled: &PulledDiagnostics) -> &str {
+        match pulled {
+            PulledDiagnostics::Unchanged(result_id) | PulledDiagnostics::Full(result_id, _) => {
+                result_id
+            }
+        }
+    }
+
+    #[test]
+    fn test_pull_diagnostics() {
+        let (first, is_computed) = pull(1, None, vec![diagnostic("foo")]);
+        assert!(is_computed);
+        assert_eq!(
+            first,
+            PulledDiagnostics::Full(result_id(&first).to_string(), vec![diagnostic("foo")])
+        );
+
+        // Nothing is computed if the analysis didn't change.
+        let (pulled, is_computed) = pull(1, Some(result_id(&first)), vec![diagnostic("bar")]);
+        assert!(!is_computed);
+        assert_eq!(
+            pulled,
+            PulledDiagnostics::Unchanged(result_id(&first).to_string())
+        );
+
+        // Diagnostics are recomputed after the analysis changed, but are still reported as
+        // unchanged if they are the same.
+        let (pulled, is_computed) = pull(2, Some(result_id(&first)), vec![diagnostic("foo")]);
+        assert!(is_computed);
+        assert!(matches!(pulled, PulledDiagnostics::Unchanged(_)));
+        assert!(result_id(&pulled).starts_with("2:"));
+
+        let (pulled, _) = pull(3, Some(result_id(&pulled)), vec![diagnostic("bar")]);
+        assert_eq!(
+            pulled,
+            PulledDiagnostics::Full(result_id(&pulled).to_string(), vec![diagnostic("bar")])
+        );
+
+        // Unknown result IDs are ignored.
+        let (pulled, _) = pull(3, Some("foo"), vec![diagnostic("bar")]);
+        assert!(matches!(pulled, PulledDiagnostics::Full(_, _)));
+    }
+}
@@ -1,5 +1,9 @@
 use std::panic;
+use std::path::PathBuf;
 
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use starpls_ide::CancellationFlag;
 use starpls_ide::Cancelled;
 
 use crate::event_loop::Task;
@@ -23,6 +27,33 @@ impl<'a> RequestDispatcher<'a> {
         &mut self,
         f: fn(&ServerSnapshot, R::Params) -> anyhow::Result<R::Result>,
     ) -> &mut Self
+    where
+        R: lsp_types::request::Request + 'static,
+        R::Params: serde::de::DeserializeOwned + Send + panic::UnwindSafe,
+    {
+        self.on_with_priority::<R>(f, false)
+    }
+
+    /// Like [`RequestDispatcher::on`], but evaluates the request on the threads reserved for
+    /// latency-sensitive requests, e.g. completions, so that typing stays responsive while
+    /// diagnostics are computed or external repositories are fetched. The request's type
+    /// inference also doesn't wait for type inference for diagnostics to finish.
+    pub(crate) fn on_latency_sensitive<R>(
+        &mut self,
+        f: fn(&ServerSnapshot, R::Params) -> anyhow::Result<R::Result>,
+    ) -> &mut Self
+    where
+        R: lsp_types::request::Request + 'static,
+        R::Params: serde::de::DeserializeOwned + Send + panic::UnwindSafe,
+    {
+        self.on_with_priority::<R>(f, true)
+    }
+
+    fn on_with_priority<R>(
+        &mut self,
+        f: fn(&ServerSnapshot, R::Params) -> anyhow::Result<R::Result>,
+        is_latency_sensitive: bool,
+    ) -> &mut Self
     where
         R: lsp_types::request::Request + 'static,
         R::Params: serde::de::DeserializeOwned + Send + panic::UnwindSafe,
@@ -32,8 +63,17 @@ impl<'a> RequestDispatcher<'a> {
             None => return self,
         };
 
-        let snapshot = self.server.snapshot();
-        self.server.task_pool_handle.spawn(move || {
+        let cancellation_flag = self.server.in_flight_requests.start(&req.id);
+        let snapshot = self.server.request_snapshot(
+            request_path(&req.params).as_deref(),
+            &cancellation_flag,
+            is_latency_sensitive,
+        );
+        let task = move || {
+            // Skip requests that were cancelled before we got to them.
+            if cancellation_flag.is_cancelled() {
+                return Task::Cancelled(req.id);
+            }
             let res = panic::catch_unwind(|| f(&snapshot, params));
             let response = match res {
                 Ok(res) => match res {
@@ -64,7 +104,13 @@ impl<'a> RequestDispatcher<'a> {
             };
 
             Task::ResponseReady(response)
-        });
+        };
+
+        if is_latency_sensitive {
+            self.server.task_pool_handle.spawn_latency_sensitive(task);
+        } else {
+            self.server.task_pool_handle.spawn(task);
+        }
 
         self
     }
@@ -101,3 +147,51 @@ impl<'a> RequestDispatcher<'a> {
         })
     }
 }
+
+/// Determines the path of the document that a request refers to, so that the request is
+/// evaluated in the workspace containing that document. Requests that aren't tied to a document,
+/// e.g. `workspace/symbol`, are evaluated across all workspaces instead.
+fn request_path(params: &serde_json::Value) -> Option<PathBuf> {
+    let uri = params
+        .pointer("/textDocument/uri")
+        // `callHierarchy/incomingCalls` and `callHierarchy/outgoingCalls`.
+        .or_else(|| params.pointer("/item/uri"))
+        // `completionItem/resolve`, see `CompletionResolveData`.
+        .or_else(|| params.pointer("/data/uri"))
+        // `workspace/executeCommand`, see `CopyLabelArgs`.
+        .or_else(|| params.pointer("/arguments/0/uri"))?
+        .as_str()?;
+    lsp_types::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+/// Tracks the requests being evaluated on the task pool, so that they can be cancelled with
+/// `$/cancelRequest`. Each request has its own [`CancellationFlag`], so cancelling a request
+/// doesn't affect any other requests.
+#[derive(Default)]
+pub(crate) struct InFlightRequests {
+    requests: Mutex<FxHashMap<lsp_server::RequestId, CancellationFlag>>,
+}
+
+impl InFlightRequests {
+    /// Marks the request as in flight, returning the flag used to cancel it.
+    fn start(&self, id: &lsp_server::RequestId) -> CancellationFlag {
+        let cancellation_flag = CancellationFlag::default();
+        self.requests
+            .lock()
+            .insert(id.clone(), cancellation_flag.clone());
+        cancellation_flag
+    }
+
+    /// Cancels the request. Requests that haven't started yet are skipped, while running
+    /// requests have their type inference aborted.
+    pub(crate) fn cancel(&self, id: &lsp_server::RequestId) {
+        if let Some(cancellation_flag) = self.requests.lock().get(id) {
+            cancellation_flag.cancel();
+        }
+    }
+
+    /// Forgets the given request once its response has been handled.
+    pub(crate) fn forget(&self, id: &lsp_server::RequestId) {
+        self.requests.lock().remove(id);
+    }
+}
@@ -11,6 +11,7 @@ use anyhow::anyhow;
 use anyhow::bail;
 use crossbeam_channel::Sender;
 use dashmap::DashMap;
+use dashmap::DashSet;
 use indexmap::IndexSet;
 use parking_lot::RwLock;
 use rustc_hash::FxHasher;
@@ -90,34 +91,48 @@ pub(crate) struct DocumentManager {
     has_closed_or_opened_documents: bool,
     changed_file_ids: Vec<(FileId, DocumentChangeKind)>,
     path_interner: Arc<PathInterner>,
-    workspace: PathBuf,
+    workspace_roots: Vec<PathBuf>,
 }
 
 impl DocumentManager {
-    pub(crate) fn new(path_interner: Arc<PathInterner>, workspace: PathBuf) -> Self {
+    pub(crate) fn new(path_interner: Arc<PathInterner>, workspace_roots: Vec<PathBuf>) -> Self {
         Self {
             documents: Default::default(),
             has_closed_or_opened_documents: false,
             changed_file_ids: Default::default(),
             path_interner,
-            workspace,
+            workspace_roots,
         }
     }
 
+    pub(crate) fn set_workspace_roots(&mut self, workspace_roots: Vec<PathBuf>) {
+        self.workspace_roots = workspace_roots;
+    }
+
     pub(crate) fn open(&mut self, path: PathBuf, version: i32, contents: String) {
         // Create/update the document with the given contents.
         self.has_closed_or_opened_documents = true;
-        let (dialect, info) =
-            match dialect_and_api_context_for_workspace_path(&self.workspace, &path) {
-                Some((dialect, api_context)) => (
-                    dialect,
-                    api_context.map(|api_context| FileInfo::Bazel {
-                        api_context,
-                        is_external: !path.starts_with(&self.workspace),
-                    }),
-                ),
-                None => return,
-            };
+
+        // Documents are resolved against the innermost workspace that contains them, since
+        // workspaces can be nested.
+        let workspace = self
+            .workspace_roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        let (dialect, info) = match dialect_and_api_context_for_workspace_path(
+            workspace.map(PathBuf::as_path).unwrap_or(Path::new("")),
+            &path,
+        ) {
+            Some((dialect, api_context)) => (
+                dialect,
+                api_context.map(|api_context| FileInfo::Bazel {
+                    api_context,
+                    is_external: workspace.is_none(),
+                }),
+            ),
+            None => return,
+        };
         let file_id = self.path_interner.intern_path(path);
         self.documents.insert(
             file_id,
@@ -145,6 +160,17 @@ impl DocumentManager {
         };
     }
 
+    /// Removes a document that isn't open in the editor, e.g. after it was deleted from disk.
+    pub(crate) fn remove(&mut self, file_id: FileId) {
+        if let Some(Document {
+            source: DocumentSource::Disk,
+            ..
+        }) = self.documents.get(&file_id)
+        {
+            self.documents.remove(&file_id);
+        }
+    }
+
     pub(crate) fn take_changes(&mut self) -> (bool, Vec<(FileId, DocumentChangeKind)>) {
         let changed_documents = mem::take(&mut self.changed_file_ids);
         let has_opened_or_closed_documents = self.has_closed_or_opened_documents;
@@ -156,6 +182,10 @@ impl DocumentManager {
         self.documents.get(&file_id)
     }
 
+    pub(crate) fn file_ids(&self) -> impl Iterator<Item = FileId> + '_ {
+        self.documents.keys().copied()
+    }
+
     pub(crate) fn lookup_by_file_id(&self, file_id: FileId) -> PathBuf {
         self.path_interner.lookup_by_file_id(file_id)
     }
@@ -163,6 +193,10 @@ impl DocumentManager {
     pub(crate) fn lookup_by_path_buf(&self, path: &PathBuf) -> Option<FileId> {
         self.path_interner.lookup_by_path_buf(path)
     }
+
+    pub(crate) fn intern_path(&self, path: PathBuf) -> FileId {
+        self.path_interner.intern_path(path)
+    }
 }
 
 #[derive(Default, Debug)]
@@ -199,6 +233,10 @@ pub(crate) struct DefaultFileLoader {
     workspace_name: Option<String>,
     external_output_base: PathBuf,
     cached_load_results: DashMap<String, PathBuf>,
+    /// The package directory of each directory looked up by [`FileLoader::label_for_file`], or
+    /// `None` if the directory isn't part of a package.
+    cached_package_dirs: DashMap<PathBuf, Option<PathBuf>>,
+    loaded_files: DashSet<FileId>,
     fetch_repo_sender: Sender<Task>,
     bzlmod_enabled: bool,
 }
@@ -220,11 +258,32 @@ impl DefaultFileLoader {
             workspace_name,
             external_output_base,
             cached_load_results: Default::default(),
+            cached_package_dirs: Default::default(),
+            loaded_files: Default::default(),
             fetch_repo_sender,
             bzlmod_enabled,
         }
     }
 
+    /// Clears the cached load resolutions and package directories, e.g. after files were
+    /// created or deleted on disk.
+    pub(crate) fn clear_cached_load_results(&self) {
+        self.cached_load_results.clear();
+        self.cached_package_dirs.clear();
+    }
+
+    /// Marks the given file as not loaded, so that its contents are read from disk again the
+    /// next time it is loaded.
+    pub(crate) fn unload_file(&self, file_id: FileId) {
+        self.loaded_files.remove(&file_id);
+    }
+
+    /// Marks the given file as loaded, e.g. because its contents were read from disk while
+    /// walking the workspace. Returns `false` if the file was already loaded.
+    pub(crate) fn mark_loaded(&self, file_id: FileId) -> bool {
+        self.loaded_files.insert(file_id)
+    }
+
     fn make_cache_key(&self, repo_kind: &RepoKind, path: &str, from: FileId) -> String {
         format!("{:?}-{:?}-{:?}", repo_kind, path, from.0)
     }
@@ -246,6 +305,30 @@ impl DefaultFileLoader {
         let key = self.make_cache_key(repo_kind, path, from);
         self.cached_load_results.insert(key, resolved_path);
     }
+
+    /// Returns the package directory of the given directory, i.e. the closest ancestor within
+    /// `root` that contains a BUILD file. Lookups are cached per directory, since they probe the
+    /// disk.
+    fn package_dir(&self, dir: &Path, root: &Path) -> Option<PathBuf> {
+        if let Some(package_dir) = self.cached_package_dirs.get(dir) {
+            return package_dir.clone();
+        }
+
+        let package_dir = if !dir.starts_with(root) {
+            None
+        } else if ["BUILD", "BUILD.bazel"]
+            .iter()
+            .any(|name| dir.join(name).is_file())
+        {
+            Some(dir.to_path_buf())
+        } else {
+            dir.parent()
+                .and_then(|parent| self.package_dir(parent, root))
+        };
+        self.cached_package_dirs
+            .insert(dir.to_path_buf(), package_dir.clone());
+        package_dir
+    }
 }
 
 struct ResolvedLabel {
@@ -329,38 +412,41 @@ impl DefaultFileLoader {
         from: FileId,
         fetch_repo_on_err: Option<String>,
     ) -> anyhow::Result<(FileId, Option<String>)> {
-        // If we've already interned this file, then simply return the file id.
-        let (file_id, contents) = match self.interner.lookup_by_path_buf(&path) {
-            Some(file_id) => (file_id, None),
-            None => {
-                let contents = match fs::read_to_string(&path) {
-                    Ok(contents) => contents,
-                    Err(err) => {
-                        if let Some(canonical_repo) = fetch_repo_on_err {
-                            if !self
-                                .external_output_base
-                                .join(&canonical_repo)
-                                .try_exists()
-                                .ok()
-                                .unwrap_or_default()
-                            {
-                                let _ = self.fetch_repo_sender.send(
-                                    Task::FetchExternalRepoRequest(FetchExternalRepoRequest {
-                                        file_id: from,
-                                        repo: canonical_repo,
-                                    }),
-                                );
-                            }
-                        }
-                        return Err(err.into());
-                    }
-                };
+        // If we've already loaded this file, then simply return the file id. Files can also be
+        // interned without being loaded, e.g. by the symbol indexer, in which case we still need
+        // to read their contents.
+        if let Some(file_id) = self.interner.lookup_by_path_buf(&path) {
+            if self.loaded_files.contains(&file_id) {
+                return Ok((file_id, None));
+            }
+        }
 
-                (self.interner.intern_path(path), Some(contents))
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                if let Some(canonical_repo) = fetch_repo_on_err {
+                    if !self
+                        .external_output_base
+                        .join(&canonical_repo)
+                        .try_exists()
+                        .ok()
+                        .unwrap_or_default()
+                    {
+                        let _ = self.fetch_repo_sender.send(Task::FetchExternalRepoRequest(
+                            FetchExternalRepoRequest {
+                                file_id: from,
+                                repo: canonical_repo,
+                            },
+                        ));
+                    }
+                }
+                return Err(err.into());
             }
         };
 
-        Ok((file_id, contents))
+        let file_id = self.interner.intern_path(path);
+        self.loaded_files.insert(file_id);
+        Ok((file_id, Some(contents)))
     }
 
     fn repo_for_path<'a>(&'a self, path: &'a Path) -> Option<&str> {
@@ -399,6 +485,10 @@ impl FileLoader for DefaultFileLoader {
         };
 
         let resolved_label = try_opt!(self.resolve_label(&label, from)?);
+        let canonical_repo = match label.kind() {
+            RepoKind::Apparent if self.bzlmod_enabled => resolved_label.canonical_repo.clone(),
+            _ => None,
+        };
         let res = if fs::metadata(&resolved_label.resolved_path)
             .ok()
             .map(|metadata| metadata.is_file())
@@ -406,6 +496,7 @@ impl FileLoader for DefaultFileLoader {
         {
             ResolvedPath::Source {
                 path: resolved_label.resolved_path,
+                canonical_repo,
             }
         } else {
             if label.target().is_empty() {
@@ -430,6 +521,7 @@ impl FileLoader for DefaultFileLoader {
                 build_file,
                 target: label.target().to_string(),
                 contents,
+                canonical_repo,
             }
         };
 
@@ -653,6 +745,12 @@ impl FileLoader for DefaultFileLoader {
         }
     }
 
+    fn read_file(&self, file_id: FileId) -> anyhow::Result<Option<String>> {
+        let contents = fs::read_to_string(self.interner.lookup_by_file_id(file_id))?;
+        self.loaded_files.insert(file_id);
+        Ok(Some(contents))
+    }
+
     fn resolve_build_file(&self, file_id: FileId) -> Option<String> {
         let path = self.interner.lookup_by_file_id(file_id);
         let path = path.strip_prefix(&self.workspace).ok()?;
@@ -665,6 +763,58 @@ impl FileLoader for DefaultFileLoader {
             None
         }
     }
+
+    fn label_for_file(&self, file_id: FileId, from: FileId) -> Option<String> {
+        let path = self.interner.lookup_by_file_id(file_id);
+        let repo = self.repo_for_path(&path)?;
+        let root = if repo.is_empty() {
+            self.workspace.clone()
+        } else {
+            self.external_output_base.join(repo)
+        };
+
+        let package_dir = self.package_dir(path.parent()?, &root)?;
+        let package = label_path(package_dir.strip_prefix(&root).ok()?)?;
+        let target = label_path(path.strip_prefix(&package_dir).ok()?)?;
+
+        let from_path = self.interner.lookup_by_file_id(from);
+        let from_repo = self.repo_for_path(&from_path);
+        let repo = if from_repo == Some(repo) {
+            String::new()
+        } else if repo.is_empty() {
+            "@".to_string()
+        } else if self.bzlmod_enabled {
+            // Prefer the apparent repository name visible from the loading file, falling back to
+            // the canonical name otherwise.
+            from_repo
+                .and_then(|from_repo| {
+                    let apparent_repos = self.bazel_client.repo_mapping_keys(from_repo).ok()?;
+                    apparent_repos.into_iter().find(|apparent_repo| {
+                        self.bazel_client
+                            .resolve_repo_from_mapping(apparent_repo, from_repo)
+                            .ok()
+                            .flatten()
+                            .as_deref()
+                            == Some(repo)
+                    })
+                })
+                .map(|apparent_repo| format!("@{}", apparent_repo))
+                .unwrap_or_else(|| format!("@@{}", repo))
+        } else {
+            format!("@{}", repo)
+        };
+
+        Some(format!("{}//{}:{}", repo, package, target))
+    }
+}
+
+fn label_path(path: &Path) -> Option<String> {
+    Some(
+        path.components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()?
+            .join("/"),
+    )
 }
 
 fn read_dir_packages_and_targets(
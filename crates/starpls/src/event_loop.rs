@@ -1,21 +1,28 @@
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+
 use crossbeam_channel::select;
 use log::debug;
+use log::error;
 use lsp_server::Connection;
 use lsp_types::InitializeParams;
 use lsp_types::WorkDoneProgressCreateParams;
 use rustc_hash::FxHashSet;
 use starpls_common::FileId;
 
+use crate::bazel::BazelContext;
 use crate::commands::server::ServerCommand;
+use crate::config::ClientSettings;
 use crate::config::ServerConfig;
-use crate::convert;
+use crate::diagnostics::collect_diagnostics;
 use crate::dispatcher::RequestDispatcher;
 use crate::document::DocumentSource;
 use crate::extensions;
 use crate::handlers::notifications;
 use crate::handlers::requests;
 use crate::server::Server;
-use crate::server::ServerSnapshot;
+use crate::workspace::WorkspaceFile;
 
 #[macro_export]
 macro_rules! match_notification {
@@ -48,6 +55,27 @@ pub(crate) enum RefreshAllWorkspaceTargetsProgress {
     End(Option<Vec<String>>),
 }
 
+#[derive(Debug)]
+pub(crate) enum RefreshBazelContextProgress {
+    Begin,
+    /// Contains the new Bazel configuration if it differs from the cached one.
+    End(Option<BazelContext>),
+}
+
+#[derive(Debug)]
+pub(crate) enum AnalyzeWorkspaceProgress {
+    /// The Starlark files in the workspace folder were read and are ready for analysis.
+    FilesLoaded(Vec<WorkspaceFile>),
+    /// Diagnostics were collected for a batch of files, with the number of analyzed files and
+    /// the files of the batch that still need to be analyzed. These are left over if collecting
+    /// was cancelled, e.g. by an edit.
+    Report(
+        usize,
+        Vec<(FileId, Vec<lsp_types::Diagnostic>)>,
+        Vec<FileId>,
+    ),
+}
+
 #[derive(Debug)]
 pub(crate) enum Task {
     AnalysisRequested(Vec<FileId>),
@@ -57,12 +85,18 @@ pub(crate) enum Task {
     ResponseReady(lsp_server::Response),
     /// Retry a previously failed request (e.g. due to Salsa cancellation).
     Retry(lsp_server::Request),
-    /// Events from fetching external repositories.
-    FetchExternalRepos(FetchExternalReposProgress),
+    /// A request was cancelled by the client before it was evaluated.
+    Cancelled(lsp_server::RequestId),
+    /// Events from fetching external repositories for the given workspace folder.
+    FetchExternalRepos(PathBuf, FetchExternalReposProgress),
     /// A request to fetch an external repository.
     FetchExternalRepoRequest(FetchExternalRepoRequest),
-    /// Events from refreshing targets for the current workspace.
-    RefreshAllWorkspaceTargets(RefreshAllWorkspaceTargetsProgress),
+    /// Events from refreshing targets for the given workspace folder.
+    RefreshAllWorkspaceTargets(PathBuf, RefreshAllWorkspaceTargetsProgress),
+    /// Events from the given run of analyzing all files in the given workspace folder.
+    AnalyzeWorkspace(PathBuf, u64, AnalyzeWorkspaceProgress),
+    /// Events from refreshing the cached Bazel configuration for the given workspace folder.
+    RefreshBazelContext(PathBuf, RefreshBazelContextProgress),
 }
 
 #[derive(Debug)]
@@ -77,14 +111,41 @@ pub fn process_connection(
     initialize_params: InitializeParams,
 ) -> anyhow::Result<()> {
     debug!("initializing state and starting event loop");
-    let config = ServerConfig {
+    let mut config = ServerConfig {
         args,
         caps: initialize_params.capabilities,
     };
-    let server = Server::new(connection, config)?;
+    if let Some(options) = initialize_params.initialization_options {
+        match ClientSettings::from_json(options) {
+            Ok(settings) => config.apply_settings(settings),
+            Err(err) => error!("failed to parse initialization options: {}", err),
+        }
+    }
+    let workspace_folders = workspace_folders(&initialize_params.workspace_folders)?;
+    let mut server = Server::new(connection, config, workspace_folders)?;
+
+    // The `initialized` notification was already consumed while initializing the connection.
+    server.initialized();
     server.run()
 }
 
+/// Determines the workspace folders opened by the client, falling back to the current working
+/// directory if the client didn't specify any.
+fn workspace_folders(
+    workspace_folders: &Option<Vec<lsp_types::WorkspaceFolder>>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let folders = workspace_folders
+        .iter()
+        .flatten()
+        .filter_map(|folder| folder.uri.to_file_path().ok())
+        .collect::<Vec<_>>();
+    if folders.is_empty() {
+        Ok(vec![env::current_dir()?])
+    } else {
+        Ok(folders)
+    }
+}
+
 impl Server {
     fn run(mut self) -> anyhow::Result<()> {
         while let Some(event) = self.next_event() {
@@ -127,14 +188,28 @@ impl Server {
             }
         };
 
-        if !self.pending_repos.is_empty() && !self.is_fetching_repos {
-            self.fetch_bazel_external_repos();
-        }
+        self.fetch_bazel_external_repos();
 
         // Update our diagnostics if a triggering event (e.g. document open/close/change) occured.
         // This is done asynchronously, so any new diagnostics resulting from this won't be seen until the next turn
         // of the event loop.
         let (changed_file_ids, should_request_analysis) = self.process_changes();
+
+        // Clients that pull diagnostics request them for edited documents themselves, so we
+        // skip computing diagnostics here. They are only told to pull again if diagnostics may
+        // have changed for other reasons, e.g. after fetching external repositories.
+        if self.config.has_pull_diagnostics_support() {
+            let has_forced_analysis = !self.force_analysis_for_files.is_empty();
+            self.force_analysis_for_files.clear();
+            let has_changed_diagnostics = !self.diagnostics_manager.take_changes().is_empty();
+            if (has_forced_analysis || has_changed_diagnostics)
+                && self.config.has_diagnostic_refresh_support()
+            {
+                self.send_request::<lsp_types::request::WorkspaceDiagnosticRefresh>(());
+            }
+            return Ok(());
+        }
+
         let mut files_to_update = Vec::new();
         if should_request_analysis {
             self.analysis_requested_for_files = None;
@@ -149,17 +224,19 @@ impl Server {
         if !files_to_update.is_empty() {
             self.update_diagnostics(files_to_update);
         }
+        self.collect_workspace_diagnostics();
 
         let changed_file_ids = self.diagnostics_manager.take_changes();
-
         for file_id in changed_file_ids {
             let document_manager = self.document_manager.read();
-            // Only send diagnostics for currently open editors.
+            // Only send diagnostics for currently open editors, unless we're analyzing the
+            // whole workspace.
             let version = match document_manager
                 .get(file_id)
                 .map(|document| document.source)
             {
-                Some(DocumentSource::Editor(version)) => version,
+                Some(DocumentSource::Editor(version)) => Some(version),
+                _ if self.config.args.enable_workspace_diagnostics => None,
                 _ => continue,
             };
             let diagnostics = self
@@ -176,7 +253,7 @@ impl Server {
                 lsp_types::PublishDiagnosticsParams {
                     uri,
                     diagnostics,
-                    version: Some(version),
+                    version,
                 },
             );
         }
@@ -185,17 +262,32 @@ impl Server {
     }
 
     fn update_diagnostics(&mut self, file_ids: Vec<FileId>) {
-        let snapshot = self.snapshot();
+        // Each file's diagnostics are computed by the workspace that contains it.
+        let mut file_ids_by_workspace = vec![Vec::new(); self.workspaces.len()];
+        for file_id in file_ids {
+            let path = self.path_interner.lookup_by_file_id(file_id);
+            file_ids_by_workspace[self.workspace_index(&path)].push(file_id);
+        }
+
+        let snapshots = file_ids_by_workspace
+            .into_iter()
+            .zip(&self.workspaces)
+            .filter(|(file_ids, _)| !file_ids.is_empty())
+            .map(|(file_ids, workspace)| (self.workspace_snapshot(workspace), file_ids))
+            .collect::<Vec<_>>();
         self.task_pool_handle.spawn(move || {
             let mut res = Vec::new();
 
             // Query the database for diagnostics for each file and convert them to an LSP-compatible format.
-            for file_id in file_ids {
-                let diagnostics = match collect_diagnostics(&snapshot, file_id) {
-                    Some(diagnositcs) => diagnositcs,
-                    None => continue,
-                };
-                res.push((file_id, diagnostics));
+            // If the operation was cancelled, simply continue to the next file.
+            for (snapshot, file_ids) in snapshots {
+                for file_id in file_ids {
+                    let diagnostics = match collect_diagnostics(&snapshot, file_id) {
+                        Ok(Some(diagnositcs)) => diagnositcs,
+                        _ => continue,
+                    };
+                    res.push((file_id, diagnostics));
+                }
             }
 
             Task::DiagnosticsReady(res)
@@ -211,22 +303,57 @@ impl Server {
         RequestDispatcher::new(req, self)
             .on::<extensions::ShowSyntaxTree>(requests::show_syntax_tree)
             .on::<extensions::ShowHir>(requests::show_hir)
-            .on::<lsp_types::request::Completion>(requests::completion)
+            .on::<lsp_types::request::CallHierarchyIncomingCalls>(
+                requests::call_hierarchy_incoming_calls,
+            )
+            .on::<lsp_types::request::CallHierarchyOutgoingCalls>(
+                requests::call_hierarchy_outgoing_calls,
+            )
+            .on::<lsp_types::request::CallHierarchyPrepare>(requests::prepare_call_hierarchy)
+            .on::<lsp_types::request::CodeActionRequest>(requests::code_action)
+            .on::<lsp_types::request::CodeLensRequest>(requests::code_lens)
+            .on_latency_sensitive::<lsp_types::request::Completion>(requests::completion)
+            .on_latency_sensitive::<lsp_types::request::ResolveCompletionItem>(
+                requests::completion_resolve,
+            )
+            .on::<lsp_types::request::DocumentDiagnosticRequest>(requests::document_diagnostic)
+            .on::<lsp_types::request::DocumentHighlightRequest>(requests::document_highlight)
+            .on::<lsp_types::request::DocumentLinkRequest>(requests::document_link)
             .on::<lsp_types::request::DocumentSymbolRequest>(requests::document_symbols)
+            .on::<lsp_types::request::ExecuteCommand>(requests::execute_command)
+            .on::<lsp_types::request::FoldingRangeRequest>(requests::folding_range)
+            .on::<lsp_types::request::Formatting>(requests::formatting)
             .on::<lsp_types::request::GotoDefinition>(requests::goto_definition)
-            .on::<lsp_types::request::HoverRequest>(requests::hover)
+            .on::<lsp_types::request::GotoImplementation>(requests::goto_implementation)
+            .on::<lsp_types::request::GotoTypeDefinition>(requests::goto_type_definition)
+            .on_latency_sensitive::<lsp_types::request::HoverRequest>(requests::hover)
+            .on::<lsp_types::request::InlayHintRequest>(requests::inlay_hints)
+            .on::<lsp_types::request::PrepareRenameRequest>(requests::prepare_rename)
+            .on::<lsp_types::request::RangeFormatting>(requests::range_formatting)
             .on::<lsp_types::request::References>(requests::find_references)
-            .on::<lsp_types::request::SignatureHelpRequest>(requests::signature_help)
+            .on::<lsp_types::request::Rename>(requests::rename)
+            .on::<lsp_types::request::SelectionRangeRequest>(requests::selection_range)
+            .on::<lsp_types::request::SemanticTokensFullRequest>(requests::semantic_tokens_full)
+            .on::<lsp_types::request::SemanticTokensRangeRequest>(requests::semantic_tokens_range)
+            .on_latency_sensitive::<lsp_types::request::SignatureHelpRequest>(
+                requests::signature_help,
+            )
+            .on::<lsp_types::request::WorkspaceDiagnosticRequest>(requests::workspace_diagnostic)
+            .on::<lsp_types::request::WorkspaceSymbolRequest>(requests::workspace_symbol)
             .finish();
     }
 
     fn handle_notification(&mut self, not: lsp_server::Notification) -> anyhow::Result<()> {
         match_notification! {
             match not {
+                if lsp_types::notification::Cancel as params => notifications::cancel_request(self, params),
                 if lsp_types::notification::DidOpenTextDocument as params => notifications::did_open_text_document(self, params),
                 if lsp_types::notification::DidCloseTextDocument as params => notifications::did_close_text_document(self, params),
                 if lsp_types::notification::DidChangeTextDocument as params => notifications::did_change_text_document(self, params),
                 if lsp_types::notification::DidSaveTextDocument as params => notifications::did_save_text_document(self, params),
+                if lsp_types::notification::DidChangeConfiguration as params => notifications::did_change_configuration(self, params),
+                if lsp_types::notification::DidChangeWatchedFiles as params => notifications::did_change_watched_files(self, params),
+                if lsp_types::notification::DidChangeWorkspaceFolders as params => notifications::did_change_workspace_folders(self, params),
                 _ => Ok(())
             }
         }
@@ -244,9 +371,17 @@ impl Server {
             Task::ResponseReady(resp) => {
                 self.respond(resp);
             }
-            Task::Retry(req) => self.handle_request(req),
-            Task::FetchExternalRepos(progress) => {
-                let token = "FetchExternalRepos".to_string();
+            Task::Retry(req) => {
+                // Don't retry requests that were cancelled by the client in the meantime.
+                if self.req_queue.incoming.is_completed(&req.id) {
+                    self.in_flight_requests.forget(&req.id);
+                } else {
+                    self.handle_request(req);
+                }
+            }
+            Task::Cancelled(id) => self.in_flight_requests.forget(&id),
+            Task::FetchExternalRepos(folder, progress) => {
+                let token = format!("FetchExternalRepos:{}", folder.display());
                 let work_done = match progress {
                     FetchExternalReposProgress::Begin(repos) => {
                         self.send_request::<lsp_types::request::WorkDoneProgressCreate>(
@@ -274,14 +409,20 @@ impl Server {
                         })
                     }
                     FetchExternalReposProgress::End(files, failed_repos) => {
-                        self.is_fetching_repos = false;
+                        let bzlmod_enabled = match self.workspace_for_folder_mut(&folder) {
+                            Some(workspace) => {
+                                workspace.is_fetching_repos = false;
+                                workspace.bzlmod_enabled
+                            }
+                            None => false,
+                        };
                         self.force_analysis_for_files.extend(files);
 
                         // Fetching external repositories with `bazel query`, as in the case when bzlmod is disabled, often
                         // results in a non-zero exit code because of errors that we don't really care about. Therefore, to
                         // avoid noise, we only send an error message when fetching with `bazel fetch`, which is the case
                         // when bzlmod is enabled.
-                        if !failed_repos.is_empty() && self.bzlmod_enabled {
+                        if !failed_repos.is_empty() && bzlmod_enabled {
                             self.send_error_message(&format!(
                                 "Failed to fetch external repositories: {}. Please check the server logs for more details.",
                                 failed_repos.join(", ")
@@ -302,18 +443,56 @@ impl Server {
                 );
             }
             Task::FetchExternalRepoRequest(FetchExternalRepoRequest { file_id, repo }) => {
-                if !self.fetched_repos.contains(&repo) {
-                    self.pending_repos.insert(repo);
-                    self.pending_files.insert(file_id);
+                let path = self.path_interner.lookup_by_file_id(file_id);
+                let workspace = self.workspace_for_path_mut(&path);
+                if !workspace.fetched_repos.contains(&repo) {
+                    workspace.pending_repos.insert(repo);
+                    workspace.pending_files.insert(file_id);
                 }
             }
-            Task::RefreshAllWorkspaceTargets(progress) => {
-                let token = "RefreshAllWorkspaceTargets";
+            Task::RefreshBazelContext(folder, progress) => {
+                let token = format!("RefreshBazelContext:{}", folder.display());
+                let work_done = match progress {
+                    RefreshBazelContextProgress::Begin => {
+                        self.send_request::<lsp_types::request::WorkDoneProgressCreate>(
+                            WorkDoneProgressCreateParams {
+                                token: lsp_types::NumberOrString::String(token.clone()),
+                            },
+                        );
+
+                        lsp_types::WorkDoneProgress::Begin(lsp_types::WorkDoneProgressBegin {
+                            title: "Refreshing Bazel configuration".to_string(),
+                            ..Default::default()
+                        })
+                    }
+                    RefreshBazelContextProgress::End(bazel_cx) => {
+                        if let Some(bazel_cx) = bazel_cx {
+                            self.update_bazel_context(&folder, bazel_cx);
+                        }
+
+                        lsp_types::WorkDoneProgress::End(lsp_types::WorkDoneProgressEnd {
+                            message: None,
+                        })
+                    }
+                };
+
+                self.send_notification::<lsp_types::notification::Progress>(
+                    lsp_types::ProgressParams {
+                        token: lsp_types::NumberOrString::String(token),
+                        value: lsp_types::ProgressParamsValue::WorkDone(work_done),
+                    },
+                );
+            }
+            Task::AnalyzeWorkspace(folder, id, progress) => {
+                self.handle_analyze_workspace_progress(folder, id, progress)
+            }
+            Task::RefreshAllWorkspaceTargets(folder, progress) => {
+                let token = format!("RefreshAllWorkspaceTargets:{}", folder.display());
                 let work_done = match progress {
                     RefreshAllWorkspaceTargetsProgress::Begin => {
                         self.send_request::<lsp_types::request::WorkDoneProgressCreate>(
                             WorkDoneProgressCreateParams {
-                                token: lsp_types::NumberOrString::String(token.to_string()),
+                                token: lsp_types::NumberOrString::String(token.clone()),
                             },
                         );
 
@@ -323,9 +502,11 @@ impl Server {
                         })
                     }
                     RefreshAllWorkspaceTargetsProgress::End(targets) => {
-                        self.is_refreshing_all_workspace_targets = false;
-                        if let Some(targets) = targets {
-                            self.analysis.set_all_workspace_targets(targets);
+                        if let Some(workspace) = self.workspace_for_folder_mut(&folder) {
+                            workspace.is_refreshing_all_workspace_targets = false;
+                            if let Some(targets) = targets {
+                                workspace.analysis.set_all_workspace_targets(targets);
+                            }
                         }
 
                         lsp_types::WorkDoneProgress::End(lsp_types::WorkDoneProgressEnd {
@@ -336,7 +517,7 @@ impl Server {
 
                 self.send_notification::<lsp_types::notification::Progress>(
                     lsp_types::ProgressParams {
-                        token: lsp_types::NumberOrString::String(token.to_string()),
+                        token: lsp_types::NumberOrString::String(token),
                         value: lsp_types::ProgressParamsValue::WorkDone(work_done),
                     },
                 );
@@ -344,13 +525,138 @@ impl Server {
         }
     }
 
+    fn handle_analyze_workspace_progress(
+        &mut self,
+        folder: PathBuf,
+        id: u64,
+        progress: AnalyzeWorkspaceProgress,
+    ) {
+        // Drop events from runs that were cancelled in the meantime.
+        let Some(analysis) = self
+            .workspace_for_folder_mut(&folder)
+            .and_then(|workspace| workspace.workspace_analysis.as_ref())
+            .filter(|analysis| analysis.id == id)
+        else {
+            return;
+        };
+        let token = analysis.progress_token(&folder);
+
+        let work_done = match progress {
+            AnalyzeWorkspaceProgress::FilesLoaded(files) => {
+                let num_files = self.add_workspace_files(&folder, files);
+                if num_files == 0 {
+                    self.end_workspace_analysis(&folder, 0)
+                } else {
+                    workspace_analysis_report(0, num_files)
+                }
+            }
+            AnalyzeWorkspaceProgress::Report(num_analyzed_files, diagnostics, remaining_files) => {
+                for (file_id, diagnostics) in diagnostics {
+                    self.diagnostics_manager
+                        .set_diagnostics(file_id, diagnostics);
+                }
+                let Some(analysis) = self
+                    .workspace_for_folder_mut(&folder)
+                    .and_then(|workspace| workspace.workspace_analysis.as_mut())
+                else {
+                    return;
+                };
+                analysis.is_collecting_diagnostics = false;
+                analysis.num_analyzed_files += num_analyzed_files;
+                analysis.pending_files.extend(remaining_files);
+                if analysis.pending_files.is_empty() {
+                    let num_files = analysis.num_files;
+                    self.end_workspace_analysis(&folder, num_files)
+                } else {
+                    workspace_analysis_report(analysis.num_analyzed_files, analysis.num_files)
+                }
+            }
+        };
+
+        self.send_notification::<lsp_types::notification::Progress>(lsp_types::ProgressParams {
+            token: lsp_types::NumberOrString::String(token),
+            value: lsp_types::ProgressParamsValue::WorkDone(work_done),
+        });
+    }
+
+    /// Sends the beginning of the progress for the current run of analyzing the given
+    /// workspace.
+    pub(crate) fn begin_workspace_analysis(&mut self, index: usize) {
+        let workspace = &self.workspaces[index];
+        let Some(analysis) = &workspace.workspace_analysis else {
+            return;
+        };
+        let token = analysis.progress_token(&workspace.folder);
+
+        self.send_request::<lsp_types::request::WorkDoneProgressCreate>(
+            WorkDoneProgressCreateParams {
+                token: lsp_types::NumberOrString::String(token.clone()),
+            },
+        );
+        self.send_notification::<lsp_types::notification::Progress>(lsp_types::ProgressParams {
+            token: lsp_types::NumberOrString::String(token),
+            value: lsp_types::ProgressParamsValue::WorkDone(lsp_types::WorkDoneProgress::Begin(
+                lsp_types::WorkDoneProgressBegin {
+                    title: "Analyzing workspace".to_string(),
+                    message: Some("Loading files".to_string()),
+                    percentage: Some(0),
+                    ..Default::default()
+                },
+            )),
+        });
+    }
+
+    /// Cancels the current run of analyzing the given workspace, if any, and ends its progress.
+    pub(crate) fn cancel_workspace_analysis(&mut self, index: usize) {
+        let workspace = &mut self.workspaces[index];
+        let Some(analysis) = workspace.workspace_analysis.take() else {
+            return;
+        };
+        analysis.cancellation_flag.cancel();
+        let token = analysis.progress_token(&workspace.folder);
+
+        self.send_notification::<lsp_types::notification::Progress>(lsp_types::ProgressParams {
+            token: lsp_types::NumberOrString::String(token),
+            value: lsp_types::ProgressParamsValue::WorkDone(lsp_types::WorkDoneProgress::End(
+                lsp_types::WorkDoneProgressEnd {
+                    message: Some("Cancelled".to_string()),
+                },
+            )),
+        });
+    }
+
+    fn end_workspace_analysis(
+        &mut self,
+        folder: &Path,
+        num_files: usize,
+    ) -> lsp_types::WorkDoneProgress {
+        if let Some(workspace) = self.workspace_for_folder_mut(folder) {
+            workspace.workspace_analysis = None;
+        }
+        lsp_types::WorkDoneProgress::End(lsp_types::WorkDoneProgressEnd {
+            message: Some(format!("Analyzed {} files", num_files)),
+        })
+    }
+
     fn respond(&mut self, resp: lsp_server::Response) {
+        self.in_flight_requests.forget(&resp.id);
         if self.req_queue.incoming.complete(resp.id.clone()).is_some() {
             self.connection.sender.send(resp.into()).unwrap();
         }
     }
 }
 
+fn workspace_analysis_report(
+    num_analyzed_files: usize,
+    num_files: usize,
+) -> lsp_types::WorkDoneProgress {
+    lsp_types::WorkDoneProgress::Report(lsp_types::WorkDoneProgressReport {
+        message: Some(format!("{}/{} files", num_analyzed_files, num_files)),
+        percentage: Some((num_analyzed_files * 100 / num_files.max(1)) as u32),
+        ..Default::default()
+    })
+}
+
 fn cast_notification<R>(not: &lsp_server::Notification) -> Option<R::Params>
 where
     R: lsp_types::notification::Notification,
@@ -363,21 +669,3 @@ where
         None
     }
 }
-
-fn collect_diagnostics(
-    snapshot: &ServerSnapshot,
-    file_id: FileId,
-) -> Option<Vec<lsp_types::Diagnostic>> {
-    let line_index = snapshot.analysis_snapshot.line_index(file_id).ok()??;
-
-    // Get the diagnostics for the current path. If the operation was cancelled, simply continue to the next file.
-    let diagnostics = snapshot.analysis_snapshot.diagnostics(file_id).ok()?;
-
-    // Convert the diagnostics. This includes translating text offsets into `(line, column)` format.
-    Some(
-        diagnostics
-            .into_iter()
-            .flat_map(|diagnostic| convert::lsp_diagnostic_from_native(diagnostic, line_index))
-            .collect::<Vec<_>>(),
-    )
-}
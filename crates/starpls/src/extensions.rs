@@ -1,5 +1,7 @@
 use lsp_types::request::Request;
+use lsp_types::Position;
 use lsp_types::TextDocumentIdentifier;
+use lsp_types::Url;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -32,3 +34,27 @@ impl Request for ShowHir {
     type Result = String;
     const METHOD: &'static str = "starpls/showHir";
 }
+
+/// Command that computes the fully-qualified label of a target, e.g. `//foo/bar:baz`. Clients are
+/// expected to copy the returned label to the clipboard.
+pub const COPY_LABEL_COMMAND: &str = "starpls.copyLabel";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyLabelArgs {
+    /// The BUILD file that declares the target.
+    pub uri: Url,
+    pub name: String,
+}
+
+/// Data attached to completion items, used to compute their details during
+/// `completionItem/resolve`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionResolveData {
+    pub uri: Url,
+    pub position: Position,
+    /// The file to load the item's symbol from, if the symbol isn't in scope yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_import: Option<u32>,
+}
@@ -1,7 +1,28 @@
+use std::fs;
+use std::path::Path;
+
+use starpls_common::FileInfo;
+use starpls_ide::Change;
+
+use crate::config::ClientSettings;
 use crate::convert;
+use crate::document::Document;
+use crate::document::DocumentSource;
 use crate::server::Server;
 use crate::utils::apply_document_content_changes;
 
+pub(crate) fn cancel_request(
+    server: &mut Server,
+    params: lsp_types::CancelParams,
+) -> anyhow::Result<()> {
+    let id = match params.id {
+        lsp_types::NumberOrString::Number(id) => id.into(),
+        lsp_types::NumberOrString::String(id) => id.into(),
+    };
+    server.cancel_request(id);
+    Ok(())
+}
+
 pub(crate) fn did_open_text_document(
     server: &mut Server,
     params: lsp_types::DidOpenTextDocumentParams,
@@ -46,23 +67,197 @@ pub(crate) fn did_save_text_document(
     params: lsp_types::DidSaveTextDocumentParams,
 ) -> anyhow::Result<()> {
     let path = convert::path_buf_from_url(&params.text_document.uri)?;
-    if server
-        .document_manager
-        .read()
-        .lookup_by_path_buf(&path)
-        .is_some()
-    {
-        match path.file_name().and_then(|file_name| file_name.to_str()) {
-            Some("MODULE.bazel" | "WORKSPACE" | "WORKSPACE.bazel" | "WORKSPACE.bzlmod") => {}
-            Some(file_name) if file_name.ends_with(".MODULE.bazel") => {}
-            Some("BUILD" | "BUILD.bazel") => {
-                server.refresh_all_workspace_targets();
-                return Ok(());
+    let document_manager = server.document_manager.read();
+    if let Some(file_id) = document_manager.lookup_by_path_buf(&path) {
+        // Keep the workspace symbol index in sync with the saved contents.
+        if let Some(Document {
+            contents,
+            info: Some(FileInfo::Bazel { api_context, .. }),
+            ..
+        }) = document_manager.get(file_id)
+        {
+            server.workspaces[server.workspace_index(&path)]
+                .symbol_indexer
+                .index_file(file_id, &path, contents, api_context.clone());
+        }
+        drop(document_manager);
+
+        match workspace_file_kind(&path) {
+            Some(WorkspaceFileKind::Build) => server.refresh_all_workspace_targets(Some(&path)),
+            Some(WorkspaceFileKind::Module) => server.workspace_for_path_mut(&path).reset_repos(),
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn did_change_configuration(
+    server: &mut Server,
+    params: lsp_types::DidChangeConfigurationParams,
+) -> anyhow::Result<()> {
+    // Clients that support `workspace/configuration` usually don't include the changed settings,
+    // so we ask for them instead.
+    if server.config.has_configuration_support() {
+        server.pull_configuration();
+    } else {
+        server.update_settings(ClientSettings::from_json(params.settings)?);
+    }
+    Ok(())
+}
+
+pub(crate) fn did_change_watched_files(
+    server: &mut Server,
+    params: lsp_types::DidChangeWatchedFilesParams,
+) -> anyhow::Result<()> {
+    // A file can be loaded by several workspaces, e.g. with nested workspaces, so changes are
+    // applied to all of them. Updating or removing a file is a no-op for workspaces that haven't
+    // loaded it.
+    let mut changes = server
+        .workspaces
+        .iter()
+        .map(|_| Change::default())
+        .collect::<Vec<_>>();
+    let mut changed_build_files = Vec::new();
+    let mut changed_module_files = Vec::new();
+    let mut created_files = Vec::new();
+    let mut has_created_or_deleted_files = false;
+    let mut document_manager = server.document_manager.write();
+
+    for event in params.changes {
+        let path = match convert::path_buf_from_url(&event.uri) {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+        let file_id = document_manager.lookup_by_path_buf(&path);
+
+        // The editor is the source of truth for open documents.
+        if let Some(Document {
+            source: DocumentSource::Editor(_),
+            ..
+        }) = file_id.and_then(|file_id| document_manager.get(file_id))
+        {
+            continue;
+        }
+
+        // Files that haven't been loaded yet will simply be read from disk when needed.
+        if event.typ == lsp_types::FileChangeType::DELETED {
+            has_created_or_deleted_files = true;
+            if let Some(file_id) = file_id {
+                for (workspace, change) in server.workspaces.iter().zip(&mut changes) {
+                    workspace.loader.unload_file(file_id);
+                    workspace.symbol_indexer.remove_file(file_id);
+                    change.remove_file(file_id);
+                }
+                document_manager.remove(file_id);
+
+                // Clear any diagnostics reported while analyzing the workspace.
+                if server.config.should_analyze_workspace() {
+                    server
+                        .diagnostics_manager
+                        .set_diagnostics(file_id, Vec::new());
+                }
+            }
+        } else {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(_) => continue,
+            };
+            server.workspaces[server.workspace_index(&path)]
+                .symbol_indexer
+                .index_path(&path, &contents);
+
+            if event.typ == lsp_types::FileChangeType::CREATED {
+                has_created_or_deleted_files = true;
+                if let Some(file_id) = file_id {
+                    for workspace in &server.workspaces {
+                        workspace.loader.unload_file(file_id);
+                    }
+                }
+                if server.config.should_analyze_workspace() {
+                    created_files.push((path.clone(), contents.clone()));
+                }
+            }
+
+            // Modified files keep their file IDs, so they only need to be updated in the
+            // workspaces that have loaded them.
+            if let Some(file_id) = file_id {
+                if document_manager.get(file_id).is_some() {
+                    document_manager.modify(file_id, contents, None);
+                } else {
+                    for change in &mut changes {
+                        change.update_file(file_id, contents.clone());
+                    }
+                    if server.config.should_analyze_workspace() {
+                        server.force_analysis_for_files.insert(file_id);
+                    }
+                }
             }
-            _ => return Ok(()),
         }
-        server.bazel_client.clear_repo_mappings();
-        server.fetched_repos.clear();
+
+        match workspace_file_kind(&path) {
+            Some(WorkspaceFileKind::Build) => changed_build_files.push(path),
+            Some(WorkspaceFileKind::Module) => changed_module_files.push(path),
+            None => {}
+        }
+    }
+
+    // Creating or deleting files can change what load statements resolve to.
+    for (workspace, mut change) in server.workspaces.iter_mut().zip(changes) {
+        if has_created_or_deleted_files {
+            workspace.loader.clear_cached_load_results();
+            change.invalidate_loads();
+        }
+        workspace.analysis.apply_change(change);
+    }
+    server
+        .force_analysis_for_files
+        .extend(document_manager.file_ids());
+    drop(document_manager);
+
+    for (path, contents) in created_files {
+        server.add_created_workspace_file(path, contents);
+    }
+
+    for path in changed_build_files {
+        server.refresh_all_workspace_targets(Some(&path));
+    }
+    for path in changed_module_files {
+        server.workspace_for_path_mut(&path).reset_repos();
     }
     Ok(())
 }
+
+pub(crate) fn did_change_workspace_folders(
+    server: &mut Server,
+    params: lsp_types::DidChangeWorkspaceFoldersParams,
+) -> anyhow::Result<()> {
+    for folder in params.event.removed {
+        if let Ok(path) = folder.uri.to_file_path() {
+            server.remove_workspace(&path);
+        }
+    }
+    for folder in params.event.added {
+        if let Ok(path) = folder.uri.to_file_path() {
+            server.add_workspace(path);
+        }
+    }
+    Ok(())
+}
+
+enum WorkspaceFileKind {
+    Build,
+    Module,
+}
+
+/// Determines whether the given file affects the workspace's targets or its external
+/// repositories.
+fn workspace_file_kind(path: &Path) -> Option<WorkspaceFileKind> {
+    match path.file_name()?.to_str()? {
+        "BUILD" | "BUILD.bazel" => Some(WorkspaceFileKind::Build),
+        "MODULE.bazel" | "WORKSPACE" | "WORKSPACE.bazel" | "WORKSPACE.bzlmod" => {
+            Some(WorkspaceFileKind::Module)
+        }
+        file_name if file_name.ends_with(".MODULE.bazel") => Some(WorkspaceFileKind::Module),
+        _ => None,
+    }
+}
@@ -1,16 +1,30 @@
 use anyhow::Ok;
+use rustc_hash::FxHashMap;
+use starpls_common::FileId;
 use starpls_ide::CompletionItemKind;
 use starpls_ide::CompletionMode::InsertText;
+use starpls_ide::CompletionMode::Snippet;
 use starpls_ide::CompletionMode::TextEdit;
 use starpls_ide::Edit;
 use starpls_ide::FilePosition;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
 
 use crate::convert::path_buf_from_url;
 use crate::convert::{self};
+use crate::diagnostics::collect_diagnostics;
+use crate::diagnostics::pull_diagnostics;
+use crate::diagnostics::PulledDiagnostics;
+use crate::document::DocumentSource;
+use crate::extensions::CompletionResolveData;
+use crate::extensions::CopyLabelArgs;
 use crate::extensions::ShowHirParams;
 use crate::extensions::ShowSyntaxTreeParams;
+use crate::extensions::COPY_LABEL_COMMAND;
+use crate::semantic_tokens::lsp_semantic_tokens_from_native;
 use crate::server::ServerSnapshot;
 use crate::utils::response_from_locations;
+use crate::workspace::find_workspace_files;
 
 macro_rules! try_opt {
     ($expr:expr) => {
@@ -68,13 +82,58 @@ pub(crate) fn goto_definition(
     Ok(Some(resp))
 }
 
+pub(crate) fn goto_implementation(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::request::GotoImplementationParams,
+) -> anyhow::Result<Option<lsp_types::request::GotoImplementationResponse>> {
+    let path = path_buf_from_url(&params.text_document_position_params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.text_document_position_params.position,
+    )?);
+    let resp = response_from_locations(
+        snapshot,
+        file_id,
+        snapshot
+            .analysis_snapshot
+            .goto_implementation(FilePosition { file_id, pos })?
+            .unwrap_or_else(Vec::new)
+            .into_iter(),
+    );
+    Ok(Some(resp))
+}
+
+pub(crate) fn goto_type_definition(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::request::GotoTypeDefinitionParams,
+) -> anyhow::Result<Option<lsp_types::request::GotoTypeDefinitionResponse>> {
+    let path = path_buf_from_url(&params.text_document_position_params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.text_document_position_params.position,
+    )?);
+    let resp = response_from_locations(
+        snapshot,
+        file_id,
+        snapshot
+            .analysis_snapshot
+            .goto_type_definition(FilePosition { file_id, pos })?
+            .unwrap_or_else(Vec::new)
+            .into_iter(),
+    );
+    Ok(Some(resp))
+}
+
 pub(crate) fn find_references(
     snapshot: &ServerSnapshot,
     params: lsp_types::ReferenceParams,
 ) -> anyhow::Result<Option<Vec<lsp_types::Location>>> {
     let path = path_buf_from_url(&params.text_document_position.text_document.uri)?;
     let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
-    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
     let pos = try_opt!(convert::text_size_from_lsp_position(
         snapshot,
         file_id,
@@ -86,6 +145,11 @@ pub(crate) fn find_references(
         .unwrap_or_else(Vec::new)
         .into_iter()
         .filter_map(|location| {
+            // References can span multiple files, so look up the line index for each location.
+            let line_index = snapshot
+                .analysis_snapshot
+                .line_index(location.file_id)
+                .ok()??;
             Some(lsp_types::Location {
                 range: convert::lsp_range_from_text_range(location.range, line_index)?,
                 uri: lsp_types::Url::from_file_path(
@@ -101,6 +165,73 @@ pub(crate) fn find_references(
     Ok(Some(resp.collect()))
 }
 
+pub(crate) fn code_action(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::CodeActionParams,
+) -> anyhow::Result<Option<lsp_types::CodeActionResponse>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let start = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.start,
+    )?);
+    let end = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.end,
+    )?);
+    let actions = try_opt!(snapshot.analysis_snapshot.code_actions(
+        file_id,
+        TextRange::new(start, end),
+        &snapshot.symbol_indexer.index.read(),
+    )?);
+    Ok(Some(
+        actions
+            .into_iter()
+            .map(|action| {
+                convert::lsp_code_action_from_native(snapshot, action, line_index)
+                    .map(lsp_types::CodeActionOrCommand::CodeAction)
+            })
+            .collect::<anyhow::Result<_>>()?,
+    ))
+}
+
+pub(crate) fn code_lens(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::CodeLensParams,
+) -> anyhow::Result<Option<Vec<lsp_types::CodeLens>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let lenses = try_opt!(snapshot.analysis_snapshot.code_lenses(file_id)?);
+    Ok(Some(
+        lenses
+            .into_iter()
+            .filter_map(|lens| {
+                convert::lsp_code_lens_from_native(&params.text_document.uri, lens, line_index)
+            })
+            .collect(),
+    ))
+}
+
+pub(crate) fn execute_command(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::ExecuteCommandParams,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    match params.command.as_str() {
+        COPY_LABEL_COMMAND => {
+            let arg = try_opt!(params.arguments.into_iter().next());
+            let args: CopyLabelArgs = serde_json::from_value(arg)?;
+            let path = path_buf_from_url(&args.uri)?;
+            let label = try_opt!(snapshot.symbol_indexer.label_for_target(&path, &args.name));
+            Ok(Some(serde_json::Value::String(label)))
+        }
+        _ => Ok(None),
+    }
+}
+
 pub(crate) fn completion(
     snapshot: &ServerSnapshot,
     params: lsp_types::CompletionParams,
@@ -113,79 +244,170 @@ pub(crate) fn completion(
         file_id,
         params.text_document_position.position,
     )?);
+    let uri = params.text_document_position.text_document.uri;
+    let position = params.text_document_position.position;
 
-    Ok(Some(
-        snapshot
-            .analysis_snapshot
-            .completions(
-                FilePosition { file_id, pos },
-                params.context.and_then(|cx| cx.trigger_character),
-            )?
-            .unwrap_or_else(Vec::new)
-            .into_iter()
-            .flat_map(|item| {
-                let sort_text = Some(item.sort_text());
-                let (insert_text, text_edit) = match item.mode {
-                    Some(mode) => match mode {
-                        InsertText(text) => (Some(text), None),
-                        TextEdit(edit) => (
-                            None,
-                            Some(match edit {
-                                Edit::TextEdit(edit) => {
-                                    lsp_types::CompletionTextEdit::Edit(lsp_types::TextEdit {
-                                        range: convert::lsp_range_from_text_range(
-                                            edit.range, line_index,
-                                        )?,
-                                        new_text: edit.new_text,
-                                    })
-                                }
-                                Edit::InsertReplaceEdit(edit)
-                                    if snapshot.config.has_insert_replace_support() =>
-                                {
-                                    lsp_types::CompletionTextEdit::InsertAndReplace(
-                                        lsp_types::InsertReplaceEdit {
-                                            new_text: edit.new_text,
-                                            insert: convert::lsp_range_from_text_range(
-                                                edit.insert,
-                                                line_index,
-                                            )?,
-                                            replace: convert::lsp_range_from_text_range(
-                                                edit.replace,
-                                                line_index,
+    let mut items = snapshot
+        .analysis_snapshot
+        .completions(
+            FilePosition { file_id, pos },
+            params.context.and_then(|cx| cx.trigger_character),
+        )?
+        .unwrap_or_else(Vec::new);
+
+    // Symbols that need to be loaded are only offered for the name being typed, so the client
+    // needs to ask again as the name changes.
+    let auto_imports = snapshot
+        .analysis_snapshot
+        .auto_import_completions(
+            FilePosition { file_id, pos },
+            &snapshot.symbol_indexer.index.read(),
+        )?
+        .unwrap_or_else(Vec::new);
+    let is_incomplete = !auto_imports.is_empty();
+    items.extend(auto_imports);
+
+    Ok(Some(lsp_types::CompletionResponse::List(
+        lsp_types::CompletionList {
+            is_incomplete,
+            items: items
+                .into_iter()
+                .flat_map(|item| {
+                    let sort_text = Some(item.sort_text());
+                    let mut insert_text_format = None;
+                    let (insert_text, text_edit) = match item.mode {
+                        Some(mode) => match mode {
+                            InsertText(text) => (Some(text), None),
+                            Snippet(text) if snapshot.config.has_snippet_support() => {
+                                insert_text_format = Some(lsp_types::InsertTextFormat::SNIPPET);
+                                (Some(text), None)
+                            }
+                            Snippet(_) => (None, None),
+                            TextEdit(edit) => (
+                                None,
+                                Some(match edit {
+                                    Edit::TextEdit(edit) => {
+                                        lsp_types::CompletionTextEdit::Edit(lsp_types::TextEdit {
+                                            range: convert::lsp_range_from_text_range(
+                                                edit.range, line_index,
                                             )?,
-                                        },
-                                    )
-                                }
-                                _ => return None,
-                            }),
-                        ),
-                    },
-                    None => (None, None),
-                };
+                                            new_text: edit.new_text,
+                                        })
+                                    }
+                                    Edit::InsertReplaceEdit(edit)
+                                        if snapshot.config.has_insert_replace_support() =>
+                                    {
+                                        lsp_types::CompletionTextEdit::InsertAndReplace(
+                                            lsp_types::InsertReplaceEdit {
+                                                new_text: edit.new_text,
+                                                insert: convert::lsp_range_from_text_range(
+                                                    edit.insert,
+                                                    line_index,
+                                                )?,
+                                                replace: convert::lsp_range_from_text_range(
+                                                    edit.replace,
+                                                    line_index,
+                                                )?,
+                                            },
+                                        )
+                                    }
+                                    _ => return None,
+                                }),
+                            ),
+                        },
+                        None => (None, None),
+                    };
+                    let data = serde_json::to_value(CompletionResolveData {
+                        uri: uri.clone(),
+                        position,
+                        auto_import: item.auto_import.map(|file_id| file_id.0),
+                    })
+                    .ok()?;
 
-                Some(lsp_types::CompletionItem {
-                    label: item.label,
-                    kind: Some(match item.kind {
-                        CompletionItemKind::Function => lsp_types::CompletionItemKind::FUNCTION,
-                        CompletionItemKind::Field => lsp_types::CompletionItemKind::FIELD,
-                        CompletionItemKind::Variable => lsp_types::CompletionItemKind::VARIABLE,
-                        CompletionItemKind::Class => lsp_types::CompletionItemKind::CLASS,
-                        CompletionItemKind::Module => lsp_types::CompletionItemKind::MODULE,
-                        CompletionItemKind::Keyword => lsp_types::CompletionItemKind::KEYWORD,
-                        CompletionItemKind::File => lsp_types::CompletionItemKind::FILE,
-                        CompletionItemKind::Folder => lsp_types::CompletionItemKind::FOLDER,
-                        CompletionItemKind::Constant => lsp_types::CompletionItemKind::CONSTANT,
-                    }),
-                    sort_text,
-                    insert_text,
-                    text_edit,
-                    filter_text: item.filter_text,
-                    ..Default::default()
+                    Some(lsp_types::CompletionItem {
+                        label: item.label,
+                        kind: Some(match item.kind {
+                            CompletionItemKind::Function => lsp_types::CompletionItemKind::FUNCTION,
+                            CompletionItemKind::Field => lsp_types::CompletionItemKind::FIELD,
+                            CompletionItemKind::Variable => lsp_types::CompletionItemKind::VARIABLE,
+                            CompletionItemKind::Class => lsp_types::CompletionItemKind::CLASS,
+                            CompletionItemKind::Module => lsp_types::CompletionItemKind::MODULE,
+                            CompletionItemKind::Keyword => lsp_types::CompletionItemKind::KEYWORD,
+                            CompletionItemKind::File => lsp_types::CompletionItemKind::FILE,
+                            CompletionItemKind::Folder => lsp_types::CompletionItemKind::FOLDER,
+                            CompletionItemKind::Constant => lsp_types::CompletionItemKind::CONSTANT,
+                        }),
+                        sort_text,
+                        insert_text,
+                        insert_text_format,
+                        text_edit,
+                        filter_text: item.filter_text,
+                        data: Some(data),
+                        ..Default::default()
+                    })
                 })
-            })
-            .collect::<Vec<_>>()
-            .into(),
-    ))
+                .collect(),
+        },
+    )))
+}
+
+pub(crate) fn completion_resolve(
+    snapshot: &ServerSnapshot,
+    mut item: lsp_types::CompletionItem,
+) -> anyhow::Result<lsp_types::CompletionItem> {
+    let data: CompletionResolveData = match item.data.take() {
+        Some(data) => serde_json::from_value(data)?,
+        None => return Ok(item),
+    };
+    let path = path_buf_from_url(&data.uri)?;
+    let file_id = match snapshot.document_manager.read().lookup_by_path_buf(&path) {
+        Some(file_id) => file_id,
+        None => return Ok(item),
+    };
+    let pos = match convert::text_size_from_lsp_position(snapshot, file_id, data.position)? {
+        Some(pos) => pos,
+        None => return Ok(item),
+    };
+
+    let pos = FilePosition { file_id, pos };
+
+    // Symbols that need to be loaded are resolved from the file they're loaded from, since
+    // they aren't in scope yet.
+    if let Some(auto_import_file_id) = data.auto_import {
+        let line_index = match snapshot.analysis_snapshot.line_index(file_id)? {
+            Some(line_index) => line_index,
+            None => return Ok(item),
+        };
+        if let Some(auto_import) = snapshot.analysis_snapshot.resolve_auto_import(
+            pos,
+            FileId(auto_import_file_id),
+            &item.label,
+        )? {
+            item.label_details = Some(lsp_types::CompletionItemLabelDetails {
+                detail: None,
+                description: Some(auto_import.module),
+            });
+            item.additional_text_edits =
+                convert::lsp_range_from_text_range(auto_import.edit.range, line_index).map(
+                    |range| {
+                        vec![lsp_types::TextEdit {
+                            range,
+                            new_text: auto_import.edit.new_text,
+                        }]
+                    },
+                );
+        }
+        return Ok(item);
+    }
+
+    if let Some(details) = snapshot
+        .analysis_snapshot
+        .resolve_completion_item(pos, &item.label)?
+    {
+        item.detail = details.detail;
+        item.documentation = details.documentation.map(to_markup_doc);
+    }
+    Ok(item)
 }
 
 pub(crate) fn hover(
@@ -211,6 +433,34 @@ pub(crate) fn hover(
         }))
 }
 
+pub(crate) fn inlay_hints(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::InlayHintParams,
+) -> anyhow::Result<Option<Vec<lsp_types::InlayHint>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let start = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.start,
+    )?);
+    let end = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.end,
+    )?);
+    let hints = try_opt!(snapshot
+        .analysis_snapshot
+        .inlay_hints(file_id, Some(TextRange::new(start, end)))?);
+    Ok(Some(
+        hints
+            .into_iter()
+            .filter_map(|hint| convert::lsp_inlay_hint_from_native(hint, line_index))
+            .collect(),
+    ))
+}
+
 pub(crate) fn signature_help(
     snapshot: &ServerSnapshot,
     params: lsp_types::SignatureHelpParams,
@@ -249,6 +499,95 @@ pub(crate) fn signature_help(
         }))
 }
 
+pub(crate) fn document_diagnostic(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::DocumentDiagnosticParams,
+) -> anyhow::Result<lsp_types::DocumentDiagnosticReportResult> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = snapshot.document_manager.read().lookup_by_path_buf(&path);
+    let pulled = match file_id {
+        Some(file_id) => pull_diagnostics(
+            snapshot.analysis_snapshot.revision(),
+            params.previous_result_id.as_deref(),
+            || collect_diagnostics(snapshot, file_id),
+        )?,
+        None => None,
+    };
+    let report = match pulled {
+        Some(PulledDiagnostics::Unchanged(result_id)) => {
+            lsp_types::DocumentDiagnosticReport::Unchanged(
+                lsp_types::RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents: None,
+                    unchanged_document_diagnostic_report:
+                        lsp_types::UnchangedDocumentDiagnosticReport { result_id },
+                },
+            )
+        }
+        Some(PulledDiagnostics::Full(result_id, items)) => {
+            lsp_types::DocumentDiagnosticReport::Full(
+                lsp_types::RelatedFullDocumentDiagnosticReport {
+                    related_documents: None,
+                    full_document_diagnostic_report: lsp_types::FullDocumentDiagnosticReport {
+                        result_id: Some(result_id),
+                        items,
+                    },
+                },
+            )
+        }
+        None => lsp_types::DocumentDiagnosticReport::Full(
+            lsp_types::RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: lsp_types::FullDocumentDiagnosticReport {
+                    result_id: None,
+                    items: Vec::new(),
+                },
+            },
+        ),
+    };
+    Ok(lsp_types::DocumentDiagnosticReportResult::Report(report))
+}
+
+pub(crate) fn document_highlight(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::DocumentHighlightParams,
+) -> anyhow::Result<Option<Vec<lsp_types::DocumentHighlight>>> {
+    let path = path_buf_from_url(&params.text_document_position_params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.text_document_position_params.position,
+    )?);
+    Ok(snapshot
+        .analysis_snapshot
+        .document_highlight(FilePosition { file_id, pos })?
+        .map(|highlights| {
+            highlights
+                .into_iter()
+                .filter_map(|highlight| {
+                    convert::lsp_document_highlight_from_native(highlight, line_index)
+                })
+                .collect()
+        }))
+}
+
+pub(crate) fn document_link(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::DocumentLinkParams,
+) -> anyhow::Result<Option<Vec<lsp_types::DocumentLink>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let links = try_opt!(snapshot.analysis_snapshot.document_links(file_id)?);
+    Ok(Some(
+        links
+            .into_iter()
+            .filter_map(|link| convert::lsp_document_link_from_native(snapshot, link, line_index))
+            .collect(),
+    ))
+}
+
 pub(crate) fn document_symbols(
     snapshot: &ServerSnapshot,
     params: lsp_types::DocumentSymbolParams,
@@ -268,9 +607,414 @@ pub(crate) fn document_symbols(
         }))
 }
 
+pub(crate) fn folding_range(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::FoldingRangeParams,
+) -> anyhow::Result<Option<Vec<lsp_types::FoldingRange>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    Ok(snapshot
+        .analysis_snapshot
+        .folding_ranges(file_id)?
+        .map(|folds| {
+            folds
+                .into_iter()
+                .filter_map(|fold| convert::lsp_folding_range_from_native(fold, line_index))
+                .collect()
+        }))
+}
+
+pub(crate) fn formatting(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::DocumentFormattingParams,
+) -> anyhow::Result<Option<Vec<lsp_types::TextEdit>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let edits = try_opt!(snapshot.analysis_snapshot.format(file_id)?);
+    Ok(Some(
+        edits
+            .into_iter()
+            .filter_map(|edit| convert::lsp_text_edit_from_native(edit, line_index))
+            .collect(),
+    ))
+}
+
+pub(crate) fn range_formatting(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::DocumentRangeFormattingParams,
+) -> anyhow::Result<Option<Vec<lsp_types::TextEdit>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let start = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.start,
+    )?);
+    let end = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.end,
+    )?);
+    let edits = try_opt!(snapshot
+        .analysis_snapshot
+        .format_range(file_id, TextRange::new(start, end))?);
+    Ok(Some(
+        edits
+            .into_iter()
+            .filter_map(|edit| convert::lsp_text_edit_from_native(edit, line_index))
+            .collect(),
+    ))
+}
+
+pub(crate) fn prepare_call_hierarchy(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::CallHierarchyPrepareParams,
+) -> anyhow::Result<Option<Vec<lsp_types::CallHierarchyItem>>> {
+    let path = path_buf_from_url(&params.text_document_position_params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.text_document_position_params.position,
+    )?);
+    let items = try_opt!(snapshot
+        .analysis_snapshot
+        .prepare_call_hierarchy(FilePosition { file_id, pos })?);
+    let mut resp = Vec::new();
+    for item in items {
+        if let Some(item) = convert::lsp_call_hierarchy_item_from_native(snapshot, item)? {
+            resp.push(item);
+        }
+    }
+    Ok(Some(resp))
+}
+
+pub(crate) fn call_hierarchy_incoming_calls(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::CallHierarchyIncomingCallsParams,
+) -> anyhow::Result<Option<Vec<lsp_types::CallHierarchyIncomingCall>>> {
+    let (file_id, pos) = try_opt!(call_hierarchy_item_position(snapshot, &params.item)?);
+
+    // Files can't be called, so there are no incoming calls for items representing them.
+    let pos = try_opt!(pos);
+    let calls = try_opt!(snapshot
+        .analysis_snapshot
+        .call_hierarchy_incoming_calls(FilePosition { file_id, pos })?);
+    let mut resp = Vec::new();
+    for call in calls {
+        // The ranges of incoming calls are relative to the file of the caller.
+        let Some(line_index) = snapshot.analysis_snapshot.line_index(call.item.file_id)? else {
+            continue;
+        };
+        let from_ranges = call
+            .ranges
+            .into_iter()
+            .filter_map(|range| convert::lsp_range_from_text_range(range, line_index))
+            .collect();
+        if let Some(from) = convert::lsp_call_hierarchy_item_from_native(snapshot, call.item)? {
+            resp.push(lsp_types::CallHierarchyIncomingCall { from, from_ranges });
+        }
+    }
+    Ok(Some(resp))
+}
+
+pub(crate) fn call_hierarchy_outgoing_calls(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::CallHierarchyOutgoingCallsParams,
+) -> anyhow::Result<Option<Vec<lsp_types::CallHierarchyOutgoingCall>>> {
+    let (file_id, pos) = try_opt!(call_hierarchy_item_position(snapshot, &params.item)?);
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let calls = try_opt!(snapshot
+        .analysis_snapshot
+        .call_hierarchy_outgoing_calls(file_id, pos)?);
+    let mut resp = Vec::new();
+    for call in calls {
+        let from_ranges = call
+            .ranges
+            .into_iter()
+            .filter_map(|range| convert::lsp_range_from_text_range(range, line_index))
+            .collect();
+        if let Some(to) = convert::lsp_call_hierarchy_item_from_native(snapshot, call.item)? {
+            resp.push(lsp_types::CallHierarchyOutgoingCall { to, from_ranges });
+        }
+    }
+    Ok(Some(resp))
+}
+
+/// Maps a call hierarchy item back to the file and position of its name. The position is `None`
+/// for items representing the top level of a file.
+fn call_hierarchy_item_position(
+    snapshot: &ServerSnapshot,
+    item: &lsp_types::CallHierarchyItem,
+) -> anyhow::Result<Option<(FileId, Option<TextSize>)>> {
+    let path = path_buf_from_url(&item.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    if item.kind == lsp_types::SymbolKind::FILE {
+        return Ok(Some((file_id, None)));
+    }
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        item.selection_range.start,
+    )?);
+    Ok(Some((file_id, Some(pos))))
+}
+
+pub(crate) fn prepare_rename(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::TextDocumentPositionParams,
+) -> anyhow::Result<Option<lsp_types::PrepareRenameResponse>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.position,
+    )?);
+    let range = try_opt!(snapshot
+        .analysis_snapshot
+        .prepare_rename(FilePosition { file_id, pos })?);
+    Ok(convert::lsp_range_from_text_range(range, line_index)
+        .map(lsp_types::PrepareRenameResponse::Range))
+}
+
+pub(crate) fn rename(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::RenameParams,
+) -> anyhow::Result<Option<lsp_types::WorkspaceEdit>> {
+    let path = path_buf_from_url(&params.text_document_position.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let pos = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.text_document_position.position,
+    )?);
+    let change = try_opt!(snapshot
+        .analysis_snapshot
+        .rename(FilePosition { file_id, pos }, &params.new_name)??);
+    Ok(Some(convert::lsp_workspace_edit_from_native(
+        snapshot, change,
+    )?))
+}
+
+pub(crate) fn selection_range(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::SelectionRangeParams,
+) -> anyhow::Result<Option<Vec<lsp_types::SelectionRange>>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let mut selection_ranges = Vec::with_capacity(params.positions.len());
+    for position in params.positions {
+        let ranges = match convert::text_size_from_lsp_position(snapshot, file_id, position)? {
+            Some(pos) => snapshot
+                .analysis_snapshot
+                .selection_ranges(FilePosition { file_id, pos })?
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        // Build the chain of selection ranges from the outermost range inwards. Every position
+        // must have a result, so fall back to an empty range at the position itself.
+        let selection_range = ranges
+            .into_iter()
+            .rev()
+            .filter_map(|range| convert::lsp_range_from_text_range(range, line_index))
+            .fold(None, |parent, range| {
+                Some(lsp_types::SelectionRange {
+                    range,
+                    parent: parent.map(Box::new),
+                })
+            })
+            .unwrap_or(lsp_types::SelectionRange {
+                range: lsp_types::Range {
+                    start: position,
+                    end: position,
+                },
+                parent: None,
+            });
+        selection_ranges.push(selection_range);
+    }
+    Ok(Some(selection_ranges))
+}
+
+pub(crate) fn semantic_tokens_full(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::SemanticTokensParams,
+) -> anyhow::Result<Option<lsp_types::SemanticTokensResult>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let tokens = try_opt!(snapshot.analysis_snapshot.semantic_tokens(file_id, None)?);
+    Ok(Some(
+        lsp_semantic_tokens_from_native(tokens, line_index).into(),
+    ))
+}
+
+pub(crate) fn semantic_tokens_range(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::SemanticTokensRangeParams,
+) -> anyhow::Result<Option<lsp_types::SemanticTokensRangeResult>> {
+    let path = path_buf_from_url(&params.text_document.uri)?;
+    let file_id = try_opt!(snapshot.document_manager.read().lookup_by_path_buf(&path));
+    let line_index = try_opt!(snapshot.analysis_snapshot.line_index(file_id)?);
+    let start = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.start,
+    )?);
+    let end = try_opt!(convert::text_size_from_lsp_position(
+        snapshot,
+        file_id,
+        params.range.end,
+    )?);
+    let tokens = try_opt!(snapshot
+        .analysis_snapshot
+        .semantic_tokens(file_id, Some(TextRange::new(start, end)))?);
+    Ok(Some(
+        lsp_semantic_tokens_from_native(tokens, line_index).into(),
+    ))
+}
+
 fn to_markup_doc(doc: String) -> lsp_types::Documentation {
     lsp_types::Documentation::MarkupContent(lsp_types::MarkupContent {
         kind: lsp_types::MarkupKind::Markdown,
         value: doc,
     })
 }
+
+pub(crate) fn workspace_diagnostic(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::WorkspaceDiagnosticParams,
+) -> anyhow::Result<lsp_types::WorkspaceDiagnosticReportResult> {
+    let mut items = Vec::new();
+
+    // Only documents are analyzed unless the user opted into analyzing the whole workspace.
+    if !snapshot.config.args.enable_workspace_diagnostics {
+        return Ok(lsp_types::WorkspaceDiagnosticReportResult::Report(
+            lsp_types::WorkspaceDiagnosticReport { items },
+        ));
+    }
+
+    let previous_result_ids = params
+        .previous_result_ids
+        .iter()
+        .map(|previous| (&previous.uri, previous.value.as_str()))
+        .collect::<FxHashMap<_, _>>();
+    let folders = snapshot
+        .workspaces()
+        .map(|snapshot| snapshot.folder.clone())
+        .collect::<Vec<_>>();
+    for snapshot in snapshot.workspaces() {
+        // Files in nested workspaces are reported by the innermost workspace containing them.
+        // Files that were never opened or loaded are read from disk as needed.
+        let files = {
+            let document_manager = snapshot.document_manager.read();
+            find_workspace_files(&snapshot.folder, &snapshot.root)
+                .filter(|(path, _, _)| {
+                    folders
+                        .iter()
+                        .filter(|folder| path.starts_with(folder))
+                        .max_by_key(|folder| folder.components().count())
+                        == Some(&snapshot.folder)
+                })
+                .map(|(path, dialect, info)| (document_manager.intern_path(path), dialect, info))
+                .collect()
+        };
+        for file_id in snapshot.analysis_snapshot.read_files(files)? {
+            let (uri, version) = {
+                let document_manager = snapshot.document_manager.read();
+                let uri = match lsp_types::Url::from_file_path(
+                    document_manager.lookup_by_file_id(file_id),
+                ) {
+                    Ok(uri) => uri,
+                    Err(_) => continue,
+                };
+                let version = match document_manager
+                    .get(file_id)
+                    .map(|document| document.source)
+                {
+                    Some(DocumentSource::Editor(version)) => Some(version as i64),
+                    _ => None,
+                };
+                (uri, version)
+            };
+
+            let pulled = pull_diagnostics(
+                snapshot.analysis_snapshot.revision(),
+                previous_result_ids.get(&uri).copied(),
+                || collect_diagnostics(snapshot, file_id),
+            )?;
+            items.push(match pulled {
+                Some(PulledDiagnostics::Unchanged(result_id)) => {
+                    lsp_types::WorkspaceDocumentDiagnosticReport::Unchanged(
+                        lsp_types::WorkspaceUnchangedDocumentDiagnosticReport {
+                            uri,
+                            version,
+                            unchanged_document_diagnostic_report:
+                                lsp_types::UnchangedDocumentDiagnosticReport { result_id },
+                        },
+                    )
+                }
+                Some(PulledDiagnostics::Full(result_id, diagnostics)) => {
+                    lsp_types::WorkspaceDocumentDiagnosticReport::Full(
+                        lsp_types::WorkspaceFullDocumentDiagnosticReport {
+                            uri,
+                            version,
+                            full_document_diagnostic_report:
+                                lsp_types::FullDocumentDiagnosticReport {
+                                    result_id: Some(result_id),
+                                    items: diagnostics,
+                                },
+                        },
+                    )
+                }
+                None => continue,
+            });
+        }
+    }
+    Ok(lsp_types::WorkspaceDiagnosticReportResult::Report(
+        lsp_types::WorkspaceDiagnosticReport { items },
+    ))
+}
+
+pub(crate) fn workspace_symbol(
+    snapshot: &ServerSnapshot,
+    params: lsp_types::WorkspaceSymbolParams,
+) -> anyhow::Result<Option<lsp_types::WorkspaceSymbolResponse>> {
+    let symbol_indexes = snapshot
+        .workspaces()
+        .map(|snapshot| snapshot.symbol_indexer.index.read())
+        .collect::<Vec<_>>();
+    let mut symbols = Vec::new();
+    for (i, symbol_index) in symbol_indexes.iter().enumerate() {
+        // Nested workspaces can share files, whose symbols are only reported once.
+        let is_indexed_earlier = |file_id| {
+            symbol_indexes[..i]
+                .iter()
+                .any(|index| index.contains(file_id))
+        };
+        symbols.extend(
+            symbol_index
+                .search(&params.query)
+                .into_iter()
+                .filter(|symbol| !is_indexed_earlier(symbol.file_id))
+                .filter_map(|symbol| {
+                    let line_index = symbol_index.line_index(symbol.file_id)?;
+                    let uri = lsp_types::Url::from_file_path(
+                        snapshot
+                            .document_manager
+                            .read()
+                            .lookup_by_file_id(symbol.file_id),
+                    )
+                    .ok()?;
+                    convert::lsp_symbol_information_from_native(symbol, uri, line_index)
+                }),
+        );
+    }
+    Ok(Some(lsp_types::WorkspaceSymbolResponse::Flat(symbols)))
+}
@@ -1,6 +1,7 @@
 use clap::Parser;
 use clap::Subcommand;
 use commands::check::CheckCommand;
+use commands::format::FormatCommand;
 use commands::server::ServerCommand;
 
 mod bazel;
@@ -14,9 +15,12 @@ mod document;
 mod event_loop;
 mod extensions;
 mod handlers;
+mod semantic_tokens;
 mod server;
+mod symbol_indexer;
 mod task_pool;
 mod utils;
+mod workspace;
 
 #[derive(Parser)]
 struct Cli {
@@ -30,6 +34,9 @@ enum Commands {
     /// Analyze the specified Starlark files and report errors.
     Check(CheckCommand),
 
+    /// Format the specified Starlark files in place.
+    Format(FormatCommand),
+
     /// Start the language server.
     Server(ServerCommand),
 
@@ -51,6 +58,7 @@ fn main() -> anyhow::Result<()> {
 
     match cli.command {
         Some(Commands::Check(cmd)) => cmd.run(),
+        Some(Commands::Format(cmd)) => cmd.run(),
         Some(Commands::Server(cmd)) => cmd.run(),
         Some(Commands::Version) => run_version(),
         None => ServerCommand::default().run(),
@@ -0,0 +1,111 @@
+use line_index::LineIndex;
+use line_index::WideEncoding;
+use lsp_types::SemanticTokenModifier;
+use lsp_types::SemanticTokenType;
+use lsp_types::SemanticTokensLegend;
+use starpls_ide::SemanticToken;
+use starpls_ide::SemanticTokenKind;
+
+const PROVIDER: SemanticTokenType = SemanticTokenType::new("provider");
+const RULE: SemanticTokenType = SemanticTokenType::new("rule");
+const UNUSED: SemanticTokenModifier = SemanticTokenModifier::new("unused");
+
+// The order of these arrays determines the indices used when encoding tokens below.
+const SUPPORTED_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::PARAMETER,
+    PROVIDER,
+    RULE,
+    SemanticTokenType::VARIABLE,
+];
+
+const SUPPORTED_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::DEPRECATED,
+    SemanticTokenModifier::READONLY,
+    UNUSED,
+];
+
+pub(crate) fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: SUPPORTED_TYPES.to_vec(),
+        token_modifiers: SUPPORTED_MODIFIERS.to_vec(),
+    }
+}
+
+/// Encodes the given tokens using the relative format described by the LSP specification. Tokens
+/// must be sorted by their start offsets.
+pub(crate) fn lsp_semantic_tokens_from_native(
+    tokens: Vec<SemanticToken>,
+    line_index: &LineIndex,
+) -> lsp_types::SemanticTokens {
+    let mut data = Vec::with_capacity(tokens.len());
+    let mut prev_line = 0;
+    let mut prev_start = 0;
+
+    for token in tokens {
+        let (Some(start), Some(end)) = (
+            line_index.to_wide(
+                WideEncoding::Utf16,
+                line_index.line_col(token.range.start()),
+            ),
+            line_index.to_wide(WideEncoding::Utf16, line_index.line_col(token.range.end())),
+        ) else {
+            continue;
+        };
+
+        // Multiline tokens aren't supported by all clients, and the tokens we produce are
+        // almost always contained within a single line anyways.
+        if start.line != end.line {
+            continue;
+        }
+
+        let delta_line = start.line - prev_line;
+        let delta_start = if delta_line == 0 {
+            start.col - prev_start
+        } else {
+            start.col
+        };
+
+        data.push(lsp_types::SemanticToken {
+            delta_line,
+            delta_start,
+            length: end.col - start.col,
+            token_type: token_type_index(token.kind),
+            token_modifiers_bitset: token.modifiers.iter().fold(0, |bitset, modifier| {
+                bitset | 1 << token_modifier_index(*modifier)
+            }),
+        });
+
+        prev_line = start.line;
+        prev_start = start.col;
+    }
+
+    lsp_types::SemanticTokens {
+        result_id: None,
+        data,
+    }
+}
+
+fn token_type_index(kind: SemanticTokenKind) -> u32 {
+    match kind {
+        SemanticTokenKind::Function => 0,
+        SemanticTokenKind::Macro => 1,
+        SemanticTokenKind::Parameter => 2,
+        SemanticTokenKind::Provider => 3,
+        SemanticTokenKind::Rule => 4,
+        SemanticTokenKind::Variable => 5,
+    }
+}
+
+fn token_modifier_index(modifier: starpls_ide::SemanticTokenModifier) -> u32 {
+    match modifier {
+        starpls_ide::SemanticTokenModifier::Builtin => 0,
+        starpls_ide::SemanticTokenModifier::Declaration => 1,
+        starpls_ide::SemanticTokenModifier::Deprecated => 2,
+        starpls_ide::SemanticTokenModifier::Readonly => 3,
+        starpls_ide::SemanticTokenModifier::Unused => 4,
+    }
+}
@@ -1,5 +1,4 @@
-use std::fs;
-use std::mem;
+use std::iter;
 use std::panic;
 use std::path::Path;
 use std::path::PathBuf;
@@ -11,215 +10,269 @@ use log::error;
 use log::info;
 use lsp_server::Connection;
 use lsp_server::ReqQueue;
+use lsp_types::notification::DidChangeConfiguration;
+use lsp_types::notification::DidChangeWatchedFiles;
+use lsp_types::notification::Notification;
 use parking_lot::RwLock;
 use rustc_hash::FxHashSet;
 use starpls_bazel::build_language::decode_rules;
-use starpls_bazel::client::BazelCLI;
 use starpls_bazel::client::BazelClient;
 use starpls_bazel::decode_builtins;
 use starpls_bazel::APIContext;
 use starpls_bazel::Builtins;
-use starpls_common::Dialect;
 use starpls_common::FileId;
 use starpls_common::FileInfo;
-use starpls_ide::Analysis;
 use starpls_ide::AnalysisSnapshot;
+use starpls_ide::CancellationFlag;
 use starpls_ide::Change;
-use starpls_ide::InferenceOptions;
 
 use crate::bazel::BazelContext;
+use crate::config::ClientSettings;
 use crate::config::ServerConfig;
+use crate::config::CONFIGURATION_SECTION;
 use crate::debouncer::AnalysisDebouncer;
 use crate::diagnostics::DiagnosticsManager;
-use crate::document::DefaultFileLoader;
+use crate::dispatcher::InFlightRequests;
 use crate::document::DocumentChangeKind;
 use crate::document::DocumentManager;
+use crate::document::DocumentSource;
 use crate::document::PathInterner;
-use crate::event_loop::FetchExternalReposProgress;
-use crate::event_loop::RefreshAllWorkspaceTargetsProgress;
 use crate::event_loop::Task;
+use crate::symbol_indexer::SymbolIndexer;
 use crate::task_pool::TaskPool;
 use crate::task_pool::TaskPoolHandle;
+use crate::workspace::workspace_file_dialect_and_info;
+use crate::workspace::Workspace;
+use crate::workspace::WorkspaceFile;
+
+/// Handles the client's response to a request sent by the server.
+pub(crate) type ResponseHandler = fn(&mut Server, lsp_server::Response);
 
 const BAZEL_INIT_ERR_MESSAGE: &str = "Failed to fetch Bazel configuration! Please check the language server logs for more details. Certain features may not work correctly until the underlying issue is fixed.";
+const BAZEL_PATH_CHANGED_MESSAGE: &str =
+    "Changing the path to the Bazel executable requires restarting the language server.";
+const WATCHED_FILE_PATTERNS: &[&str] = &[
+    "**/*.bzl",
+    "**/BUILD",
+    "**/BUILD.bazel",
+    "**/MODULE.bazel",
+    "**/*.MODULE.bazel",
+    "**/WORKSPACE",
+    "**/WORKSPACE.bazel",
+    "**/WORKSPACE.bzlmod",
+];
 
 pub(crate) struct Server {
     pub(crate) config: Arc<ServerConfig>,
     pub(crate) connection: Connection,
-    pub(crate) req_queue: ReqQueue<(), ()>,
+    pub(crate) req_queue: ReqQueue<(), ResponseHandler>,
     pub(crate) task_pool_handle: TaskPoolHandle<Task>,
+    pub(crate) path_interner: Arc<PathInterner>,
     pub(crate) document_manager: Arc<RwLock<DocumentManager>>,
     pub(crate) diagnostics_manager: DiagnosticsManager,
-    pub(crate) analysis: Analysis,
+    /// The workspaces opened by the client. There is always at least one workspace.
+    pub(crate) workspaces: Vec<Workspace>,
     pub(crate) analysis_debouncer: AnalysisDebouncer,
     pub(crate) analysis_requested_for_files: Option<Vec<FileId>>,
-    pub(crate) bazel_client: Arc<dyn BazelClient>,
-    pub(crate) pending_repos: FxHashSet<String>,
-    pub(crate) pending_files: FxHashSet<FileId>,
     pub(crate) force_analysis_for_files: FxHashSet<FileId>,
-    pub(crate) fetched_repos: FxHashSet<String>,
-    pub(crate) is_fetching_repos: bool,
-    pub(crate) is_refreshing_all_workspace_targets: bool,
-    pub(crate) bzlmod_enabled: bool,
+    pub(crate) in_flight_requests: InFlightRequests,
 }
 
 pub(crate) struct ServerSnapshot {
     pub(crate) config: Arc<ServerConfig>,
+    /// The folder and root of the workspace, see [`Workspace`].
+    pub(crate) folder: PathBuf,
+    pub(crate) root: PathBuf,
     pub(crate) analysis_snapshot: AnalysisSnapshot,
     pub(crate) document_manager: Arc<RwLock<DocumentManager>>,
+    pub(crate) symbol_indexer: Arc<SymbolIndexer>,
+    /// Snapshots of the remaining workspaces, for requests that aren't tied to a single
+    /// document, e.g. `workspace/symbol`. This is empty for all other requests.
+    pub(crate) other_workspaces: Vec<ServerSnapshot>,
+}
+
+impl ServerSnapshot {
+    /// Returns the snapshots of all workspaces included in this snapshot.
+    pub(crate) fn workspaces(&self) -> impl Iterator<Item = &ServerSnapshot> {
+        iter::once(self).chain(&self.other_workspaces)
+    }
 }
 
 impl Server {
-    pub(crate) fn new(connection: Connection, config: ServerConfig) -> anyhow::Result<Self> {
+    pub(crate) fn new(
+        connection: Connection,
+        config: ServerConfig,
+        workspace_folders: Vec<PathBuf>,
+    ) -> anyhow::Result<Self> {
         // Create the task pool for processing incoming requests.
         let (task_pool_sender, task_pool_receiver) = crossbeam_channel::unbounded();
         let task_pool = TaskPool::with_num_threads(task_pool_sender.clone(), 4)?;
         let task_pool_handle = TaskPoolHandle::new(task_pool_receiver, task_pool);
 
-        // Check if the user specified a path to the Bazel executable.
-        let bazel_path = config
-            .args
-            .bazel_path
-            .clone()
-            .unwrap_or("bazel".to_string());
-
-        debug!(
-            "fetching Bazel configuration using Bazel executable at {:?}",
-            bazel_path
-        );
-
-        // Determine Bazel configuration.
-        let mut has_bazel_init_err = false;
-        let bazel_client = Arc::new(BazelCLI::new(&bazel_path));
-        let bazel_cx = match BazelContext::new(&*bazel_client) {
-            Ok(cx) => cx,
-            Err(err) => {
-                has_bazel_init_err = true;
-                error!("failed to initialize Bazel context: {}", err);
-                Default::default()
-            }
-        };
-
-        // Query for all targets in the current workspace, to use for label completion.
-        let targets = if config.args.enable_label_completions {
-            debug!("querying for all targets in the current workspace");
-            match bazel_client.query_all_workspace_targets() {
-                Ok(targets) => {
-                    debug!("successfully queried for all targets");
-                    targets
-                }
-                Err(err) => {
-                    error!("failed to query all workspace targets: {}", err);
-                    has_bazel_init_err = true;
-                    Default::default()
-                }
-            }
-        } else {
-            Default::default()
-        };
-
+        // File IDs are shared between workspaces, since the same file can be part of several
+        // workspaces, e.g. with nested workspaces.
         let path_interner = Arc::new(PathInterner::default());
-        let loader = DefaultFileLoader::new(
-            bazel_client.clone(),
-            path_interner.clone(),
-            bazel_cx.info.workspace.clone(),
-            bazel_cx.info.workspace_name,
-            bazel_cx.info.output_base.join("external"),
-            task_pool_sender.clone(),
-            bazel_cx.bzlmod_enabled,
-        );
-        let mut analysis = Analysis::new(
-            Arc::new(loader),
-            InferenceOptions {
-                infer_ctx_attributes: config.args.inference_options.infer_ctx_attributes,
-                use_code_flow_analysis: config.args.inference_options.use_code_flow_analysis,
-                ..Default::default()
-            },
-        );
-
-        analysis.set_all_workspace_targets(targets);
-        analysis.set_builtin_defs(load_bazel_builtins(), bazel_cx.rules);
-
-        // Check for a prelude file. We skip verifying that `//tools/build_tools` is actually a package (i.e.
-        // that it actually contains a `BUILD.bazel`) file for simplicity.
-        if let Ok((prelude, contents)) = load_bazel_prelude(&bazel_cx.info.workspace) {
-            info!("found prelude file at {:?}", prelude);
-            let file_id = path_interner.intern_path(prelude);
-            let mut change = Change::default();
-            change.create_file(
-                file_id,
-                Dialect::Bazel,
-                Some(FileInfo::Bazel {
-                    api_context: APIContext::Bzl,
-                    is_external: false,
-                }),
-                contents,
-            );
-            analysis.apply_change(change);
-            analysis.set_bazel_prelude_file(file_id);
-        }
+        let mut has_bazel_init_err = false;
+        let workspaces = workspace_folders
+            .into_iter()
+            .map(|folder| {
+                let (workspace, has_err) = Workspace::new(
+                    folder,
+                    &config,
+                    path_interner.clone(),
+                    task_pool_sender.clone(),
+                );
+                has_bazel_init_err |= has_err;
+                workspace
+            })
+            .collect::<Vec<_>>();
+        let workspace_roots = workspaces
+            .iter()
+            .map(|workspace| workspace.root.clone())
+            .collect();
 
         let analysis_debounce_interval = config.args.analysis_debounce_interval;
-        let server = Server {
+        let mut server = Server {
             config: Arc::new(config),
             connection,
             req_queue: Default::default(),
             task_pool_handle,
+            path_interner: path_interner.clone(),
             document_manager: Arc::new(RwLock::new(DocumentManager::new(
                 path_interner,
-                bazel_cx.info.workspace,
+                workspace_roots,
             ))),
             diagnostics_manager: Default::default(),
-            analysis,
+            workspaces,
             analysis_debouncer: AnalysisDebouncer::new(
                 Duration::from_millis(analysis_debounce_interval),
                 task_pool_sender,
             ),
             analysis_requested_for_files: None,
-            bazel_client,
-            pending_repos: Default::default(),
-            pending_files: Default::default(),
             force_analysis_for_files: Default::default(),
-            fetched_repos: Default::default(),
-            is_fetching_repos: false,
-            is_refreshing_all_workspace_targets: false,
-            bzlmod_enabled: bazel_cx.bzlmod_enabled,
+            in_flight_requests: Default::default(),
         };
 
         if has_bazel_init_err {
             server.send_error_message(BAZEL_INIT_ERR_MESSAGE);
         }
 
+        for workspace in &mut server.workspaces {
+            workspace.refresh_bazel_context(&server.task_pool_handle);
+        }
+        for workspace in &server.workspaces {
+            server.index_workspace_symbols(workspace);
+        }
+
         Ok(server)
     }
 
-    pub(crate) fn snapshot(&self) -> ServerSnapshot {
+    /// Creates a snapshot for evaluating a request about the given path, or about all
+    /// workspaces if no path is given. See [`starpls_ide::Analysis::cancellable_snapshot`].
+    pub(crate) fn request_snapshot(
+        &self,
+        path: Option<&Path>,
+        cancellation_flag: &CancellationFlag,
+        is_latency_sensitive: bool,
+    ) -> ServerSnapshot {
+        let snapshot = |workspace: &Workspace| {
+            self.make_snapshot(
+                workspace,
+                workspace
+                    .analysis
+                    .cancellable_snapshot(cancellation_flag.clone(), is_latency_sensitive),
+            )
+        };
+        match path {
+            Some(path) => snapshot(&self.workspaces[self.workspace_index(path)]),
+            None => {
+                let mut snapshots = self.workspaces.iter().map(snapshot);
+                let mut snapshot = snapshots.next().expect("no workspaces");
+                snapshot.other_workspaces = snapshots.collect();
+                snapshot
+            }
+        }
+    }
+
+    /// Creates a snapshot for background work in the given workspace, which is aborted once
+    /// the workspace is removed.
+    pub(crate) fn workspace_snapshot(&self, workspace: &Workspace) -> ServerSnapshot {
+        self.make_snapshot(
+            workspace,
+            workspace
+                .analysis
+                .cancellable_snapshot(workspace.cancellation_flag.clone(), false),
+        )
+    }
+
+    fn make_snapshot(
+        &self,
+        workspace: &Workspace,
+        analysis_snapshot: AnalysisSnapshot,
+    ) -> ServerSnapshot {
         ServerSnapshot {
             config: self.config.clone(),
-            analysis_snapshot: self.analysis.snapshot(),
+            folder: workspace.folder.clone(),
+            root: workspace.root.clone(),
+            analysis_snapshot,
             document_manager: Arc::clone(&self.document_manager),
+            symbol_indexer: Arc::clone(&workspace.symbol_indexer),
+            other_workspaces: Vec::new(),
         }
     }
 
+    /// Returns the index of the innermost workspace containing the given path. Paths outside
+    /// of all workspaces belong to the first workspace.
+    pub(crate) fn workspace_index(&self, path: &Path) -> usize {
+        self.workspaces
+            .iter()
+            .enumerate()
+            .filter_map(|(index, workspace)| Some((index, workspace.match_path(path)?)))
+            .max_by_key(|(_, len)| *len)
+            .map(|(index, _)| index)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn workspace_for_path_mut(&mut self, path: &Path) -> &mut Workspace {
+        let index = self.workspace_index(path);
+        &mut self.workspaces[index]
+    }
+
+    pub(crate) fn workspace_for_folder_mut(&mut self, folder: &Path) -> Option<&mut Workspace> {
+        self.workspaces
+            .iter_mut()
+            .find(|workspace| workspace.folder == folder)
+    }
+
     pub(crate) fn process_changes(&mut self) -> (Vec<FileId>, bool) {
-        let mut change = Change::default();
+        let mut changes = self
+            .workspaces
+            .iter()
+            .map(|_| Change::default())
+            .collect::<Vec<_>>();
         let mut document_manager = self.document_manager.write();
-        let (has_opened_or_closed_documents, changes) = document_manager.take_changes();
-        let changed_file_ids = changes.iter().map(|(file_id, _)| *file_id).collect();
+        let (has_opened_or_closed_documents, document_changes) = document_manager.take_changes();
+        let changed_file_ids = document_changes
+            .iter()
+            .map(|(file_id, _)| *file_id)
+            .collect();
 
-        if changes.is_empty() && self.force_analysis_for_files.is_empty() {
+        if document_changes.is_empty() && self.force_analysis_for_files.is_empty() {
             return (changed_file_ids, has_opened_or_closed_documents);
         }
 
-        let mut prelude_file = None;
+        let mut prelude_files = Vec::new();
 
-        for (file_id, change_kind) in changes {
+        for (file_id, change_kind) in document_changes {
             let document = match document_manager.get(file_id) {
                 Some(document) => document,
                 None => continue,
             };
             match change_kind {
                 DocumentChangeKind::Create => {
+                    // Documents are opened in the workspace that contains them.
+                    let path = document_manager.lookup_by_file_id(file_id);
+                    let index = self.workspace_index(&path);
                     if matches!(
                         document.info,
                         Some(FileInfo::Bazel {
@@ -227,10 +280,19 @@ impl Server {
                             ..
                         })
                     ) {
-                        prelude_file = Some(file_id)
+                        prelude_files.push((index, file_id));
+                    }
+
+                    if let Some(FileInfo::Bazel { api_context, .. }) = &document.info {
+                        self.workspaces[index].symbol_indexer.index_file(
+                            file_id,
+                            &path,
+                            &document.contents,
+                            api_context.clone(),
+                        );
                     }
 
-                    change.create_file(
+                    changes[index].create_file(
                         file_id,
                         document.dialect,
                         document.info.clone(),
@@ -238,32 +300,201 @@ impl Server {
                     );
                 }
                 DocumentChangeKind::Update => {
-                    change.update_file(file_id, document.contents.clone());
+                    // Other workspaces might have loaded the file as well, e.g. with nested
+                    // workspaces. Updating a file is a no-op for workspaces that haven't.
+                    for change in &mut changes {
+                        change.update_file(file_id, document.contents.clone());
+                    }
                 }
             }
         }
 
         drop(document_manager);
 
-        // Apply the change to our analyzer. This will cancel any affected active Salsa operations.
-        self.analysis.apply_change(change);
-        if let Some(prelude_file) = prelude_file {
-            self.analysis.set_bazel_prelude_file(prelude_file);
+        // Apply the changes to our analyzers. This will cancel any affected active Salsa operations.
+        for (workspace, change) in self.workspaces.iter_mut().zip(changes) {
+            workspace.analysis.apply_change(change);
+        }
+        for (index, prelude_file) in prelude_files {
+            self.workspaces[index]
+                .analysis
+                .set_bazel_prelude_file(prelude_file);
         }
 
         (changed_file_ids, true)
     }
 
+    /// Cancels a request on behalf of the client. Requests that haven't started yet are simply
+    /// skipped, while running requests have their in-flight type inference aborted.
+    pub(crate) fn cancel_request(&mut self, id: lsp_server::RequestId) {
+        let Some(resp) = self.req_queue.incoming.cancel(id.clone()) else {
+            return;
+        };
+        self.send(resp.into());
+        self.in_flight_requests.cancel(&id);
+    }
+
+    /// Adds a workspace folder opened by the client.
+    pub(crate) fn add_workspace(&mut self, folder: PathBuf) {
+        if self.workspace_for_folder_mut(&folder).is_some() {
+            return;
+        }
+
+        info!("adding workspace folder {:?}", folder);
+        let (mut workspace, has_bazel_init_err) = Workspace::new(
+            folder,
+            &self.config,
+            self.path_interner.clone(),
+            self.task_pool_handle.sender(),
+        );
+        if has_bazel_init_err {
+            self.send_error_message(BAZEL_INIT_ERR_MESSAGE);
+        }
+        workspace.refresh_bazel_context(&self.task_pool_handle);
+        self.index_workspace_symbols(&workspace);
+        self.workspaces.push(workspace);
+        self.reopen_documents();
+
+        if self.config.should_analyze_workspace() {
+            self.analyze_workspace(self.workspaces.len() - 1);
+        }
+    }
+
+    /// Applies a refreshed Bazel configuration to the given workspace folder. If files and labels
+    /// are resolved differently now, e.g. because the output base moved, the workspace is set up
+    /// anew.
+    pub(crate) fn update_bazel_context(&mut self, folder: &Path, bazel_cx: BazelContext) {
+        let Some(index) = self
+            .workspaces
+            .iter()
+            .position(|workspace| workspace.folder == folder)
+        else {
+            return;
+        };
+
+        let workspace = &mut self.workspaces[index];
+        if workspace.has_same_layout(&bazel_cx) {
+            workspace
+                .bazel_client
+                .set_repo_mappings(bazel_cx.repo_mappings);
+            workspace
+                .analysis
+                .set_builtin_defs(load_bazel_builtins(), bazel_cx.rules);
+            self.force_analysis_for_files
+                .extend(self.document_manager.read().file_ids());
+            return;
+        }
+
+        info!("setting up workspace folder {:?} again", folder);
+        self.cancel_workspace_analysis(index);
+        let workspace = &self.workspaces[index];
+        workspace.cancellation_flag.cancel();
+        let bazel_client = workspace.bazel_client.clone();
+        let bazel_cache = workspace.bazel_cache.clone();
+        self.workspaces[index] = Workspace::with_bazel_context(
+            folder.to_path_buf(),
+            &self.config,
+            self.path_interner.clone(),
+            self.task_pool_handle.sender(),
+            bazel_client,
+            bazel_cx,
+            bazel_cache,
+        );
+        self.index_workspace_symbols(&self.workspaces[index]);
+        self.reopen_documents();
+        self.refresh_all_workspace_targets(Some(folder));
+        if self.config.should_analyze_workspace() {
+            self.analyze_workspace(index);
+        }
+    }
+
+    /// Removes a workspace folder closed by the client. The last remaining workspace is kept
+    /// around, since documents outside of all workspaces are analyzed with it.
+    pub(crate) fn remove_workspace(&mut self, folder: &Path) {
+        if self.workspaces.len() == 1 {
+            return;
+        }
+
+        info!("removing workspace folder {:?}", folder);
+        if let Some(index) = self
+            .workspaces
+            .iter()
+            .position(|workspace| workspace.folder == folder)
+        {
+            self.cancel_workspace_analysis(index);
+        }
+        self.workspaces.retain(|workspace| {
+            if workspace.folder != folder {
+                return true;
+            }
+            workspace.cancellation_flag.cancel();
+            false
+        });
+        self.reopen_documents();
+    }
+
+    /// Opens the current documents in their containing workspaces after the set of workspaces
+    /// changed.
+    fn reopen_documents(&mut self) {
+        let mut document_manager = self.document_manager.write();
+        document_manager.set_workspace_roots(
+            self.workspaces
+                .iter()
+                .map(|workspace| workspace.root.clone())
+                .collect(),
+        );
+
+        let mut changes = self
+            .workspaces
+            .iter()
+            .map(|_| Change::default())
+            .collect::<Vec<_>>();
+        for file_id in document_manager.file_ids() {
+            let Some(document) = document_manager.get(file_id) else {
+                continue;
+            };
+            let index = self.workspace_index(&document_manager.lookup_by_file_id(file_id));
+            changes[index].create_file(
+                file_id,
+                document.dialect,
+                document.info.clone(),
+                document.contents.clone(),
+            );
+        }
+        for (workspace, change) in self.workspaces.iter_mut().zip(changes) {
+            workspace.analysis.apply_change(change);
+        }
+        self.force_analysis_for_files
+            .extend(document_manager.file_ids());
+    }
+
+    /// Indexes the workspace in the background for `workspace/symbol`.
+    fn index_workspace_symbols(&self, workspace: &Workspace) {
+        let symbol_indexer = workspace.symbol_indexer.clone();
+        self.task_pool_handle
+            .spawn_with_sender(move |_| symbol_indexer.index_workspace());
+    }
+
     pub(crate) fn send_request<R: lsp_types::request::Request>(&mut self, params: R::Params) {
+        self.send_request_with_handler::<R>(params, |_, _| {});
+    }
+
+    pub(crate) fn send_request_with_handler<R: lsp_types::request::Request>(
+        &mut self,
+        params: R::Params,
+        handler: ResponseHandler,
+    ) {
         let req = self
             .req_queue
             .outgoing
-            .register(R::METHOD.to_string(), params, ());
+            .register(R::METHOD.to_string(), params, handler);
         self.send(req.into());
     }
 
     pub(crate) fn complete_request(&mut self, resp: lsp_server::Response) {
-        self.req_queue.outgoing.complete(resp.id);
+        if let Some(handler) = self.req_queue.outgoing.complete(resp.id.clone()) {
+            handler(self, resp);
+        }
     }
 
     pub(crate) fn send_notification<N: lsp_types::notification::Notification>(
@@ -287,76 +518,321 @@ impl Server {
         )
     }
 
-    pub(crate) fn fetch_bazel_external_repos(&mut self) {
-        let repos = mem::take(&mut self.pending_repos);
-        let files = mem::take(&mut self.pending_files);
-        let bazel_client = self.bazel_client.clone();
-        let bzlmod_enabled = self.bzlmod_enabled;
-
-        self.is_fetching_repos = true;
-        self.fetched_repos.extend(repos.clone());
-        self.task_pool_handle.spawn_with_sender(move |sender| {
-            sender
-                .send(Task::FetchExternalRepos(FetchExternalReposProgress::Begin(
-                    repos.clone(),
-                )))
-                .unwrap();
-
-            let mut failed_repos = vec![];
-
-            for repo in &repos {
-                debug!("fetching external repository \"@@{}\"", repo);
-                if let Err(err) = if bzlmod_enabled {
-                    bazel_client.fetch_repo(repo)
-                } else {
-                    bazel_client.null_query_external_repo_targets(repo)
-                } {
-                    failed_repos.push(repo.clone());
-                    error!(
-                        "failed to fetch external repository \"@@{}\": {}",
-                        repo, err
-                    );
+    pub(crate) fn initialized(&mut self) {
+        // Clients only send `workspace/didChangeConfiguration` for the pull model if the server
+        // registers for it.
+        if self
+            .config
+            .has_did_change_configuration_dynamic_registration_support()
+        {
+            self.send_request::<lsp_types::request::RegisterCapability>(
+                lsp_types::RegistrationParams {
+                    registrations: vec![lsp_types::Registration {
+                        id: DidChangeConfiguration::METHOD.to_string(),
+                        method: DidChangeConfiguration::METHOD.to_string(),
+                        register_options: None,
+                    }],
+                },
+            );
+        }
+        // Watch for changes to files that aren't open in the editor, e.g. after switching
+        // branches.
+        if self
+            .config
+            .has_did_change_watched_files_dynamic_registration_support()
+        {
+            let watchers = WATCHED_FILE_PATTERNS
+                .iter()
+                .map(|pattern| lsp_types::FileSystemWatcher {
+                    glob_pattern: lsp_types::GlobPattern::String(pattern.to_string()),
+                    kind: None,
+                })
+                .collect();
+            self.send_request::<lsp_types::request::RegisterCapability>(
+                lsp_types::RegistrationParams {
+                    registrations: vec![lsp_types::Registration {
+                        id: DidChangeWatchedFiles::METHOD.to_string(),
+                        method: DidChangeWatchedFiles::METHOD.to_string(),
+                        register_options: serde_json::to_value(
+                            lsp_types::DidChangeWatchedFilesRegistrationOptions { watchers },
+                        )
+                        .ok(),
+                    }],
+                },
+            );
+        }
+        if self.config.has_configuration_support() {
+            self.pull_configuration();
+        }
+        if self.config.should_analyze_workspace() {
+            self.analyze_workspaces();
+        }
+    }
+
+    /// Requests the current settings from the client with `workspace/configuration`.
+    pub(crate) fn pull_configuration(&mut self) {
+        self.send_request_with_handler::<lsp_types::request::WorkspaceConfiguration>(
+            lsp_types::ConfigurationParams {
+                items: vec![lsp_types::ConfigurationItem {
+                    scope_uri: None,
+                    section: Some(CONFIGURATION_SECTION.to_string()),
+                }],
+            },
+            |server, resp| {
+                if let Some(err) = resp.error {
+                    error!("failed to pull configuration: {}", err.message);
+                    return;
+                }
+                let settings = resp
+                    .result
+                    .and_then(|result| {
+                        serde_json::from_value::<Vec<serde_json::Value>>(result).ok()
+                    })
+                    .and_then(|values| values.into_iter().next())
+                    .map(ClientSettings::from_json);
+                match settings {
+                    Some(Ok(settings)) => server.update_settings(settings),
+                    Some(Err(err)) => error!("failed to parse configuration: {}", err),
+                    None => {}
+                }
+            },
+        );
+    }
+
+    /// Applies settings changed by the client at runtime. Inference options take effect
+    /// immediately, with diagnostics for open documents being recomputed.
+    pub(crate) fn update_settings(&mut self, settings: ClientSettings) {
+        debug!("updating settings: {:?}", settings);
+        let prev_config = Arc::clone(&self.config);
+        let mut config = (*self.config).clone();
+        config.apply_settings(settings);
+        self.config = Arc::new(config);
+
+        if prev_config.args.bazel_path != self.config.args.bazel_path {
+            self.send_notification::<lsp_types::notification::ShowMessage>(
+                lsp_types::ShowMessageParams {
+                    message: BAZEL_PATH_CHANGED_MESSAGE.to_string(),
+                    typ: lsp_types::MessageType::WARNING,
+                },
+            );
+        }
+
+        if prev_config.args.analysis_debounce_interval
+            != self.config.args.analysis_debounce_interval
+        {
+            self.analysis_debouncer = AnalysisDebouncer::new(
+                Duration::from_millis(self.config.args.analysis_debounce_interval),
+                self.task_pool_handle.sender(),
+            );
+        }
+
+        if prev_config.args.inference_options != self.config.args.inference_options {
+            for workspace in &mut self.workspaces {
+                workspace
+                    .analysis
+                    .set_inference_options(self.config.inference_options());
+            }
+            self.force_analysis_for_files
+                .extend(self.document_manager.read().file_ids());
+        }
+
+        match (
+            prev_config.should_analyze_workspace(),
+            self.config.should_analyze_workspace(),
+        ) {
+            (false, true) => self.analyze_workspaces(),
+            (true, false) => {
+                for index in 0..self.workspaces.len() {
+                    self.cancel_workspace_analysis(index);
                 }
+                self.clear_workspace_diagnostics();
             }
+            _ => {}
+        }
 
-            sender
-                .send(Task::FetchExternalRepos(FetchExternalReposProgress::End(
-                    files,
-                    failed_repos,
-                )))
-                .unwrap();
-        });
+        if prev_config.args.enable_label_completions != self.config.args.enable_label_completions {
+            if self.config.args.enable_label_completions {
+                self.refresh_all_workspace_targets(None);
+            } else {
+                for workspace in &mut self.workspaces {
+                    workspace.analysis.set_all_workspace_targets(Vec::new());
+                }
+            }
+        }
     }
 
-    pub(crate) fn refresh_all_workspace_targets(&mut self) {
-        if self.is_refreshing_all_workspace_targets || !self.config.args.enable_label_completions {
-            return;
+    /// Clears the diagnostics published while analyzing the workspaces, i.e. those for files
+    /// that aren't open in the editor.
+    fn clear_workspace_diagnostics(&mut self) {
+        let document_manager = self.document_manager.read();
+        let uris = self
+            .diagnostics_manager
+            .retain(|file_id| {
+                matches!(
+                    document_manager
+                        .get(file_id)
+                        .map(|document| document.source),
+                    Some(DocumentSource::Editor(_))
+                )
+            })
+            .into_iter()
+            .filter_map(|file_id| {
+                lsp_types::Url::from_file_path(document_manager.lookup_by_file_id(file_id)).ok()
+            })
+            .collect::<Vec<_>>();
+        drop(document_manager);
+
+        for uri in uris {
+            self.send_notification::<lsp_types::notification::PublishDiagnostics>(
+                lsp_types::PublishDiagnosticsParams {
+                    uri,
+                    diagnostics: Vec::new(),
+                    version: None,
+                },
+            );
         }
+    }
 
-        let bazel_client = self.bazel_client.clone();
+    /// Fetches the pending external repositories for each workspace that isn't already
+    /// fetching repositories.
+    pub(crate) fn fetch_bazel_external_repos(&mut self) {
+        for workspace in &mut self.workspaces {
+            if !workspace.pending_repos.is_empty() && !workspace.is_fetching_repos {
+                workspace.fetch_external_repos(&self.task_pool_handle);
+            }
+        }
+    }
 
-        self.is_refreshing_all_workspace_targets = true;
-        self.task_pool_handle.spawn_with_sender(move |sender| {
-            sender
-                .send(Task::RefreshAllWorkspaceTargets(
-                    RefreshAllWorkspaceTargetsProgress::Begin,
-                ))
-                .unwrap();
+    /// Starts analyzing all files in each workspace that isn't already being analyzed.
+    pub(crate) fn analyze_workspaces(&mut self) {
+        for index in 0..self.workspaces.len() {
+            self.analyze_workspace(index);
+        }
+    }
 
-            let targets = match bazel_client.query_all_workspace_targets() {
-                Ok(targets) => Some(targets),
-                Err(err) => {
-                    error!("failed to query all workspace targets: {}", err);
-                    None
-                }
+    /// Starts analyzing all files in the given workspace, unless it's already being analyzed.
+    fn analyze_workspace(&mut self, index: usize) {
+        if self.workspaces[index].analyze(&self.task_pool_handle) {
+            self.begin_workspace_analysis(index);
+        }
+    }
+
+    /// Adds the given files in a workspace folder to its analysis, e.g. the files found while
+    /// walking the folder, and queues them for collecting diagnostics. Returns the number of
+    /// queued files.
+    pub(crate) fn add_workspace_files(
+        &mut self,
+        folder: &Path,
+        files: Vec<WorkspaceFile>,
+    ) -> usize {
+        let Some(index) = self
+            .workspaces
+            .iter()
+            .position(|workspace| workspace.folder == folder)
+        else {
+            return 0;
+        };
+
+        let document_manager = self.document_manager.read();
+        let mut change = Change::default();
+        let mut file_ids = Vec::new();
+        for file in files {
+            // Files in nested workspaces are analyzed by those workspaces instead.
+            if self.workspace_index(&file.path) != index {
+                continue;
+            }
+
+            // Open documents and files loaded by other files are already part of the analysis.
+            let file_id = self.path_interner.intern_path(file.path);
+            if document_manager.get(file_id).is_none()
+                && self.workspaces[index].loader.mark_loaded(file_id)
+            {
+                change.create_file(file_id, file.dialect, file.info, file.contents);
+            }
+            file_ids.push(file_id);
+        }
+        drop(document_manager);
+
+        let num_files = file_ids.len();
+        let workspace = &mut self.workspaces[index];
+        workspace.analysis.apply_change(change);
+        if let Some(analysis) = &mut workspace.workspace_analysis {
+            analysis.pending_files.extend(file_ids);
+            analysis.num_files += num_files;
+        }
+        num_files
+    }
+
+    /// Adds a file that was created on disk to the analysis of its workspace, if that
+    /// workspace is being analyzed.
+    pub(crate) fn add_created_workspace_file(&mut self, path: PathBuf, contents: String) {
+        let workspace = &self.workspaces[self.workspace_index(&path)];
+
+        // While the workspace's files are still being walked, the walk picks up the new file.
+        if !workspace
+            .workspace_analysis
+            .as_ref()
+            .is_some_and(|analysis| analysis.num_files > 0)
+        {
+            return;
+        }
+        let Some((dialect, info)) = workspace_file_dialect_and_info(&workspace.root, &path) else {
+            return;
+        };
+
+        let folder = workspace.folder.clone();
+        self.add_workspace_files(
+            &folder,
+            vec![WorkspaceFile {
+                path,
+                dialect,
+                info,
+                contents,
+            }],
+        );
+    }
+
+    /// Collects diagnostics for the files pending analysis in each workspace that is being
+    /// analyzed. This is done after applying changes, so that the snapshots are up to date.
+    pub(crate) fn collect_workspace_diagnostics(&mut self) {
+        for index in 0..self.workspaces.len() {
+            let workspace = &self.workspaces[index];
+            let Some(analysis) = &workspace.workspace_analysis else {
+                continue;
             };
+            if analysis.is_collecting_diagnostics || analysis.pending_files.is_empty() {
+                continue;
+            }
 
-            sender
-                .send(Task::RefreshAllWorkspaceTargets(
-                    RefreshAllWorkspaceTargetsProgress::End(targets),
-                ))
-                .unwrap();
-        });
+            // Collecting stops as soon as this run of the analysis is cancelled.
+            let snapshot = self.make_snapshot(
+                workspace,
+                workspace
+                    .analysis
+                    .cancellable_snapshot(analysis.cancellation_flag.clone(), false),
+            );
+            self.workspaces[index].collect_diagnostics(snapshot, &self.task_pool_handle);
+        }
+    }
+
+    /// Refreshes the targets used for label completion in the workspace containing the given
+    /// path, or in all workspaces if no path is given.
+    pub(crate) fn refresh_all_workspace_targets(&mut self, path: Option<&Path>) {
+        if !self.config.args.enable_label_completions {
+            return;
+        }
+
+        match path {
+            Some(path) => {
+                let index = self.workspace_index(path);
+                self.workspaces[index].refresh_all_workspace_targets(&self.task_pool_handle);
+            }
+            None => {
+                for workspace in &mut self.workspaces {
+                    workspace.refresh_all_workspace_targets(&self.task_pool_handle);
+                }
+            }
+        }
     }
 }
 
@@ -373,9 +849,3 @@ pub(crate) fn load_bazel_build_language(client: &dyn BazelClient) -> anyhow::Res
     let build_language_output = client.build_language()?;
     decode_rules(&build_language_output)
 }
-
-fn load_bazel_prelude(workspace: impl AsRef<Path>) -> anyhow::Result<(PathBuf, String)> {
-    let prelude = workspace.as_ref().join("tools/build_rules/prelude_bazel");
-    let contents = fs::read_to_string(&prelude)?;
-    Ok((prelude, contents))
-}
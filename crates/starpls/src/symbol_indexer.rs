@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use log::debug;
+use parking_lot::RwLock;
+use starpls_bazel::APIContext;
+use starpls_common::Dialect;
+use starpls_common::FileId;
+use starpls_common::FileInfo;
+use starpls_ide::CancellationFlag;
+use starpls_ide::FileSymbols;
+use starpls_ide::LoadGraph;
+use starpls_ide::SymbolIndex;
+use walkdir::WalkDir;
+
+use crate::commands::check::is_hidden;
+use crate::document::dialect_and_api_context_for_workspace_path;
+use crate::document::PathInterner;
+
+/// Maintains the index of symbols used for `workspace/symbol`, along with the index of `load`
+/// statements used for finding references across files. Indexing reads files directly from disk,
+/// so the indexed files don't need to be loaded into the database.
+pub(crate) struct SymbolIndexer {
+    pub(crate) index: Arc<RwLock<SymbolIndex>>,
+    load_graph: Arc<LoadGraph>,
+    interner: Arc<PathInterner>,
+    workspace: PathBuf,
+    external_output_base: PathBuf,
+    bzlmod_enabled: bool,
+    /// Stops indexing once the workspace is removed.
+    cancellation_flag: CancellationFlag,
+}
+
+impl SymbolIndexer {
+    pub(crate) fn new(
+        interner: Arc<PathInterner>,
+        load_graph: Arc<LoadGraph>,
+        workspace: PathBuf,
+        external_output_base: PathBuf,
+        bzlmod_enabled: bool,
+        cancellation_flag: CancellationFlag,
+    ) -> Self {
+        Self {
+            index: Default::default(),
+            load_graph,
+            interner,
+            workspace,
+            external_output_base,
+            bzlmod_enabled,
+            cancellation_flag,
+        }
+    }
+
+    /// Indexes every BUILD and `.bzl` file in the workspace, along with those in any external
+    /// repositories that have already been fetched.
+    pub(crate) fn index_workspace(&self) {
+        if self.workspace.as_os_str().is_empty() {
+            return;
+        }
+
+        debug!("indexing workspace symbols in {:?}", self.workspace);
+        self.index_dir(&self.workspace);
+        if let Ok(entries) = fs::read_dir(&self.external_output_base) {
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.is_dir() {
+                    self.index_dir(&path);
+                }
+            }
+        }
+    }
+
+    /// Indexes a newly fetched external repository.
+    pub(crate) fn index_repo(&self, repo: &str) {
+        let root = self.external_output_base.join(repo);
+        debug!("indexing workspace symbols in {:?}", root);
+        self.index_dir(&root);
+    }
+
+    /// Re-indexes a single file, e.g. after it was opened or saved in the editor.
+    pub(crate) fn index_file(
+        &self,
+        file_id: FileId,
+        path: &Path,
+        contents: &str,
+        api_context: APIContext,
+    ) {
+        if !matches!(api_context, APIContext::Build | APIContext::Bzl) {
+            return;
+        }
+        if let Some(label) = self.label_for_path(path) {
+            let symbols = FileSymbols::new(file_id, contents, api_context, &label);
+            self.index.write().insert(file_id, symbols);
+        }
+        self.load_graph.index_file(
+            file_id,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context,
+                is_external: path.starts_with(&self.external_output_base),
+            }),
+            contents,
+        );
+    }
+
+    fn index_dir(&self, root: &Path) {
+        for entry in WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| !is_hidden(entry))
+            .flatten()
+        {
+            if self.cancellation_flag.is_cancelled() {
+                return;
+            }
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            let Some(api_context) = self.api_context_for_path(path) else {
+                continue;
+            };
+
+            // Files that are already indexed were either indexed from the editor, which has the
+            // most recent contents, or were already indexed from disk.
+            let file_id = self.interner.intern_path(path.to_path_buf());
+            if self.index.read().contains(file_id) {
+                continue;
+            }
+            if let Ok(contents) = fs::read_to_string(path) {
+                self.index_file(file_id, path, &contents, api_context);
+            }
+        }
+    }
+
+    /// Re-indexes a file given its path, e.g. after it was changed on disk.
+    pub(crate) fn index_path(&self, path: &Path, contents: &str) {
+        if let Some(api_context) = self.api_context_for_path(path) {
+            let file_id = self.interner.intern_path(path.to_path_buf());
+            self.index_file(file_id, path, contents, api_context);
+        }
+    }
+
+    /// Removes a file from the index, e.g. after it was deleted from disk.
+    pub(crate) fn remove_file(&self, file_id: FileId) {
+        self.index.write().remove(file_id);
+        self.load_graph.remove_file(file_id);
+    }
+
+    /// Returns the API context of the given file if it declares symbols that should be indexed.
+    fn api_context_for_path(&self, path: &Path) -> Option<APIContext> {
+        match dialect_and_api_context_for_workspace_path(&self.workspace, path)? {
+            (Dialect::Bazel, Some(api_context @ (APIContext::Build | APIContext::Bzl))) => {
+                Some(api_context)
+            }
+            _ => None,
+        }
+    }
+
+    /// Determines the label of the target `name` declared in the given BUILD file.
+    pub(crate) fn label_for_target(&self, build_file: &Path, name: &str) -> Option<String> {
+        let label = self.label_for_path(build_file)?;
+        let (package, _) = label.rsplit_once(':')?;
+        Some(format!("{}:{}", package, name))
+    }
+
+    /// Determines the label of the given file, e.g. `//foo:defs.bzl` or `@@repo//foo:BUILD.bazel`.
+    fn label_for_path(&self, path: &Path) -> Option<String> {
+        let (repo, path) = match path.strip_prefix(&self.workspace) {
+            Ok(path) => (String::new(), path),
+            Err(_) => {
+                let path = path.strip_prefix(&self.external_output_base).ok()?;
+                let mut components = path.components();
+                let repo = components.next()?.as_os_str().to_str()?;
+                let prefix = if self.bzlmod_enabled { "@@" } else { "@" };
+                (format!("{}{}", prefix, repo), components.as_path())
+            }
+        };
+        let package = path
+            .parent()?
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()?
+            .join("/");
+        let name = path.file_name()?.to_str()?;
+        Some(format!("{}//{}:{}", repo, package, name))
+    }
+}
@@ -3,9 +3,19 @@ use crossbeam_channel::Sender;
 use rayon::ThreadPool;
 use rayon::ThreadPoolBuilder;
 
+/// The number of threads reserved for latency-sensitive tasks, e.g. completions and hovers, so
+/// that they don't wait behind diagnostics or Bazel fetches.
+const NUM_LATENCY_SENSITIVE_THREADS: usize = 2;
+
+/// The number of threads for low-priority tasks, e.g. analyzing the whole workspace, so that
+/// they never take up the threads used for requests.
+const NUM_LOW_PRIORITY_THREADS: usize = 1;
+
 pub(crate) struct TaskPool<T> {
     sender: Sender<T>,
     inner: ThreadPool,
+    latency_sensitive: ThreadPool,
+    low_priority: ThreadPool,
 }
 
 impl<T> TaskPool<T> {
@@ -14,9 +24,17 @@ impl<T> TaskPool<T> {
         num_threads: usize,
     ) -> anyhow::Result<TaskPool<T>> {
         let thread_pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+        let latency_sensitive_thread_pool = ThreadPoolBuilder::new()
+            .num_threads(NUM_LATENCY_SENSITIVE_THREADS)
+            .build()?;
+        let low_priority_thread_pool = ThreadPoolBuilder::new()
+            .num_threads(NUM_LOW_PRIORITY_THREADS)
+            .build()?;
         Ok(TaskPool {
             sender,
             inner: thread_pool,
+            latency_sensitive: latency_sensitive_thread_pool,
+            low_priority: low_priority_thread_pool,
         })
     }
 
@@ -31,6 +49,28 @@ impl<T> TaskPool<T> {
         })
     }
 
+    fn spawn_latency_sensitive<F>(&self, f: F)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.latency_sensitive.spawn({
+            let sender = self.sender.clone();
+            move || sender.send(f()).unwrap()
+        })
+    }
+
+    fn spawn_low_priority<F>(&self, f: F)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.low_priority.spawn({
+            let sender = self.sender.clone();
+            move || sender.send(f()).unwrap()
+        })
+    }
+
     #[allow(unused)]
     fn spawn_with_sender<F>(&self, f: F)
     where
@@ -62,6 +102,27 @@ impl<T> TaskPoolHandle<T> {
         self.pool.spawn(f)
     }
 
+    /// Like [`TaskPoolHandle::spawn`], but runs the task on the threads reserved for
+    /// latency-sensitive tasks.
+    pub(crate) fn spawn_latency_sensitive<F>(&self, f: F)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.pool.spawn_latency_sensitive(f)
+    }
+
+    /// Like [`TaskPoolHandle::spawn`], but runs the task on the threads reserved for
+    /// low-priority tasks. Long-running work should be split into several tasks, so that
+    /// changes can be applied in between.
+    pub(crate) fn spawn_low_priority<F>(&self, f: F)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.pool.spawn_low_priority(f)
+    }
+
     #[allow(unused)]
     pub(crate) fn spawn_with_sender<F>(&self, f: F)
     where
@@ -70,4 +131,8 @@ impl<T> TaskPoolHandle<T> {
     {
         self.pool.spawn_with_sender(f)
     }
+
+    pub(crate) fn sender(&self) -> Sender<T> {
+        self.pool.sender.clone()
+    }
 }
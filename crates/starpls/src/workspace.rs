@@ -0,0 +1,606 @@
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crossbeam_channel::Sender;
+use log::debug;
+use log::error;
+use log::info;
+use rustc_hash::FxHashSet;
+use starpls_bazel::client::BazelCLI;
+use starpls_bazel::client::BazelClient;
+use starpls_bazel::APIContext;
+use starpls_common::Dialect;
+use starpls_common::FileId;
+use starpls_common::FileInfo;
+use starpls_ide::Analysis;
+use starpls_ide::CancellationFlag;
+use starpls_ide::Change;
+
+use crate::bazel::BazelCache;
+use crate::bazel::BazelContext;
+use crate::commands::check::walk_dir;
+use crate::config::ServerConfig;
+use crate::diagnostics::collect_diagnostics;
+use crate::document::dialect_and_api_context_for_workspace_path;
+use crate::document::DefaultFileLoader;
+use crate::document::PathInterner;
+use crate::event_loop::AnalyzeWorkspaceProgress;
+use crate::event_loop::FetchExternalReposProgress;
+use crate::event_loop::RefreshAllWorkspaceTargetsProgress;
+use crate::event_loop::RefreshBazelContextProgress;
+use crate::event_loop::Task;
+use crate::server::load_bazel_builtins;
+use crate::server::ServerSnapshot;
+use crate::symbol_indexer::SymbolIndexer;
+use crate::task_pool::TaskPoolHandle;
+
+/// The number of files to collect diagnostics for in a single task while analyzing a workspace.
+/// Progress is reported after each batch.
+const WORKSPACE_ANALYSIS_BATCH_SIZE: usize = 32;
+
+static NEXT_WORKSPACE_ANALYSIS_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A Bazel workspace opened by the client, i.e. one of its workspace folders. Each workspace has
+/// its own Bazel context, builtins and file loader, and therefore its own [`Analysis`].
+pub(crate) struct Workspace {
+    /// The workspace folder as reported by the client. This identifies the workspace.
+    pub(crate) folder: PathBuf,
+    /// The workspace root as reported by `bazel info`. This is empty if we failed to fetch the
+    /// Bazel configuration.
+    pub(crate) root: PathBuf,
+    pub(crate) workspace_name: Option<String>,
+    pub(crate) external_output_base: PathBuf,
+    pub(crate) analysis: Analysis,
+    pub(crate) loader: Arc<DefaultFileLoader>,
+    pub(crate) bazel_client: Arc<dyn BazelClient>,
+    pub(crate) bazel_cache: Option<BazelCache>,
+    /// The Bazel configuration the workspace was set up with if it was served from the cache,
+    /// until it is refreshed with [`Workspace::refresh_bazel_context`].
+    pub(crate) cached_bazel_cx: Option<BazelContext>,
+    pub(crate) bzlmod_enabled: bool,
+    pub(crate) symbol_indexer: Arc<SymbolIndexer>,
+    pub(crate) pending_repos: FxHashSet<String>,
+    pub(crate) pending_files: FxHashSet<FileId>,
+    pub(crate) fetched_repos: FxHashSet<String>,
+    pub(crate) is_fetching_repos: bool,
+    pub(crate) is_refreshing_all_workspace_targets: bool,
+    /// The progress of analyzing all files in the workspace, or `None` if the workspace isn't
+    /// currently being analyzed.
+    pub(crate) workspace_analysis: Option<WorkspaceAnalysis>,
+    /// Cancelled once the workspace is removed, which aborts its indexing and analysis in the
+    /// background.
+    pub(crate) cancellation_flag: CancellationFlag,
+}
+
+/// The state of analyzing all files in a workspace in the background, see
+/// [`Workspace::analyze`].
+pub(crate) struct WorkspaceAnalysis {
+    /// Identifies this run of the analysis. Events from earlier runs, e.g. ones that were
+    /// cancelled because the workspace was set up again, are dropped.
+    pub(crate) id: u64,
+    /// Cancelled once this run is superseded, which aborts walking the workspace folder and
+    /// collecting diagnostics.
+    pub(crate) cancellation_flag: CancellationFlag,
+    /// The files whose diagnostics haven't been collected yet.
+    pub(crate) pending_files: Vec<FileId>,
+    pub(crate) num_files: usize,
+    pub(crate) num_analyzed_files: usize,
+    pub(crate) is_collecting_diagnostics: bool,
+}
+
+impl WorkspaceAnalysis {
+    fn new() -> Self {
+        WorkspaceAnalysis {
+            id: NEXT_WORKSPACE_ANALYSIS_ID.fetch_add(1, Ordering::Relaxed),
+            cancellation_flag: Default::default(),
+            pending_files: Vec::new(),
+            num_files: 0,
+            num_analyzed_files: 0,
+            is_collecting_diagnostics: false,
+        }
+    }
+
+    /// The token used for reporting the progress of this run to the client.
+    pub(crate) fn progress_token(&self, folder: &Path) -> String {
+        format!("AnalyzeWorkspace:{}:{}", folder.display(), self.id)
+    }
+}
+
+/// A Starlark file found while walking a workspace folder.
+#[derive(Debug)]
+pub(crate) struct WorkspaceFile {
+    pub(crate) path: PathBuf,
+    pub(crate) dialect: Dialect,
+    pub(crate) info: Option<FileInfo>,
+    pub(crate) contents: String,
+}
+
+impl Workspace {
+    /// Fetches the Bazel configuration for the given workspace folder and sets up its analysis.
+    /// Returns the workspace along with whether fetching the Bazel configuration failed.
+    pub(crate) fn new(
+        folder: PathBuf,
+        config: &ServerConfig,
+        path_interner: Arc<PathInterner>,
+        task_pool_sender: Sender<Task>,
+    ) -> (Self, bool) {
+        // Check if the user specified a path to the Bazel executable.
+        let bazel_path = config
+            .args
+            .bazel_path
+            .clone()
+            .unwrap_or("bazel".to_string());
+
+        // Determine Bazel configuration. Fetching it can take several seconds, so we prefer a
+        // cached configuration, which is refreshed in the background instead.
+        let mut has_bazel_init_err = false;
+        let bazel_client = Arc::new(BazelCLI::new(&bazel_path).with_working_dir(&folder));
+        let bazel_cache = BazelCache::new(&folder, &bazel_path, &*bazel_client);
+        let cached_bazel_cx = bazel_cache.as_ref().and_then(|cache| match cache.load() {
+            Ok(cx) => Some(cx),
+            Err(err) => {
+                debug!("no cached Bazel configuration for {:?}: {}", folder, err);
+                None
+            }
+        });
+        let is_cached = cached_bazel_cx.is_some();
+        let bazel_cx = match cached_bazel_cx {
+            Some(cx) => {
+                info!("using cached Bazel configuration for {:?}", folder);
+                cx
+            }
+            None => {
+                debug!(
+                    "fetching Bazel configuration for {:?} using Bazel executable at {:?}",
+                    folder, bazel_path
+                );
+                match BazelContext::new(&*bazel_client) {
+                    Ok(cx) => {
+                        if let Some(cache) = &bazel_cache {
+                            if let Err(err) = cache.store(&cx) {
+                                error!("failed to cache Bazel configuration: {}", err);
+                            }
+                        }
+                        cx
+                    }
+                    Err(err) => {
+                        has_bazel_init_err = true;
+                        error!("failed to initialize Bazel context: {}", err);
+                        Default::default()
+                    }
+                }
+            }
+        };
+        let cached_bazel_cx = is_cached.then(|| bazel_cx.clone());
+
+        // Query for all targets in the current workspace, to use for label completion.
+        let targets = if config.args.enable_label_completions {
+            debug!("querying for all targets in the current workspace");
+            match bazel_client.query_all_workspace_targets() {
+                Ok(targets) => {
+                    debug!("successfully queried for all targets");
+                    targets
+                }
+                Err(err) => {
+                    error!("failed to query all workspace targets: {}", err);
+                    has_bazel_init_err = true;
+                    Default::default()
+                }
+            }
+        } else {
+            Default::default()
+        };
+
+        let mut workspace = Self::with_bazel_context(
+            folder,
+            config,
+            path_interner,
+            task_pool_sender,
+            bazel_client,
+            bazel_cx,
+            bazel_cache,
+        );
+        workspace.analysis.set_all_workspace_targets(targets);
+        workspace.cached_bazel_cx = cached_bazel_cx;
+        (workspace, has_bazel_init_err)
+    }
+
+    /// Sets up the analysis for the given workspace folder using an already determined Bazel
+    /// configuration.
+    pub(crate) fn with_bazel_context(
+        folder: PathBuf,
+        config: &ServerConfig,
+        path_interner: Arc<PathInterner>,
+        task_pool_sender: Sender<Task>,
+        bazel_client: Arc<dyn BazelClient>,
+        bazel_cx: BazelContext,
+        bazel_cache: Option<BazelCache>,
+    ) -> Self {
+        bazel_client.set_repo_mappings(bazel_cx.repo_mappings);
+
+        let external_output_base = bazel_cx.info.output_base.join("external");
+        let loader = Arc::new(DefaultFileLoader::new(
+            bazel_client.clone(),
+            path_interner.clone(),
+            bazel_cx.info.workspace.clone(),
+            bazel_cx.info.workspace_name.clone(),
+            external_output_base.clone(),
+            task_pool_sender,
+            bazel_cx.bzlmod_enabled,
+        ));
+        let mut analysis = Analysis::new(loader.clone(), config.inference_options());
+        let cancellation_flag = CancellationFlag::default();
+        let symbol_indexer = Arc::new(SymbolIndexer::new(
+            path_interner.clone(),
+            analysis.load_graph(),
+            bazel_cx.info.workspace.clone(),
+            external_output_base.clone(),
+            bazel_cx.bzlmod_enabled,
+            cancellation_flag.clone(),
+        ));
+
+        analysis.set_builtin_defs(load_bazel_builtins(), bazel_cx.rules);
+
+        // Check for a prelude file. We skip verifying that `//tools/build_tools` is actually a package (i.e.
+        // that it actually contains a `BUILD.bazel`) file for simplicity.
+        if let Ok((prelude, contents)) = load_bazel_prelude(&bazel_cx.info.workspace) {
+            info!("found prelude file at {:?}", prelude);
+            let file_id = path_interner.intern_path(prelude);
+            let mut change = Change::default();
+            change.create_file(
+                file_id,
+                Dialect::Bazel,
+                Some(FileInfo::Bazel {
+                    api_context: APIContext::Bzl,
+                    is_external: false,
+                }),
+                contents,
+            );
+            analysis.apply_change(change);
+            analysis.set_bazel_prelude_file(file_id);
+        }
+
+        Workspace {
+            folder,
+            root: bazel_cx.info.workspace,
+            workspace_name: bazel_cx.info.workspace_name,
+            external_output_base,
+            analysis,
+            loader,
+            bazel_client,
+            bazel_cache,
+            cached_bazel_cx: None,
+            bzlmod_enabled: bazel_cx.bzlmod_enabled,
+            symbol_indexer,
+            pending_repos: Default::default(),
+            pending_files: Default::default(),
+            fetched_repos: Default::default(),
+            is_fetching_repos: false,
+            is_refreshing_all_workspace_targets: false,
+            workspace_analysis: None,
+            cancellation_flag,
+        }
+    }
+
+    /// Returns whether the given Bazel configuration resolves files and labels the same way as
+    /// the one the workspace was set up with.
+    pub(crate) fn has_same_layout(&self, bazel_cx: &BazelContext) -> bool {
+        self.root == bazel_cx.info.workspace
+            && self.workspace_name == bazel_cx.info.workspace_name
+            && self.external_output_base == bazel_cx.info.output_base.join("external")
+            && self.bzlmod_enabled == bazel_cx.bzlmod_enabled
+    }
+
+    /// Returns how closely the workspace matches the given path, i.e. the length of the
+    /// workspace directory containing it, or `None` if the path isn't part of the workspace.
+    /// Files in the workspace's external repositories are considered part of it as well.
+    pub(crate) fn match_path(&self, path: &Path) -> Option<usize> {
+        [&self.folder, &self.root, &self.external_output_base]
+            .into_iter()
+            .filter(|dir| !dir.as_os_str().is_empty() && path.starts_with(dir))
+            .map(|dir| dir.components().count())
+            .max()
+    }
+
+    /// Forgets the repository mappings and the repositories fetched so far, e.g. after
+    /// `MODULE.bazel` changed.
+    pub(crate) fn reset_repos(&mut self) {
+        self.bazel_client.clear_repo_mappings();
+        self.fetched_repos.clear();
+    }
+
+    pub(crate) fn fetch_external_repos(&mut self, task_pool_handle: &TaskPoolHandle<Task>) {
+        let repos = mem::take(&mut self.pending_repos);
+        let files = mem::take(&mut self.pending_files);
+        let folder = self.folder.clone();
+        let bazel_client = self.bazel_client.clone();
+        let bzlmod_enabled = self.bzlmod_enabled;
+        let symbol_indexer = self.symbol_indexer.clone();
+        let cancellation_flag = self.cancellation_flag.clone();
+
+        self.is_fetching_repos = true;
+        self.fetched_repos.extend(repos.clone());
+        task_pool_handle.spawn_with_sender(move |sender| {
+            sender
+                .send(Task::FetchExternalRepos(
+                    folder.clone(),
+                    FetchExternalReposProgress::Begin(repos.clone()),
+                ))
+                .unwrap();
+
+            let mut failed_repos = vec![];
+
+            for repo in &repos {
+                if cancellation_flag.is_cancelled() {
+                    break;
+                }
+                debug!("fetching external repository \"@@{}\"", repo);
+                if let Err(err) = if bzlmod_enabled {
+                    bazel_client.fetch_repo(repo)
+                } else {
+                    bazel_client.null_query_external_repo_targets(repo)
+                } {
+                    failed_repos.push(repo.clone());
+                    error!(
+                        "failed to fetch external repository \"@@{}\": {}",
+                        repo, err
+                    );
+                } else {
+                    symbol_indexer.index_repo(repo);
+                }
+            }
+
+            sender
+                .send(Task::FetchExternalRepos(
+                    folder,
+                    FetchExternalReposProgress::End(files, failed_repos),
+                ))
+                .unwrap();
+        });
+    }
+
+    pub(crate) fn refresh_all_workspace_targets(
+        &mut self,
+        task_pool_handle: &TaskPoolHandle<Task>,
+    ) {
+        if self.is_refreshing_all_workspace_targets {
+            return;
+        }
+
+        let folder = self.folder.clone();
+        let bazel_client = self.bazel_client.clone();
+
+        self.is_refreshing_all_workspace_targets = true;
+        task_pool_handle.spawn_with_sender(move |sender| {
+            sender
+                .send(Task::RefreshAllWorkspaceTargets(
+                    folder.clone(),
+                    RefreshAllWorkspaceTargetsProgress::Begin,
+                ))
+                .unwrap();
+
+            let targets = match bazel_client.query_all_workspace_targets() {
+                Ok(targets) => Some(targets),
+                Err(err) => {
+                    error!("failed to query all workspace targets: {}", err);
+                    None
+                }
+            };
+
+            sender
+                .send(Task::RefreshAllWorkspaceTargets(
+                    folder,
+                    RefreshAllWorkspaceTargetsProgress::End(targets),
+                ))
+                .unwrap();
+        });
+    }
+
+    /// Fetches the Bazel configuration in the background if the workspace was set up with a
+    /// cached one. The cache is updated if the configuration changed in the meantime, e.g.
+    /// because a different Bazel release is used now.
+    pub(crate) fn refresh_bazel_context(&mut self, task_pool_handle: &TaskPoolHandle<Task>) {
+        let Some(cached_bazel_cx) = self.cached_bazel_cx.take() else {
+            return;
+        };
+
+        let folder = self.folder.clone();
+        let bazel_client = self.bazel_client.clone();
+        let bazel_cache = self.bazel_cache.clone();
+
+        task_pool_handle.spawn_with_sender(move |sender| {
+            sender
+                .send(Task::RefreshBazelContext(
+                    folder.clone(),
+                    RefreshBazelContextProgress::Begin,
+                ))
+                .unwrap();
+
+            let bazel_cx = match BazelContext::new(&*bazel_client) {
+                Ok(bazel_cx) if bazel_cx == cached_bazel_cx => {
+                    debug!("cached Bazel configuration for {:?} is up to date", folder);
+                    None
+                }
+                Ok(bazel_cx) => {
+                    info!("Bazel configuration for {:?} changed", folder);
+                    if let Some(cache) = &bazel_cache {
+                        if let Err(err) = cache.store(&bazel_cx) {
+                            error!("failed to cache Bazel configuration: {}", err);
+                        }
+                    }
+                    Some(bazel_cx)
+                }
+                Err(err) => {
+                    error!("failed to refresh Bazel context: {}", err);
+                    None
+                }
+            };
+
+            sender
+                .send(Task::RefreshBazelContext(
+                    folder,
+                    RefreshBazelContextProgress::End(bazel_cx),
+                ))
+                .unwrap();
+        });
+    }
+
+    /// Starts analyzing all Starlark files in the workspace folder in the background, so that
+    /// diagnostics are reported for files that aren't open in the editor as well. The files are
+    /// walked and read here, while their diagnostics are collected by
+    /// [`Workspace::collect_diagnostics`] once they were added to the analysis. Returns whether
+    /// a new run was started, i.e. whether the workspace wasn't already being analyzed.
+    pub(crate) fn analyze(&mut self, task_pool_handle: &TaskPoolHandle<Task>) -> bool {
+        if self.workspace_analysis.is_some() {
+            return false;
+        }
+
+        let analysis = WorkspaceAnalysis::new();
+        let folder = self.folder.clone();
+        let root = self.root.clone();
+        let id = analysis.id;
+        let cancellation_flag = analysis.cancellation_flag.clone();
+
+        task_pool_handle.spawn_low_priority(move || {
+            debug!("loading Starlark files in {:?}", folder);
+            let files = load_workspace_files(&folder, &root, &cancellation_flag);
+            Task::AnalyzeWorkspace(folder, id, AnalyzeWorkspaceProgress::FilesLoaded(files))
+        });
+        self.workspace_analysis = Some(analysis);
+        true
+    }
+
+    /// Collects diagnostics for the next batch of files pending analysis using the given
+    /// snapshot. Batches run one at a time on the low-priority threads, leaving the remaining
+    /// threads to requests and to diagnostics for open documents. If the snapshot is invalidated,
+    /// e.g. by an edit, collecting stops and the remaining files are handed back to be picked up
+    /// again with a newer snapshot.
+    pub(crate) fn collect_diagnostics(
+        &mut self,
+        snapshot: ServerSnapshot,
+        task_pool_handle: &TaskPoolHandle<Task>,
+    ) {
+        let analysis = match &mut self.workspace_analysis {
+            Some(analysis) if !analysis.is_collecting_diagnostics => analysis,
+            _ => return,
+        };
+        if analysis.pending_files.is_empty() {
+            return;
+        }
+
+        let remaining_files = analysis
+            .pending_files
+            .split_off(WORKSPACE_ANALYSIS_BATCH_SIZE.min(analysis.pending_files.len()));
+        let mut file_ids = mem::replace(&mut analysis.pending_files, remaining_files);
+        let folder = self.folder.clone();
+        let id = analysis.id;
+        let cancellation_flag = analysis.cancellation_flag.clone();
+
+        analysis.is_collecting_diagnostics = true;
+        task_pool_handle.spawn_low_priority(move || {
+            let mut num_analyzed_files = 0;
+            let mut diagnostics = Vec::new();
+
+            for &file_id in &file_ids {
+                if cancellation_flag.is_cancelled() {
+                    break;
+                }
+                match collect_diagnostics(&snapshot, file_id) {
+                    Ok(Some(file_diagnostics)) => diagnostics.push((file_id, file_diagnostics)),
+                    Ok(None) => {}
+                    Err(_) => break,
+                }
+                num_analyzed_files += 1;
+            }
+
+            Task::AnalyzeWorkspace(
+                folder,
+                id,
+                AnalyzeWorkspaceProgress::Report(
+                    num_analyzed_files,
+                    diagnostics,
+                    file_ids.split_off(num_analyzed_files),
+                ),
+            )
+        });
+    }
+}
+
+/// Walks the given workspace folder the same way as `starpls check`, returning the paths of all
+/// Starlark files in it along with their dialects and file info.
+pub(crate) fn find_workspace_files<'a>(
+    folder: &Path,
+    root: &'a Path,
+) -> impl Iterator<Item = (PathBuf, Dialect, Option<FileInfo>)> + 'a {
+    walk_dir(folder.to_path_buf(), &[])
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(move |entry| {
+            let path = entry.into_path();
+            let (dialect, info) = workspace_file_dialect_and_info(root, &path)?;
+            Some((path, dialect, info))
+        })
+}
+
+/// Returns the dialect and file info of the given path if it's a Starlark file that's part of
+/// the workspace analysis.
+pub(crate) fn workspace_file_dialect_and_info(
+    root: &Path,
+    path: &Path,
+) -> Option<(Dialect, Option<FileInfo>)> {
+    let (dialect, api_context) = dialect_and_api_context_for_workspace_path(root, path)?;
+
+    // As with `starpls check`, plain Starlark files are only recognized by their extensions.
+    if dialect == Dialect::Standard
+        && !matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("star" | "sky")
+        )
+    {
+        return None;
+    }
+
+    let info = api_context.map(|api_context| FileInfo::Bazel {
+        api_context,
+        is_external: false,
+    });
+    Some((dialect, info))
+}
+
+/// Finds and reads all Starlark files in the given workspace folder, stopping early if the
+/// analysis is cancelled in the meantime.
+fn load_workspace_files(
+    folder: &Path,
+    root: &Path,
+    cancellation_flag: &CancellationFlag,
+) -> Vec<WorkspaceFile> {
+    find_workspace_files(folder, root)
+        .take_while(|_| !cancellation_flag.is_cancelled())
+        .filter_map(|(path, dialect, info)| {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    error!("failed to read {:?}: {}", path, err);
+                    return None;
+                }
+            };
+            Some(WorkspaceFile {
+                path,
+                dialect,
+                info,
+                contents,
+            })
+        })
+        .collect()
+}
+
+fn load_bazel_prelude(workspace: impl AsRef<Path>) -> anyhow::Result<(PathBuf, String)> {
+    let prelude = workspace.as_ref().join("tools/build_rules/prelude_bazel");
+    let contents = fs::read_to_string(&prelude)?;
+    Ok((prelude, contents))
+}
@@ -7,11 +7,13 @@ use std::str;
 use anyhow::anyhow;
 use anyhow::bail;
 use parking_lot::RwLock;
+use serde::Deserialize;
+use serde::Serialize;
 use serde_json::Deserializer;
 
 const DEFAULT_WORKSPACE_NAMES: &[&str] = &["__main__", "_main"];
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BazelInfo {
     pub output_base: PathBuf,
     pub release: String,
@@ -23,12 +25,14 @@ pub struct BazelInfo {
 pub trait BazelClient: Send + Sync + 'static {
     fn build_language(&self) -> anyhow::Result<Vec<u8>>;
     fn info(&self) -> anyhow::Result<BazelInfo>;
+    fn version(&self) -> anyhow::Result<String>;
     fn resolve_repo_from_mapping(
         &self,
         apparent_repo: &str,
         from_repo: &str,
     ) -> anyhow::Result<Option<String>>;
     fn clear_repo_mappings(&self);
+    fn set_repo_mappings(&self, mappings: HashMap<String, HashMap<String, String>>);
     fn null_query_external_repo_targets(&self, repo: &str) -> anyhow::Result<()>;
     fn repo_mapping_keys(&self, from_repo: &str) -> anyhow::Result<Vec<String>>;
     fn query_all_workspace_targets(&self) -> anyhow::Result<Vec<String>>;
@@ -38,6 +42,7 @@ pub trait BazelClient: Send + Sync + 'static {
 
 pub struct BazelCLI {
     executable: PathBuf,
+    working_dir: Option<PathBuf>,
     repo_mappings: RwLock<HashMap<String, HashMap<String, String>>>,
 }
 
@@ -49,12 +54,23 @@ impl BazelCLI {
         }
     }
 
+    /// Runs Bazel commands from the given directory instead of the current working directory,
+    /// e.g. for workspaces other than the one the server was started in.
+    pub fn with_working_dir(mut self, working_dir: impl AsRef<Path>) -> Self {
+        self.working_dir = Some(working_dir.as_ref().to_path_buf());
+        self
+    }
+
     fn run_command<I, S>(&self, args: I) -> anyhow::Result<Vec<u8>>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<std::ffi::OsStr>,
     {
-        let output = Command::new(&self.executable).args(args).output()?;
+        let mut command = Command::new(&self.executable);
+        if let Some(working_dir) = &self.working_dir {
+            command.current_dir(working_dir);
+        }
+        let output = command.args(args).output()?;
         if !output.status.success() {
             bail!(
                 "failed to run Bazel command with exit status {}, stderr={:?}",
@@ -71,6 +87,13 @@ impl BazelClient for BazelCLI {
         self.run_command(["info", "build-language"])
     }
 
+    fn version(&self) -> anyhow::Result<String> {
+        // Unlike `bazel version`, this doesn't have to start a Bazel server, while still
+        // reporting the release used for the workspace, e.g. as picked by Bazelisk.
+        let output = self.run_command(["--version"])?;
+        Ok(str::from_utf8(&output)?.trim().to_string())
+    }
+
     fn info(&self) -> anyhow::Result<BazelInfo> {
         let output = self.run_command([
             "info",
@@ -152,6 +175,10 @@ impl BazelClient for BazelCLI {
         self.repo_mappings.write().clear();
     }
 
+    fn set_repo_mappings(&self, mappings: HashMap<String, HashMap<String, String>>) {
+        *self.repo_mappings.write() = mappings;
+    }
+
     fn null_query_external_repo_targets(&self, repo: &str) -> anyhow::Result<()> {
         self.run_command(["query", "--keep_going", &format!("@@{}//...", repo)])?;
         Ok(())
@@ -201,6 +228,7 @@ impl Default for BazelCLI {
     fn default() -> Self {
         Self {
             executable: "bazel".into(),
+            working_dir: None,
             repo_mappings: Default::default(),
         }
     }
@@ -144,6 +144,10 @@ pub fn decode_builtins(data: &[u8]) -> anyhow::Result<Builtins> {
     Ok(builtins)
 }
 
+pub fn encode_builtins(builtins: &Builtins) -> Vec<u8> {
+    builtins.encode_to_vec()
+}
+
 pub fn resolve_workspace(from: impl AsRef<Path>) -> io::Result<Option<(PathBuf, PathBuf)>> {
     let mut package: Option<PathBuf> = None;
     for ancestor in from
@@ -51,14 +51,19 @@ pub struct LoadItemCandidate {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FileId(pub u32);
 
+/// The result of resolving a label. `canonical_repo` is the canonical name of the repository
+/// containing the label if it was referred to by an apparent repository name, e.g. `rules_go+`
+/// for `@rules_go//go:def.bzl`.
 pub enum ResolvedPath {
     Source {
         path: PathBuf,
+        canonical_repo: Option<String>,
     },
     BuildTarget {
         build_file: FileId,
         target: String,
         contents: Option<String>,
+        canonical_repo: Option<String>,
     },
 }
 
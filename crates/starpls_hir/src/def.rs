@@ -17,6 +17,7 @@ use starpls_syntax::ast::UnaryOp;
 use starpls_syntax::ast::{self};
 use starpls_syntax::TextRange;
 
+use crate::module;
 use crate::typeck::TypeRef;
 use crate::Db;
 
@@ -24,6 +25,7 @@ pub(crate) mod codeflow;
 mod lower;
 pub(crate) mod resolver;
 pub(crate) mod scope;
+pub(crate) mod unused;
 
 #[cfg(test)]
 mod tests;
@@ -368,6 +370,44 @@ pub struct LoadStmt {
     pub(crate) ptr: SyntaxNodePtr,
 }
 
+/// Tracks which files exist for the purposes of resolving `load` statements. Its generation is
+/// bumped whenever files are created or removed, since this can change which file a `load`
+/// statement resolves to, even though none of the files' contents changed.
+#[salsa::input]
+pub struct LoadResolution {
+    pub generation: u64,
+}
+
+#[salsa::tracked]
+pub(crate) struct ResolvedLoadStmts {
+    #[return_ref]
+    pub(crate) resolved: FxHashMap<LoadStmt, Result<Option<File>, String>>,
+}
+
+/// Resolves the `load` statements in the given file to the files they load.
+#[salsa::tracked]
+pub(crate) fn resolved_load_stmts_query(db: &dyn Db, file: File) -> ResolvedLoadStmts {
+    // Files are looked up through the loader, which Salsa doesn't know about, so instead we
+    // depend on the load resolution generation to re-resolve when files are created or removed.
+    db.get_load_resolution().generation(db);
+
+    let resolved = module(db, file)
+        .stmts
+        .iter()
+        .filter_map(|(_, stmt)| match stmt {
+            Stmt::Load { load_stmt, .. } => Some(*load_stmt),
+            _ => None,
+        })
+        .map(|load_stmt| {
+            let res = db
+                .load_file(load_stmt.module(db), file.dialect(db), file.id(db))
+                .map_err(|err| err.to_string());
+            (load_stmt, res)
+        })
+        .collect();
+    ResolvedLoadStmts::new(db, resolved)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LoadItem {
     Direct {
@@ -50,6 +50,9 @@ pub(crate) enum FlowNode {
 pub(crate) struct CodeFlowGraph {
     pub(crate) flow_nodes: Arena<FlowNode>,
     pub(crate) hir_to_flow_node: FxHashMap<ScopeHirId, FlowNodeId>,
+    /// The flow node at the end of each function's body, i.e. where the function implicitly
+    /// returns `None`.
+    pub(crate) def_end_flow_node: FxHashMap<StmtId, FlowNodeId>,
 }
 
 #[allow(unused)]
@@ -71,6 +74,7 @@ impl<'a> CodeFlowLoweringContext<'a> {
         let cfg = CodeFlowGraph {
             flow_nodes,
             hir_to_flow_node: Default::default(),
+            def_end_flow_node: Default::default(),
         };
         CodeFlowLoweringContext {
             module,
@@ -111,6 +115,7 @@ impl<'a> CodeFlowLoweringContext<'a> {
             Stmt::Def { stmts, .. } => {
                 self.with_new_start_node(|this| {
                     this.lower_stmts(stmts);
+                    this.result.def_end_flow_node.insert(stmt, this.curr_node);
                 });
             }
 
@@ -146,8 +151,11 @@ impl<'a> CodeFlowLoweringContext<'a> {
                 self.curr_node = self.finish_branch_or_loop_node(post_if_node);
             }
 
-            Stmt::Return { expr: Some(expr) } => {
-                self.lower_expr(*expr);
+            Stmt::Return { expr } => {
+                if let Some(expr) = expr {
+                    self.lower_expr(*expr);
+                }
+                self.curr_node = self.unreachable_node;
             }
 
             Stmt::Expr { expr } => {
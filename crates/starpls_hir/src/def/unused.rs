@@ -0,0 +1,300 @@
+use rustc_hash::FxHashSet;
+use starpls_common::parse;
+use starpls_common::File;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::TextRange;
+
+use crate::def::resolver::Resolver;
+use crate::def::scope::module_scopes;
+use crate::def::scope::ExecutionScopeId;
+use crate::def::scope::LoadItemDef;
+use crate::def::scope::ScopeDef;
+use crate::def::scope::ScopeHirId;
+use crate::def::scope::VariableDef;
+use crate::def::Expr;
+use crate::def::ExprId;
+use crate::def::LoadItem;
+use crate::def::LoadItemId;
+use crate::def::Stmt;
+use crate::def::StmtId;
+use crate::module;
+use crate::source_map;
+use crate::typeck::InferenceOptions;
+use crate::typeck::TypeRef;
+use crate::Db;
+use crate::Name;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct UnusedDefinition {
+    pub(crate) name: Name,
+    pub(crate) range: TextRange,
+    pub(crate) is_load_item: bool,
+}
+
+#[salsa::tracked]
+pub(crate) struct UnusedDefinitionsResult {
+    #[return_ref]
+    pub(crate) defs: Vec<UnusedDefinition>,
+}
+
+impl UnusedDefinitionsResult {
+    /// Returns the unused definitions that should be reported to the user under the given
+    /// options. Symbols loaded by the prelude are made available to all BUILD files, so they
+    /// are never reported.
+    pub(crate) fn reported_defs<'a>(
+        self,
+        db: &'a dyn Db,
+        file: File,
+        options: &InferenceOptions,
+    ) -> impl Iterator<Item = &'a UnusedDefinition> {
+        let allow_unused_definitions = options.allow_unused_definitions;
+        let report_unused_loads =
+            options.report_unused_loads && db.get_bazel_prelude_file() != Some(file.id(db));
+        self.defs(db).iter().filter(move |def| {
+            !allow_unused_definitions && (!def.is_load_item || report_unused_loads)
+        })
+    }
+}
+
+/// Finds the definitions in the given file that are never accessed. Only name resolution is
+/// involved, so this is much cheaper than type checking the file, and doesn't depend on which
+/// expressions happen to have been inferred.
+///
+/// Variables and functions are only considered if they are local to a function or private to
+/// the module, since other definitions may be loaded by other files. Load items are always
+/// considered.
+#[salsa::tracked]
+pub(crate) fn unused_definitions_query(db: &dyn Db, file: File) -> UnusedDefinitionsResult {
+    let module = module(db, file);
+    let mut collector = UsageCollector {
+        db,
+        file,
+        used_exprs: FxHashSet::default(),
+        used_stmts: FxHashSet::default(),
+        used_load_items: FxHashSet::default(),
+    };
+
+    for (expr, name) in module.exprs.iter().filter_map(|(id, expr)| match expr {
+        Expr::Name { name } => Some((id, name)),
+        _ => None,
+    }) {
+        collector.resolve_name(name, expr.into());
+    }
+
+    // Names can also be referenced by type comments.
+    for (stmt, data) in module.stmts.iter() {
+        match data {
+            Stmt::Assign {
+                type_ref: Some((type_ref, _)),
+                ..
+            } => collector.resolve_type_ref(type_ref, stmt.into()),
+            Stmt::Def { func, .. } => {
+                for param in func.params(db).iter() {
+                    if let Some(type_ref) = module[*param].type_ref() {
+                        collector.resolve_type_ref(&type_ref, stmt.into());
+                    }
+                }
+                if let Some(type_ref) = func.ret_type_ref(db) {
+                    collector.resolve_type_ref(&type_ref, stmt.into());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let UsageCollector {
+        used_exprs,
+        used_stmts,
+        used_load_items,
+        ..
+    } = collector;
+    let scopes = module_scopes(db, file).scopes(db);
+    let source_map = source_map(db, file);
+    let mut defs = Vec::new();
+
+    // Report variables in the order in which they appear in the module.
+    let mut unused_exprs = scopes
+        .scopes
+        .iter()
+        .flat_map(|(_, scope)| {
+            scope.defs.iter().flat_map(move |(name, defs)| {
+                defs.iter().filter_map(move |def| match def {
+                    ScopeDef::Variable(VariableDef {
+                        file: def_file,
+                        expr,
+                        ..
+                    }) if *def_file == file
+                        && (scope.execution_scope != ExecutionScopeId::Module
+                            || name.as_str().starts_with('_')) =>
+                    {
+                        Some((*expr, name))
+                    }
+                    _ => None,
+                })
+            })
+        })
+        .filter(|(expr, name)| name.as_str() != "_" && !used_exprs.contains(expr))
+        .collect::<Vec<_>>();
+    unused_exprs.sort_by_key(|(expr, _)| expr.index());
+    unused_exprs.dedup_by_key(|(expr, _)| *expr);
+    defs.extend(unused_exprs.into_iter().filter_map(|(expr, name)| {
+        Some(UnusedDefinition {
+            name: name.clone(),
+            range: source_map
+                .expr_map_back
+                .get(&expr)?
+                .syntax_node_ptr()
+                .text_range(),
+            is_load_item: false,
+        })
+    }));
+
+    defs.extend(
+        module
+            .stmts
+            .iter()
+            .filter(|(stmt, _)| !used_stmts.contains(stmt))
+            .filter_map(|(stmt, _)| unused_function(db, file, stmt)),
+    );
+
+    defs.extend(
+        module
+            .load_items
+            .iter()
+            .filter(|(load_item, _)| !used_load_items.contains(load_item))
+            .filter_map(|(load_item, item)| unused_load_item(db, file, load_item, item)),
+    );
+
+    UnusedDefinitionsResult::new(db, defs)
+}
+
+fn unused_function(db: &dyn Db, file: File, stmt: StmtId) -> Option<UnusedDefinition> {
+    let Stmt::Def { func, .. } = &module(db, file)[stmt] else {
+        return None;
+    };
+    let name = func.name(db);
+    if name.is_missing() {
+        return None;
+    }
+
+    // Don't report exported functions as unused.
+    let scopes = module_scopes(db, file).scopes(db);
+    let scope = scopes.scopes_by_hir_id.get(&ScopeHirId::Stmt(stmt))?;
+    if scopes.scopes[*scope].execution_scope == ExecutionScopeId::Module
+        && !name.as_str().starts_with('_')
+    {
+        return None;
+    }
+
+    let ptr = source_map(db, file).stmt_map_back.get(&stmt)?;
+    let node = ptr
+        .syntax_node_ptr()
+        .try_to_node(&parse(db, file).syntax(db))?;
+    let name_node = ast::DefStmt::cast(node)?.name()?;
+    Some(UnusedDefinition {
+        name,
+        range: name_node.syntax().text_range(),
+        is_load_item: false,
+    })
+}
+
+fn unused_load_item(
+    db: &dyn Db,
+    file: File,
+    load_item: LoadItemId,
+    item: &LoadItem,
+) -> Option<UnusedDefinition> {
+    let name = match item {
+        LoadItem::Direct { name, .. } => &**name,
+        LoadItem::Aliased { alias, .. } => alias.as_str(),
+    };
+    if name.is_empty() || name == "_" {
+        return None;
+    }
+
+    // Report the alias for aliased items, and the entire string otherwise.
+    let range = source_map(db, file)
+        .load_item_map_back
+        .get(&load_item)
+        .and_then(|ptr| ptr.try_to_node(&parse(db, file).syntax(db)))
+        .and_then(|node| match node {
+            ast::LoadItem::Direct(item) => Some(item.syntax().text_range()),
+            ast::LoadItem::Aliased(item) => item.alias().map(|alias| alias.syntax().text_range()),
+        })?;
+    Some(UnusedDefinition {
+        name: Name::from_str(name),
+        range,
+        is_load_item: true,
+    })
+}
+
+struct UsageCollector<'a> {
+    db: &'a dyn Db,
+    file: File,
+    used_exprs: FxHashSet<ExprId>,
+    used_stmts: FxHashSet<StmtId>,
+    used_load_items: FxHashSet<LoadItemId>,
+}
+
+impl UsageCollector<'_> {
+    fn resolve_name(&mut self, name: &Name, usage: ScopeHirId) {
+        let resolver = Resolver::new_for_hir_execution_scope(self.db, self.file, usage);
+        let Some(usage_scope) = resolver.scope_for_hir_id(usage) else {
+            return;
+        };
+        let Some((def_execution_scope, defs)) = resolver.resolve_name(name) else {
+            return;
+        };
+        for def in defs.skip_while(|def| def.scope > usage_scope) {
+            match def.def {
+                ScopeDef::Variable(VariableDef { file, expr, .. }) => {
+                    if *file == self.file
+                        && usage != ScopeHirId::Expr(*expr)
+                        && (def_execution_scope != ExecutionScopeId::Module
+                            || name.as_str().starts_with('_'))
+                    {
+                        self.used_exprs.insert(*expr);
+                    }
+                }
+                ScopeDef::Function(def) => {
+                    if let Some(stmt) = def.stmt().filter(|stmt| stmt.file == self.file) {
+                        self.used_stmts.insert(stmt.value);
+                    }
+                }
+                ScopeDef::LoadItem(LoadItemDef { load_item, .. }) => {
+                    self.used_load_items.insert(*load_item);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Only the first segment of a path, e.g. `foo` in `foo.FooInfo`, refers to a definition.
+    fn resolve_type_ref(&mut self, type_ref: &TypeRef, usage: ScopeHirId) {
+        match type_ref {
+            TypeRef::Name(name, args) => {
+                self.resolve_name(name, usage);
+                self.resolve_type_args(args, usage);
+            }
+            TypeRef::Path(segments, args) => {
+                if let Some(name) = segments.first() {
+                    self.resolve_name(name, usage);
+                }
+                self.resolve_type_args(args, usage);
+            }
+            TypeRef::Union(type_refs) => {
+                for type_ref in type_refs.iter() {
+                    self.resolve_type_ref(type_ref, usage);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_type_args(&mut self, args: &Option<Box<[TypeRef]>>, usage: ScopeHirId) {
+        for type_ref in args.iter().flat_map(|args| args.iter()) {
+            self.resolve_type_ref(type_ref, usage);
+        }
+    }
+}
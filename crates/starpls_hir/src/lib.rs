@@ -5,6 +5,7 @@ use def::scope;
 use def::scope::module_scopes;
 use def::scope::FunctionDef;
 use def::scope::ParameterDef;
+use def::unused::unused_definitions_query;
 use def::Function;
 use def::LoadItemId;
 use def::Stmt;
@@ -22,6 +23,7 @@ use starpls_syntax::ast;
 use starpls_syntax::ast::AstNode;
 use starpls_syntax::ast::AstPtr;
 use starpls_syntax::ast::SyntaxNodePtr;
+use starpls_syntax::TextRange;
 use starpls_syntax::TextSize;
 use starpls_syntax::T;
 use typeck::builtins::BuiltinFunction;
@@ -40,6 +42,7 @@ use typeck::TagParam;
 use typeck::Tuple;
 
 use crate::def::ExprId;
+pub use crate::def::LoadResolution;
 use crate::def::Module;
 use crate::def::ModuleSourceMap;
 pub use crate::def::Name;
@@ -47,6 +50,7 @@ pub use crate::display::DisplayWithDb;
 pub use crate::display::DisplayWithDbWrapper;
 pub use crate::test_database::Fixture;
 pub use crate::typeck::builtins::BuiltinDefs;
+pub use crate::typeck::CancellationFlag;
 pub use crate::typeck::Cancelled;
 pub use crate::typeck::GlobalContext;
 pub use crate::typeck::InferenceOptions;
@@ -75,12 +79,17 @@ pub struct Jar(
     ModuleInfo,
     def::Function,
     def::LoadStmt,
+    def::LoadResolution,
+    def::ResolvedLoadStmts,
+    def::resolved_load_stmts_query,
     def::InternedString,
     def::codeflow::CodeFlowGraphResult,
     def::codeflow::code_flow_graph,
     def::scope::ModuleScopes,
     def::scope::module_scopes,
     def::scope::module_scopes_query,
+    def::unused::UnusedDefinitionsResult,
+    def::unused::unused_definitions_query,
     typeck::builtins::BuiltinDefs,
     typeck::builtins::BuiltinFunction,
     typeck::builtins::BuiltinGlobals,
@@ -115,6 +124,14 @@ pub trait Db: salsa::DbWithJar<Jar> + starpls_common::Db {
     fn get_bazel_prelude_file(&self) -> Option<FileId>;
     fn set_all_workspace_targets(&mut self, targets: Vec<String>);
     fn get_all_workspace_targets(&self) -> Arc<Vec<String>>;
+    fn get_load_resolution(&self) -> LoadResolution;
+
+    /// Returns the flag used to abort type inference for the current request, if any.
+    fn cancellation_flag(&self) -> Option<CancellationFlag>;
+
+    /// Whether the current request is latency-sensitive, e.g. a completion, in which case type
+    /// inference shouldn't wait behind other requests.
+    fn is_latency_sensitive(&self) -> bool;
 }
 
 /// Return the diagnostics accumulated by Salsa queries on the given file.
@@ -234,6 +251,47 @@ impl<'a> Semantics<'a> {
         Some(with_tcx(self.db, |tcx| tcx.infer_expr(file, *expr).into()))
     }
 
+    /// Infers the return type of a function from the `return` statements in its body. Functions
+    /// whose end is reachable according to code-flow analysis may also implicitly return `None`.
+    /// Returns `None` if the type of any returned expression is unknown.
+    pub fn infer_return_type(&self, file: File, def_stmt: &ast::DefStmt) -> Option<Type> {
+        let suite = def_stmt.suite()?;
+        let mut tys = Vec::new();
+        for return_stmt in suite
+            .syntax()
+            .descendants()
+            .filter_map(ast::ReturnStmt::cast)
+        {
+            // Skip `return` statements belonging to nested functions.
+            if return_stmt
+                .syntax()
+                .ancestors()
+                .find_map(ast::DefStmt::cast)
+                .as_ref()
+                != Some(def_stmt)
+            {
+                continue;
+            }
+            let ty = match return_stmt.expr() {
+                Some(expr) => self.type_of_expr(file, &expr)?.ty,
+                None => Ty::none(),
+            };
+            if ty.kind() == &TyKind::Unknown {
+                return None;
+            }
+            tys.push(ty);
+        }
+        let ptr = AstPtr::new(&ast::Statement::Def(def_stmt.clone()));
+        let is_end_reachable = match source_map(self.db, file).stmt_map.get(&ptr) {
+            Some(stmt) => with_tcx(self.db, |tcx| tcx.is_end_of_def_reachable(file, *stmt)),
+            None => true,
+        };
+        if is_end_reachable {
+            tys.push(Ty::none());
+        }
+        Some(Ty::union(tys.into_iter()).into())
+    }
+
     pub fn resolve_param(&self, file: File, param: &ast::Parameter) -> Option<(Param, Type)> {
         let module = module(self.db, file);
         let param = source_map(self.db, file)
@@ -276,6 +334,15 @@ impl<'a> Semantics<'a> {
         })
     }
 
+    /// Returns the ranges of the definitions in the given file that are never accessed. Unlike
+    /// the corresponding diagnostics, this doesn't require type checking the file.
+    pub fn unused_definitions(&self, file: File) -> Vec<TextRange> {
+        unused_definitions_query(self.db, file)
+            .reported_defs(self.db, file, self.db.gcx().options())
+            .map(|def| def.range)
+            .collect()
+    }
+
     pub fn scope_for_module(&self, file: File) -> SemanticsScope {
         let resolver = Resolver::new_for_module(self.db, file);
         SemanticsScope { resolver }
@@ -383,6 +450,33 @@ impl Type {
         matches!(self.ty.kind(), TyKind::Function(_))
     }
 
+    pub fn is_rule(&self) -> bool {
+        matches!(self.ty.kind(), TyKind::Rule(_))
+    }
+
+    pub fn is_provider(&self) -> bool {
+        matches!(
+            self.ty.kind(),
+            TyKind::Provider(_) | TyKind::ProviderRawConstructor(_, _)
+        )
+    }
+
+    pub fn is_macro(&self) -> bool {
+        matches!(self.ty.kind(), TyKind::Macro(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self.ty.kind(), TyKind::String(_))
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self.ty.kind(), TyKind::List(_))
+    }
+
+    pub fn is_dict(&self) -> bool {
+        matches!(self.ty.kind(), TyKind::Dict(_, _, _))
+    }
+
     pub fn params(&self, db: &dyn Db) -> Vec<(Param, Type)> {
         match self.ty.params(db) {
             Some(params) => params.map(|(param, ty)| (param, ty.into())).collect(),
@@ -485,6 +579,24 @@ impl Type {
         }
     }
 
+    /// Returns the call that declared this type, e.g. the `provider()` call for a provider and its
+    /// instances, the `rule()` call for a rule, or the `struct()` call for an inline struct.
+    pub fn declaration_source(&self, db: &dyn Db) -> Option<InFile<ast::CallExpr>> {
+        let call_expr = match self.ty.kind() {
+            TyKind::Provider(Provider::Custom(provider))
+            | TyKind::ProviderInstance(Provider::Custom(provider))
+            | TyKind::ProviderRawConstructor(_, Provider::Custom(provider)) => provider.call_expr,
+            TyKind::Rule(rule) => rule.call_expr,
+            TyKind::Macro(makro) => makro.call_expr,
+            TyKind::ModuleExtension(module_extension)
+            | TyKind::ModuleExtensionProxy(module_extension) => module_extension.call_expr,
+            TyKind::Struct(Some(typeck::Struct::Inline { call_expr, .. })) => *call_expr,
+            TyKind::TagClass(tag_class) | TyKind::Tag(tag_class) => tag_class.call_expr,
+            _ => return None,
+        };
+        call_expr_source(db, call_expr)
+    }
+
     pub fn try_as_inline_struct(&self) -> Option<Struct> {
         match self.ty.kind() {
             TyKind::Struct(strukt) => strukt.as_ref().and_then(|strukt| match strukt {
@@ -514,19 +626,23 @@ pub struct Struct {
 impl Struct {
     /// Returns the AST node corresponding to the `struct()` call that created this struct.
     pub fn call_expr(&self, db: &dyn Db) -> Option<InFile<ast::CallExpr>> {
-        let call_expr = source_map(db, self.call_expr.file)
-            .expr_map_back
-            .get(&self.call_expr.value)
-            .cloned()?
-            .cast::<ast::CallExpr>()?
-            .try_to_node(&parse(db, self.call_expr.file).syntax(db))?;
-        Some(InFile {
-            file: self.call_expr.file,
-            value: call_expr,
-        })
+        call_expr_source(db, self.call_expr)
     }
 }
 
+fn call_expr_source(db: &dyn Db, call_expr: InFile<ExprId>) -> Option<InFile<ast::CallExpr>> {
+    let value = source_map(db, call_expr.file)
+        .expr_map_back
+        .get(&call_expr.value)
+        .cloned()?
+        .cast::<ast::CallExpr>()?
+        .try_to_node(&parse(db, call_expr.file).syntax(db))?;
+    Some(InFile {
+        file: call_expr.file,
+        value,
+    })
+}
+
 /// A variable definition.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Variable {
@@ -1,6 +1,7 @@
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::OnceLock;
 
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
@@ -19,10 +20,12 @@ use starpls_test_util::FixtureFile;
 use starpls_test_util::FixtureType;
 
 use crate::BuiltinDefs;
+use crate::CancellationFlag;
 use crate::Db;
 use crate::Dialect;
 use crate::GlobalContext;
 use crate::InferenceOptions;
+use crate::LoadResolution;
 
 #[derive(Default)]
 #[salsa::db(starpls_common::Jar, crate::Jar)]
@@ -32,6 +35,7 @@ pub(crate) struct TestDatabase {
     files: Arc<DashMap<FileId, File>>,
     prelude_file: Option<FileId>,
     all_workspace_targets: Arc<Vec<String>>,
+    load_resolution: Arc<OnceLock<LoadResolution>>,
     pub(crate) gcx: Arc<GlobalContext>,
 }
 
@@ -139,6 +143,20 @@ impl crate::Db for TestDatabase {
     fn get_all_workspace_targets(&self) -> Arc<Vec<String>> {
         Arc::clone(&self.all_workspace_targets)
     }
+
+    fn get_load_resolution(&self) -> LoadResolution {
+        *self
+            .load_resolution
+            .get_or_init(|| LoadResolution::new(self, 0))
+    }
+
+    fn cancellation_flag(&self) -> Option<CancellationFlag> {
+        None
+    }
+
+    fn is_latency_sensitive(&self) -> bool {
+        false
+    }
 }
 
 #[allow(unused)]
@@ -163,6 +163,8 @@ pub struct InferenceOptions {
     pub infer_ctx_attributes: bool,
     pub use_code_flow_analysis: bool,
     pub allow_unused_definitions: bool,
+    /// Also report load items that are never accessed as unused definitions.
+    pub report_unused_loads: bool,
 }
 
 #[derive(Default)]
@@ -171,6 +173,21 @@ struct SharedState {
     options: InferenceOptions,
 }
 
+/// Aborts the type inference for the queries on a single snapshot, e.g. once the client cancels
+/// the request being evaluated with it, without affecting type inference for other snapshots.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationFlag(Arc<AtomicCell<bool>>);
+
+impl CancellationFlag {
+    pub fn cancel(&self) {
+        self.0.store(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load()
+    }
+}
+
 /// A reference to a type in a source file.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum TypeRef {
@@ -916,6 +933,30 @@ impl Param {
         }
     }
 
+    /// Whether an argument must be provided for this parameter, i.e. it is a simple parameter
+    /// without a default value or a mandatory attribute.
+    pub fn is_mandatory(&self, db: &dyn Db) -> bool {
+        match self.0 {
+            ParamInner::Param { func, index } => {
+                let module = module(db, func.file(db));
+                matches!(
+                    module[func.params(db)[index]],
+                    HirDefParam::Simple { default: None, .. }
+                )
+            }
+            ParamInner::BuiltinParam { parent, index } => parent.params(db)[index].is_mandatory(),
+            ParamInner::RuleParam(RuleParam::Keyword { ref attr, .. })
+            | ParamInner::TagParam(TagParam::Keyword { ref attr, .. }) => attr.mandatory,
+            ParamInner::RuleParam(RuleParam::BuiltinKeyword(ref kind, index)) => {
+                common_attributes_query(db)
+                    .get(db, kind.clone(), index)
+                    .1
+                    .mandatory
+            }
+            _ => false,
+        }
+    }
+
     pub fn is_kwargs_dict(&self, db: &dyn Db) -> bool {
         match self.0 {
             // TODO(withered-magic): Handle lambda parameters.
@@ -1457,6 +1498,8 @@ pub(crate) struct Rule {
     pub(crate) kind: RuleKind,
     pub(crate) doc: Option<Box<str>>,
     pub(crate) attrs: Option<Arc<RuleAttributes>>,
+    /// The `rule()` or `repository_rule()` call that declared this rule.
+    pub(crate) call_expr: InFile<ExprId>,
 }
 
 impl Rule {
@@ -1500,6 +1543,8 @@ pub(crate) struct CustomProvider {
     pub(crate) name: Option<Name>,
     pub(crate) doc: Option<InternedString>,
     pub(crate) fields: Option<CustomProviderFields>,
+    /// The `provider()` call that declared this provider.
+    pub(crate) call_expr: InFile<ExprId>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -1549,6 +1594,8 @@ pub(crate) struct TagClassData {
 pub(crate) struct ModuleExtension {
     pub(crate) doc: Option<Box<str>>,
     pub(crate) tag_classes: Option<Box<[TagClassData]>>,
+    /// The `module_extension()` call that declared this module extension.
+    pub(crate) call_expr: InFile<ExprId>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -1561,6 +1608,8 @@ pub(crate) struct AttributeData {
 pub(crate) struct TagClass {
     pub(crate) attrs: Option<Box<[AttributeData]>>,
     pub(crate) doc: Option<InternedString>,
+    /// The `tag_class()` call that declared this tag class.
+    pub(crate) call_expr: InFile<ExprId>,
 }
 
 impl TyKind {
@@ -1586,6 +1635,8 @@ pub(crate) struct Macro {
     /// Attributes defined in the `attrs` argument to the `macro()` function.
     pub(crate) attrs: Option<Arc<RuleAttributes>>,
     pub(crate) doc: Option<InternedString>,
+    /// The `macro()` call that declared this macro.
+    pub(crate) call_expr: InFile<ExprId>,
 }
 
 impl Macro {
@@ -1700,16 +1751,50 @@ impl GlobalContext {
         CancelGuard::new(self)
     }
 
+    /// Discards all inferred types, e.g. because the files they were inferred from changed.
+    /// In-flight type inference should be cancelled first, since it holds on to the context.
+    pub fn clear(&self) {
+        *self.cx.lock() = Default::default();
+    }
+
+    pub(crate) fn options(&self) -> &InferenceOptions {
+        &self.shared_state.options
+    }
+
     pub fn with_tcx<F, T>(&self, db: &dyn Db, mut f: F) -> T
     where
         F: FnMut(&mut TyContext) -> T + std::panic::UnwindSafe,
     {
-        let mut cx = self.cx.lock();
+        // Latency-sensitive requests don't wait for other type inference, e.g. for diagnostics,
+        // to release the shared context. Instead, they infer the types they need in a scratch
+        // context that is discarded afterwards.
+        let mut shared_cx;
+        let mut scratch_cx;
+        let cx = match self.cx.try_lock() {
+            Some(guard) => {
+                shared_cx = guard;
+                &mut *shared_cx
+            }
+            None if db.is_latency_sensitive() => {
+                scratch_cx = InferenceContext::default();
+                &mut scratch_cx
+            }
+            None => {
+                shared_cx = self.cx.lock();
+                &mut *shared_cx
+            }
+        };
+
+        // Type inference aborted for a cancelled request can leave loads that were being
+        // resolved behind.
+        cx.load_resolution_stack.clear();
+
         let mut tcx = TyContext {
             db,
-            cx: &mut cx,
+            cx,
             intrinsics: intrinsic_types(db),
             shared_state: Arc::clone(&self.shared_state),
+            cancellation_flag: db.cancellation_flag(),
         };
         f(&mut tcx)
     }
@@ -1741,7 +1826,6 @@ pub(crate) struct InferenceContext {
     pub(crate) type_of_param: FxHashMap<FileParamId, Ty>,
     pub(crate) source_assign_done: FxHashSet<FileExprId>,
     pub(crate) flow_node_type_cache: FxHashMap<CodeFlowCacheKey, Option<Ty>>,
-    pub(crate) definition_is_used: FxHashMap<InFile<Either<ExprId, StmtId>>, bool>,
 }
 
 pub struct CancelGuard<'a> {
@@ -1758,9 +1842,9 @@ impl<'a> CancelGuard<'a> {
 
 impl Drop for CancelGuard<'_> {
     fn drop(&mut self) {
-        let mut cx = self.cx.lock();
+        // Wait for in-flight type inference to be aborted before allowing it again.
+        let _cx = self.cx.lock();
         self.gcx.shared_state.cancelled.store(false);
-        *cx = Default::default();
     }
 }
 
@@ -1769,6 +1853,7 @@ pub struct TyContext<'a> {
     cx: &'a mut InferenceContext,
     intrinsics: Intrinsics,
     shared_state: Arc<SharedState>,
+    cancellation_flag: Option<CancellationFlag>,
 }
 
 struct TypeRefResolver<'a, 'b> {
@@ -301,6 +301,10 @@ impl BuiltinFunction {
                         name: provider_name,
                         doc,
                         fields,
+                        call_expr: InFile {
+                            file,
+                            value: call_expr,
+                        },
                     }));
 
                     TyKind::Tuple(Tuple::Simple(smallvec![
@@ -324,6 +328,10 @@ impl BuiltinFunction {
                         name,
                         doc,
                         fields,
+                        call_expr: InFile {
+                            file,
+                            value: call_expr,
+                        },
                     })))
                 }
             }
@@ -357,6 +365,10 @@ impl BuiltinFunction {
                     },
                     doc: doc.map(|doc| doc.value(db).clone()),
                     attrs: attrs.map(Arc::new),
+                    call_expr: InFile {
+                        file,
+                        value: call_expr,
+                    },
                 })
             }
 
@@ -450,7 +462,14 @@ impl BuiltinFunction {
                     }
                 }
 
-                TyKind::TagClass(Arc::new(TagClass { attrs, doc }))
+                TyKind::TagClass(Arc::new(TagClass {
+                    attrs,
+                    doc,
+                    call_expr: InFile {
+                        file,
+                        value: call_expr,
+                    },
+                }))
             }
 
             (None, "module_extension") => {
@@ -489,7 +508,14 @@ impl BuiltinFunction {
                     }
                 }
 
-                TyKind::ModuleExtension(Arc::new(ModuleExtension { doc, tag_classes }))
+                TyKind::ModuleExtension(Arc::new(ModuleExtension {
+                    doc,
+                    tag_classes,
+                    call_expr: InFile {
+                        file,
+                        value: call_expr,
+                    },
+                }))
             }
 
             (None, "macro") => {
@@ -513,7 +539,14 @@ impl BuiltinFunction {
                     }
                 }
 
-                TyKind::Macro(Macro { attrs, doc })
+                TyKind::Macro(Macro {
+                    attrs,
+                    doc,
+                    call_expr: InFile {
+                        file,
+                        value: call_expr,
+                    },
+                })
             }
 
             (None, "use_extension") => {
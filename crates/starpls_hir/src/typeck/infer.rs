@@ -23,9 +23,9 @@ use crate::def::codeflow::code_flow_graph;
 use crate::def::codeflow::CodeFlowGraph;
 use crate::def::codeflow::FlowNode;
 use crate::def::codeflow::FlowNodeId;
+use crate::def::resolved_load_stmts_query;
 use crate::def::resolver::Export;
 use crate::def::resolver::Resolver;
-use crate::def::scope::module_scopes;
 use crate::def::scope::ExecutionScopeId;
 use crate::def::scope::FunctionDef;
 use crate::def::scope::LoadItemDef;
@@ -33,6 +33,7 @@ use crate::def::scope::ParameterDef;
 use crate::def::scope::ScopeDef;
 use crate::def::scope::ScopeHirId;
 use crate::def::scope::VariableDef;
+use crate::def::unused::unused_definitions_query;
 use crate::def::Argument;
 use crate::def::Expr;
 use crate::def::ExprId;
@@ -248,86 +249,20 @@ impl TyContext<'_> {
         }
     }
 
-    fn report_unused_definitions(&mut self, file: File) {
-        for (expr, name) in module(self.db, file).exprs.iter().filter_map(|(id, expr)| {
-            if let Expr::Name { name } = expr {
-                Some((id, name))
-            } else {
-                None
-            }
-        }) {
-            // Don't report assignments to the placeholder "_" as unused.
-            if name.as_str() == "_" {
-                continue;
-            }
-
-            if let Some(false) = self.cx.definition_is_used.get(&InFile {
-                file,
-                value: Either::Left(expr),
-            }) {
-                self.add_expr_diagnostic_warning(
-                    file,
-                    expr,
-                    Some(vec![DiagnosticTag::Unnecessary]),
-                    format!("\"{}\" is not accessed", name.as_str()),
-                );
-            }
-        }
-
-        for (stmt, name) in module(self.db, file)
-            .stmts
-            .iter()
-            .filter_map(|(id, stmt)| match stmt {
-                Stmt::Def { func, .. } => {
-                    let name = func.name(self.db);
-                    if name.is_missing() {
-                        None
-                    } else {
-                        Some((id, name))
-                    }
-                }
-                _ => None,
+    fn unused_definition_diagnostics(&self, file: File) -> Vec<Diagnostic> {
+        let file_id = file.id(self.db);
+        unused_definitions_query(self.db, file)
+            .reported_defs(self.db, file, &self.shared_state.options)
+            .map(|def| Diagnostic {
+                message: format!("\"{}\" is not accessed", def.name.as_str()),
+                severity: Severity::Warning,
+                range: FileRange {
+                    file_id,
+                    range: def.range,
+                },
+                tags: Some(vec![DiagnosticTag::Unnecessary]),
             })
-        {
-            if !self.cx.definition_is_used.contains_key(&InFile {
-                file,
-                value: Either::Right(stmt),
-            }) {
-                fn maybe_add_diagnostic(
-                    tcx: &mut TyContext,
-                    file: File,
-                    stmt: StmtId,
-                    name: &Name,
-                ) -> Option<()> {
-                    // Don't report exported functions as unused.
-                    let scopes = module_scopes(tcx.db, file).scopes(tcx.db);
-                    let scope = scopes.scopes_by_hir_id.get(&ScopeHirId::Stmt(stmt))?;
-                    if scopes.scopes[*scope].execution_scope == ExecutionScopeId::Module
-                        && !name.as_str().starts_with('_')
-                    {
-                        return None;
-                    }
-
-                    let ptr = source_map(tcx.db, file).stmt_map_back.get(&stmt)?;
-                    let node = ptr
-                        .syntax_node_ptr()
-                        .try_to_node(&parse(tcx.db, file).syntax(tcx.db))?;
-                    let def_stmt = ast::DefStmt::cast(node)?;
-                    let name_node = def_stmt.name()?;
-
-                    tcx.add_diagnostic_for_range(
-                        file,
-                        Severity::Warning,
-                        name_node.syntax().text_range(),
-                        Some(vec![DiagnosticTag::Unnecessary]),
-                        format!("\"{}\" is not accessed", name.as_str()),
-                    );
-                    Some(())
-                }
-
-                maybe_add_diagnostic(self, file, stmt, &name);
-            }
-        }
+            .collect()
     }
 
     pub fn diagnostics_for_file(&mut self, file: File) -> Vec<Diagnostic> {
@@ -337,29 +272,40 @@ impl TyContext<'_> {
         self.infer_all_params(file);
         self.walk_stmts(file, &module.top_level);
 
-        if !self.shared_state.options.allow_unused_definitions {
-            self.report_unused_definitions(file);
-        }
+        let unused_definitions = self.unused_definition_diagnostics(file);
 
         let line_index = line_index(self.db, file);
-        self.cx
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        for diagnostic in self
+            .cx
             .diagnostics
             .iter()
-            .filter(|diagnostic| {
-                if diagnostic.range.file_id != file.id(self.db) {
-                    return false;
-                }
-                let start_line = line_index.line_col(diagnostic.range.range.start()).line;
-                let end_line = line_index.line_col(diagnostic.range.range.end()).line;
-                (start_line..=end_line)
-                    .all(|line| !module.type_ignore_comment_lines.contains(&line))
-            })
-            .cloned()
-            .collect()
+            .filter(|diagnostic| diagnostic.range.file_id == file.id(self.db))
+            .chain(&unused_definitions)
+        {
+            let start_line = line_index.line_col(diagnostic.range.range.start()).line;
+            let end_line = line_index.line_col(diagnostic.range.range.end()).line;
+            if (start_line..=end_line).any(|line| module.type_ignore_comment_lines.contains(&line))
+            {
+                continue;
+            }
+
+            // Expressions whose inference was aborted for a cancelled request are inferred again
+            // later, which can report the same diagnostic twice.
+            if !diagnostics.contains(diagnostic) {
+                diagnostics.push(diagnostic.clone());
+            }
+        }
+        diagnostics
     }
 
     fn unwind_if_cancelled(&self) {
-        if self.shared_state.cancelled.load() {
+        if self.shared_state.cancelled.load()
+            || self
+                .cancellation_flag
+                .as_ref()
+                .is_some_and(|flag| flag.is_cancelled())
+        {
             TypecheckCancelled.throw();
         }
     }
@@ -1297,7 +1243,6 @@ impl TyContext<'_> {
         expr: ExprId,
         source: Option<ExprId>,
         expected_ty: Option<Ty>,
-        execution_scope: ExecutionScopeId,
     ) -> Ty {
         let cached_ty = self
             .cx
@@ -1307,7 +1252,7 @@ impl TyContext<'_> {
         cached_ty.unwrap_or_else(|| {
             source
                 .and_then(|source| {
-                    self.infer_source_expr_assign(file, source, expected_ty, execution_scope);
+                    self.infer_source_expr_assign(file, source, expected_ty);
                     self.cx
                         .type_of_expr
                         .get(&FileExprId::new(file, expr))
@@ -1317,18 +1262,12 @@ impl TyContext<'_> {
         })
     }
 
-    fn infer_source_expr_assign(
-        &mut self,
-        file: File,
-        source: ExprId,
-        expected_ty: Option<Ty>,
-        execution_scope: ExecutionScopeId,
-    ) {
+    fn infer_source_expr_assign(&mut self, file: File, source: ExprId, expected_ty: Option<Ty>) {
         let key = FileExprId::new(file, source);
         if self.cx.source_assign_done.contains(&key) {
             return;
         }
-        self.infer_source_expr_assign_inner(file, source, expected_ty, execution_scope);
+        self.infer_source_expr_assign_inner(file, source, expected_ty);
         self.cx.source_assign_done.insert(key);
     }
 
@@ -1337,7 +1276,6 @@ impl TyContext<'_> {
         file: File,
         source: ExprId,
         expected_ty: Option<Ty>,
-        execution_scope: ExecutionScopeId,
     ) {
         // Find the parent assignment node. This can be either an assignment statement (`x = 0`), a `for` statement (`for x in 1, 2, 3`), or
         // a for comp clause in a list/dict comprehension (`[x + 1 for x in [1, 2, 3]]`).
@@ -1391,14 +1329,7 @@ impl TyContext<'_> {
             if let Some(lhs) = node.lhs() {
                 let lhs_ptr = AstPtr::new(&lhs);
                 let expr = source_map.expr_map.get(&lhs_ptr).unwrap();
-                self.assign_expr_source_ty(
-                    file,
-                    source,
-                    *expr,
-                    source_ty,
-                    expected_ty,
-                    execution_scope,
-                );
+                self.assign_expr_source_ty(file, source, *expr, source_ty, expected_ty);
                 return;
             }
         }
@@ -1439,15 +1370,15 @@ impl TyContext<'_> {
                     format!("Type \"{}\" is not iterable", source_ty.display(db).alt()),
                 );
                 for expr in targets.iter() {
-                    self.assign_expr_unknown_rec(file, *expr, execution_scope);
+                    self.assign_expr_unknown_rec(file, *expr);
                 }
                 return;
             }
         };
         if targets.len() == 1 {
-            self.assign_expr_source_ty(file, targets[0], targets[0], sub_ty, None, execution_scope);
+            self.assign_expr_source_ty(file, targets[0], targets[0], sub_ty, None);
         } else {
-            self.assign_exprs_source_ty(file, source, &targets, sub_ty, execution_scope);
+            self.assign_exprs_source_ty(file, source, &targets, sub_ty);
         }
     }
 
@@ -1472,39 +1403,14 @@ impl TyContext<'_> {
                 for def in defs.skip_while(|def| def.scope > expr_scope) {
                     let ty = match def.def {
                         ScopeDef::Variable(VariableDef { file, expr, source }) => {
-                            if def_execution_scope != ExecutionScopeId::Module
-                                || name.as_str().starts_with('_')
-                            {
-                                if let ScopeHirId::Expr(usage_expr) = hir_id {
-                                    let key = InFile {
-                                        file: *file,
-                                        value: Either::Left(*expr),
-                                    };
-                                    if usage_expr != *expr {
-                                        self.cx.definition_is_used.insert(key, true);
-                                    }
-                                }
-                            }
-
                             if self.shared_state.options.use_code_flow_analysis {
                                 var_defs.push((file, *expr, *source));
                                 continue;
                             } else {
-                                self.infer_assign(*file, *expr, *source, None, def_execution_scope)
-                            }
-                        }
-                        ScopeDef::Function(def) => {
-                            if let Some(stmt) = def.stmt() {
-                                self.cx.definition_is_used.insert(
-                                    InFile {
-                                        file: stmt.file,
-                                        value: Either::Right(stmt.value),
-                                    },
-                                    true,
-                                );
+                                self.infer_assign(*file, *expr, *source, None)
                             }
-                            TyKind::Function(def.clone()).intern()
                         }
+                        ScopeDef::Function(def) => TyKind::Function(def.clone()).intern(),
                         ScopeDef::Parameter(ParameterDef { func, index }) => {
                             self.infer_param(file, func.params(self.db)[*index])
                         }
@@ -1529,7 +1435,7 @@ impl TyContext<'_> {
                 // logic (i.e. `known_ty` is `Some`), we still compute the effective type if only to infer types
                 // for the relevant assignment statements.
                 let effective_ty = Ty::union(var_defs.into_iter().map(|(file, expr, source)| {
-                    self.infer_assign(*file, expr, source, known_ty.clone(), def_execution_scope)
+                    self.infer_assign(*file, expr, source, known_ty.clone())
                 }));
 
                 if known_ty.is_some() {
@@ -1626,7 +1532,7 @@ impl TyContext<'_> {
                         continue;
                     }
 
-                    self.infer_source_expr_assign(file, *source, None, execution_scope);
+                    self.infer_source_expr_assign(file, *source, None);
                     self.cx
                         .type_of_expr
                         .get(&FileExprId::new(file, *expr))
@@ -1671,6 +1577,38 @@ impl TyContext<'_> {
         self.cache_ref_type_at_flow_node(file, execution_scope, name, start_node, res)
     }
 
+    /// Returns whether the end of the given function's body is reachable, i.e. whether the
+    /// function can implicitly return `None`.
+    pub(crate) fn is_end_of_def_reachable(&mut self, file: File, stmt: StmtId) -> bool {
+        let cfg = code_flow_graph(self.db, file).cfg(self.db);
+        match cfg.def_end_flow_node.get(&stmt) {
+            Some(end_node) => self.is_flow_node_reachable(cfg, file, *end_node),
+            None => true,
+        }
+    }
+
+    fn is_flow_node_reachable(
+        &mut self,
+        cfg: &CodeFlowGraph,
+        file: File,
+        node: FlowNodeId,
+    ) -> bool {
+        match &cfg.flow_nodes[node] {
+            FlowNode::Start | FlowNode::Loop { .. } => true,
+            FlowNode::Assign { antecedent, .. } => {
+                self.is_flow_node_reachable(cfg, file, *antecedent)
+            }
+            FlowNode::Branch { antecedents } => antecedents
+                .iter()
+                .any(|antecedent| self.is_flow_node_reachable(cfg, file, *antecedent)),
+            FlowNode::Call { expr, antecedent } => {
+                self.infer_expr(file, *expr) != Ty::never()
+                    && self.is_flow_node_reachable(cfg, file, *antecedent)
+            }
+            FlowNode::Unreachable => false,
+        }
+    }
+
     fn exists_flow_path(
         &mut self,
         cfg: &CodeFlowGraph,
@@ -1746,19 +1684,9 @@ impl TyContext<'_> {
         expr: ExprId,
         source_ty: Ty,
         expected_ty: Option<Ty>,
-        execution_scope: ExecutionScopeId,
     ) {
         match module(self.db, file).exprs.get(expr).unwrap() {
-            Expr::Name { name } => {
-                if execution_scope != ExecutionScopeId::Module || name.as_str().starts_with('_') {
-                    self.cx
-                        .definition_is_used
-                        .entry(InFile {
-                            file,
-                            value: Either::Left(expr),
-                        })
-                        .or_insert(false);
-                }
+            Expr::Name { .. } => {
                 // If we have an expected type from a type comment, use that.
                 // We also emit any error if the source and expected types aren't compatible.
                 if let Some(expected_ty) = expected_ty {
@@ -1779,11 +1707,9 @@ impl TyContext<'_> {
                 }
             }
             Expr::List { exprs } | Expr::Tuple { exprs } => {
-                self.assign_exprs_source_ty(file, root, exprs, source_ty, execution_scope);
-            }
-            Expr::Paren { expr } => {
-                self.assign_expr_source_ty(file, root, *expr, source_ty, None, execution_scope)
+                self.assign_exprs_source_ty(file, root, exprs, source_ty);
             }
+            Expr::Paren { expr } => self.assign_expr_source_ty(file, root, *expr, source_ty, None),
             _ => {}
         }
     }
@@ -1794,23 +1720,22 @@ impl TyContext<'_> {
         root: ExprId,
         exprs: &[ExprId],
         source_ty: Ty,
-        execution_scope: ExecutionScopeId,
     ) {
         match source_ty.kind() {
             TyKind::List(ty) | TyKind::Tuple(Tuple::Variable(ty)) => {
                 for expr in exprs.iter().copied() {
-                    self.assign_expr_source_ty(file, root, expr, ty.clone(), None, execution_scope);
+                    self.assign_expr_source_ty(file, root, expr, ty.clone(), None);
                 }
             }
             TyKind::Tuple(Tuple::Simple(tys)) => {
                 let pairs = exprs.iter().copied().zip(tys.iter());
                 for (expr, ty) in pairs {
-                    self.assign_expr_source_ty(file, root, expr, ty.clone(), None, execution_scope);
+                    self.assign_expr_source_ty(file, root, expr, ty.clone(), None);
                 }
                 if exprs.len() != tys.len() {
                     if exprs.len() > tys.len() {
                         for expr in &exprs[tys.len()..] {
-                            self.assign_expr_unknown_rec(file, *expr, execution_scope);
+                            self.assign_expr_unknown_rec(file, *expr);
                         }
                     }
                     self.add_expr_diagnostic_error(
@@ -1826,14 +1751,7 @@ impl TyContext<'_> {
             }
             TyKind::Any | TyKind::Unknown => {
                 for expr in exprs.iter().copied() {
-                    self.assign_expr_source_ty(
-                        file,
-                        root,
-                        expr,
-                        self.unknown_ty(),
-                        None,
-                        execution_scope,
-                    );
+                    self.assign_expr_source_ty(file, root, expr, self.unknown_ty(), None);
                 }
             }
             _ => {
@@ -1847,38 +1765,21 @@ impl TyContext<'_> {
                     ),
                 );
                 for expr in exprs.iter() {
-                    self.assign_expr_unknown_rec(file, *expr, execution_scope);
+                    self.assign_expr_unknown_rec(file, *expr);
                 }
             }
         }
     }
 
-    fn assign_expr_unknown_rec(
-        &mut self,
-        file: File,
-        expr: ExprId,
-        execution_scope: ExecutionScopeId,
-    ) {
+    fn assign_expr_unknown_rec(&mut self, file: File, expr: ExprId) {
         let module = module(self.db, file);
         let node = &module[expr];
         self.set_expr_type(file, expr, self.unknown_ty());
 
-        match node {
-            Expr::Name { name }
-                if execution_scope != ExecutionScopeId::Module
-                    || name.as_str().starts_with('_') =>
-            {
-                self.cx
-                    .definition_is_used
-                    .entry(InFile {
-                        file,
-                        value: Either::Left(expr),
-                    })
-                    .or_insert(false);
-            }
-            _ => node.walk_child_exprs(|expr| {
-                self.assign_expr_unknown_rec(file, expr, execution_scope);
-            }),
+        if !matches!(node, Expr::Name { .. }) {
+            node.walk_child_exprs(|expr| {
+                self.assign_expr_unknown_rec(file, expr);
+            });
         }
     }
 
@@ -2206,14 +2107,13 @@ impl TyContext<'_> {
             return *loaded_file;
         }
 
-        let module = load_stmt.module(self.db);
-        let res = match self
-            .db
-            .load_file(module, file.dialect(self.db), file.id(self.db))
+        let res = match resolved_load_stmts_query(self.db, file)
+            .resolved(self.db)
+            .get(&load_stmt)
         {
-            Ok(Some(loaded_file)) => Some(loaded_file),
-            Ok(None) => return None,
-            Err(err) => {
+            Some(Ok(Some(loaded_file))) => Some(*loaded_file),
+            Some(Ok(None)) | None => return None,
+            Some(Err(err)) => {
                 self.add_diagnostic_for_range(
                     file,
                     Severity::Warning,
@@ -7,6 +7,7 @@ use itertools::Itertools;
 use starpls_bazel::APIContext;
 use starpls_common::parse;
 use starpls_common::Db as _;
+use starpls_common::DiagnosticTag;
 use starpls_common::Dialect;
 use starpls_common::FileId;
 use starpls_common::FileInfo;
@@ -46,6 +47,55 @@ fn check_infer_with_unused_definitions(input: &str, expect: Expect) {
     check_infer_with_options(input, expect, Default::default())
 }
 
+fn check_unused_definitions(input: &str, expect: Expect) {
+    check_unused_definitions_with_options(
+        input,
+        expect,
+        InferenceOptions {
+            report_unused_loads: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// Checks only the diagnostics for unused definitions, which don't depend on the builtins.
+fn check_unused_definitions_with_options(input: &str, expect: Expect, options: InferenceOptions) {
+    let mut builder = TestDatabaseBuilder::default();
+    builder.set_inference_options(options);
+    let mut db = builder.build();
+    let file = db.create_file(
+        FileId(0),
+        Dialect::Bazel,
+        Some(FileInfo::Bazel {
+            api_context: APIContext::Bzl,
+            is_external: false,
+        }),
+        input.to_string(),
+    );
+    let diagnostics = db.gcx.with_tcx(&db, |tcx| tcx.diagnostics_for_file(file));
+    let mut res = String::new();
+    for diagnostic in diagnostics
+        .into_iter()
+        .filter(|diagnostic| {
+            diagnostic
+                .tags
+                .as_ref()
+                .is_some_and(|tags| tags.contains(&DiagnosticTag::Unnecessary))
+        })
+        .sorted_by(|lhs, rhs| lhs.range.range.start().cmp(&rhs.range.range.start()))
+    {
+        writeln!(
+            res,
+            "{:?}..{:?} {}",
+            diagnostic.range.range.start(),
+            diagnostic.range.range.end(),
+            diagnostic.message
+        )
+        .unwrap();
+    }
+    expect.assert_eq(&res);
+}
+
 fn check_infer_with_options(input: &str, expect: Expect, options: InferenceOptions) {
     let mut builder = TestDatabaseBuilder::default();
     builder.add_function("provider");
@@ -2144,6 +2194,67 @@ def f():
     );
 }
 
+#[test]
+fn test_early_return() {
+    check_infer_with_code_flow_analysis(
+        r#"
+cond = 1 < 2
+def f():
+    x = 0
+    if cond:
+        x = "less"
+        return
+    x
+
+def g():
+    x = 0
+    if cond:
+        x = "less"
+    else:
+        x = 1.
+        return
+    x
+
+def h():
+    x = 1
+    for y in 1, 2, 3:
+        x = "one"
+        return
+    x
+"#,
+        expect![[r#"
+            1..5 "cond": bool
+            8..9 "1": Literal[1]
+            12..13 "2": Literal[2]
+            8..13 "1 < 2": bool
+            27..28 "x": Literal[0]
+            31..32 "0": Literal[0]
+            40..44 "cond": bool
+            54..55 "x": Literal["less"]
+            58..64 "\"less\"": Literal["less"]
+            84..85 "x": int
+            100..101 "x": Literal[0]
+            104..105 "0": Literal[0]
+            113..117 "cond": bool
+            127..128 "x": Literal["less"]
+            131..137 "\"less\"": Literal["less"]
+            156..157 "x": float
+            160..162 "1.": float
+            182..183 "x": string
+            198..199 "x": Literal[1]
+            202..203 "1": Literal[1]
+            212..213 "y": int
+            217..218 "1": Literal[1]
+            220..221 "2": Literal[2]
+            223..224 "3": Literal[3]
+            217..224 "1, 2, 3": tuple[Literal[1], Literal[2], Literal[3]]
+            234..235 "x": Literal["one"]
+            238..243 "\"one\"": Literal["one"]
+            263..264 "x": string | int
+        "#]],
+    );
+}
+
 #[test]
 fn test_unused_definitions() {
     check_infer_with_unused_definitions(
@@ -2240,6 +2351,51 @@ def baz():
     );
 }
 
+#[test]
+fn test_unused_load_items() {
+    check_unused_definitions(
+        r#"
+load(":a.bzl", "used", "unused", _reexported = "reexported")
+load(":b.bzl", "attr_only", "nested_only", "type_only")
+
+exported = _reexported
+
+def foo():
+    return attr_only.bar
+
+def bar():
+    def nested():
+        return nested_only
+    return nested
+
+def baz(x):
+    # type: (type_only.Info) -> None
+    pass
+
+used()
+"#,
+        expect![[r#"
+            24..32 "unused" is not accessed
+        "#]],
+    );
+}
+
+#[test]
+fn test_unused_load_items_not_reported_by_default() {
+    check_unused_definitions_with_options(
+        r#"
+load(":a.bzl", "unused")
+
+def _foo():
+    pass
+"#,
+        expect![[r#"
+            31..35 "_foo" is not accessed
+        "#]],
+        Default::default(),
+    );
+}
+
 #[test]
 fn test_unreachable_fail() {
     check_infer_with_code_flow_analysis(
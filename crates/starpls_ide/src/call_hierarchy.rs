@@ -0,0 +1,455 @@
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_common::FileId;
+use starpls_common::InFile;
+use starpls_hir::Name;
+use starpls_hir::ScopeDef;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::SyntaxKind::*;
+use starpls_syntax::SyntaxToken;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
+use starpls_syntax::T;
+
+use crate::find_references::find_references;
+use crate::util::pick_best_token;
+use crate::Database;
+use crate::FilePosition;
+use crate::SymbolKind;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallHierarchyItem {
+    /// The name of the function, rule or macro. This is `None` for items representing the top
+    /// level of a file, e.g. for the targets declared by a BUILD file.
+    pub name: Option<String>,
+    pub kind: SymbolKind,
+    pub file_id: FileId,
+    pub range: TextRange,
+    pub selection_range: TextRange,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallHierarchyCall {
+    pub item: CallHierarchyItem,
+
+    /// The ranges of the callees at each call site. These are always relative to the file of the
+    /// calling item.
+    pub ranges: Vec<TextRange>,
+}
+
+pub(crate) fn prepare_call_hierarchy(
+    db: &Database,
+    FilePosition { file_id, pos }: FilePosition,
+) -> Option<Vec<CallHierarchyItem>> {
+    let sema = Semantics::new(db);
+    let file = db.get_file(file_id)?;
+    let token = pick_token(&sema, file, pos)?;
+    let node = token.parent()?;
+
+    let defs = if let Some(node) = ast::NameRef::cast(node.clone()) {
+        let name = Name::from_ast_name_ref(node.clone());
+        let scope = sema.scope_for_expr(file, &ast::Expression::cast(node.syntax().clone())?)?;
+        scope.resolve_name(&name)
+    } else if let Some(node) = ast::Name::cast(node) {
+        let def_stmt = ast::DefStmt::cast(node.syntax().parent()?)?;
+        vec![ScopeDef::Callable(sema.resolve_def_stmt(file, &def_stmt)?)]
+    } else {
+        return None;
+    };
+
+    let mut items = Vec::new();
+    for item in defs.into_iter().filter_map(|def| item_for_def(&sema, def)) {
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+    (!items.is_empty()).then_some(items)
+}
+
+/// Finds the calls to the function, rule or macro whose name is at the given position, grouped
+/// by the calling function. Calls made from the top level of a file, e.g. the rule and macro
+/// calls in a BUILD file, are grouped under an item for the file itself.
+pub(crate) fn incoming_calls(db: &Database, pos: FilePosition) -> Option<Vec<CallHierarchyCall>> {
+    prepare_call_hierarchy(db, pos)?;
+    let sema = Semantics::new(db);
+    let mut calls = Vec::new();
+    for location in find_references(db, pos)? {
+        let Some(file) = db.get_file(location.file_id) else {
+            continue;
+        };
+
+        // Only references that are the callee of a call expression are calls.
+        let Some(call_expr) = sema
+            .parse(file)
+            .syntax(db)
+            .covering_element(location.range)
+            .into_token()
+            .and_then(|token| token.parent())
+            .filter(|node| node.kind() == NAME_REF)
+            .and_then(|node| node.parent())
+            .and_then(ast::CallExpr::cast)
+        else {
+            continue;
+        };
+        if sema.resolve_call_expr(file, &call_expr).is_none() {
+            continue;
+        }
+
+        let caller = match call_expr.syntax().ancestors().find_map(ast::DefStmt::cast) {
+            Some(def_stmt) => def_stmt_item(location.file_id, &def_stmt),
+            None => Some(file_item(&sema, file)),
+        };
+        if let Some(caller) = caller {
+            add_call(&mut calls, caller, location.range);
+        }
+    }
+    Some(calls)
+}
+
+/// Finds the calls made by the function whose name is at the given position, grouped by the
+/// function, rule or macro being called. If `pos` is `None`, the calls made from the top level of
+/// the file are returned instead.
+pub(crate) fn outgoing_calls(
+    db: &Database,
+    file_id: FileId,
+    pos: Option<TextSize>,
+) -> Option<Vec<CallHierarchyCall>> {
+    let sema = Semantics::new(db);
+    let file = db.get_file(file_id)?;
+    let call_exprs = match pos {
+        Some(pos) => {
+            let token = pick_token(&sema, file, pos)?;
+            let Some(def_stmt) = ast::Name::cast(token.parent()?)
+                .and_then(|name| name.syntax().parent())
+                .and_then(ast::DefStmt::cast)
+            else {
+                // Rules and macros don't call anything directly.
+                return Some(Vec::new());
+            };
+            def_stmt
+                .syntax()
+                .descendants()
+                .filter_map(ast::CallExpr::cast)
+                .collect::<Vec<_>>()
+        }
+        None => sema
+            .parse(file)
+            .syntax(db)
+            .descendants()
+            .filter_map(ast::CallExpr::cast)
+            .filter(|call_expr| {
+                !call_expr
+                    .syntax()
+                    .ancestors()
+                    .any(|node| node.kind() == DEF_STMT)
+            })
+            .collect(),
+    };
+
+    let mut calls = Vec::new();
+    for call_expr in call_exprs {
+        let Some(callee) = call_expr.callee() else {
+            continue;
+        };
+        if let Some(target) = call_target(&sema, file, &call_expr, &callee) {
+            add_call(&mut calls, target, callee.syntax().text_range());
+        }
+    }
+    Some(calls)
+}
+
+/// Maps a call site to the function, rule or macro being called. Builtin functions and rules are
+/// skipped since they aren't defined in any file.
+fn call_target(
+    sema: &Semantics,
+    file: File,
+    call_expr: &ast::CallExpr,
+    callee: &ast::Expression,
+) -> Option<CallHierarchyItem> {
+    let callable = sema.resolve_call_expr(file, call_expr)?;
+    if callable.is_user_defined() {
+        return item_for_def(sema, ScopeDef::Callable(callable));
+    }
+
+    // Rules and macros can only be located through the variables they are assigned to.
+    if !callable.is_rule() && !callable.is_macro() {
+        return None;
+    }
+    let ast::Expression::Name(name_ref) = callee else {
+        return None;
+    };
+    let scope = sema.scope_for_expr(file, callee)?;
+    scope
+        .resolve_name(&Name::from_ast_name_ref(name_ref.clone()))
+        .into_iter()
+        .find_map(|def| item_for_def(sema, def))
+}
+
+/// Converts a user-defined function, or a variable holding a rule or macro, into an item.
+/// Loaded symbols are followed to their definitions.
+fn item_for_def(sema: &Semantics, def: ScopeDef) -> Option<CallHierarchyItem> {
+    let db = sema.db;
+    match def {
+        ScopeDef::LoadItem(ref load_item) => item_for_def(sema, sema.def_for_load_item(load_item)?),
+        ScopeDef::Callable(ref callable) if callable.is_user_defined() => {
+            let InFile { file, value: ptr } = def.syntax_node_ptr(db)?;
+            let def_stmt = ptr
+                .try_to_node(&sema.parse(file).syntax(db))
+                .and_then(ast::DefStmt::cast)?;
+            def_stmt_item(file.id(db), &def_stmt)
+        }
+        ScopeDef::Variable(_) => {
+            let ty = def.ty(db);
+            let kind = if ty.is_rule() {
+                SymbolKind::Class
+            } else if ty.is_macro() {
+                SymbolKind::Function
+            } else {
+                return None;
+            };
+            let InFile { file, value: ptr } = def.syntax_node_ptr(db)?;
+            let node = ptr.try_to_node(&sema.parse(file).syntax(db))?;
+            let assign_stmt = node.ancestors().find_map(ast::AssignStmt::cast)?;
+            Some(CallHierarchyItem {
+                name: Some(node.text().to_string()),
+                kind,
+                file_id: file.id(db),
+                range: assign_stmt.syntax().text_range(),
+                selection_range: node.text_range(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn def_stmt_item(file_id: FileId, def_stmt: &ast::DefStmt) -> Option<CallHierarchyItem> {
+    let name = def_stmt.name()?;
+    Some(CallHierarchyItem {
+        name: Some(name.syntax().text().to_string()),
+        kind: SymbolKind::Function,
+        file_id,
+        range: def_stmt.syntax().text_range(),
+        selection_range: name.syntax().text_range(),
+    })
+}
+
+fn file_item(sema: &Semantics, file: File) -> CallHierarchyItem {
+    let root = sema.parse(file).syntax(sema.db);
+    CallHierarchyItem {
+        name: None,
+        kind: SymbolKind::File,
+        file_id: file.id(sema.db),
+        range: root.text_range(),
+        selection_range: TextRange::empty(root.text_range().start()),
+    }
+}
+
+fn add_call(calls: &mut Vec<CallHierarchyCall>, item: CallHierarchyItem, range: TextRange) {
+    match calls.iter_mut().find(|call| call.item == item) {
+        Some(call) => call.ranges.push(range),
+        None => calls.push(CallHierarchyCall {
+            item,
+            ranges: vec![range],
+        }),
+    }
+}
+
+fn pick_token(sema: &Semantics, file: File, pos: TextSize) -> Option<SyntaxToken> {
+    pick_best_token(
+        sema.parse(file).syntax(sema.db).token_at_offset(pos),
+        |kind| match kind {
+            T![ident] => 2,
+            T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}'] => 0,
+            kind if kind.is_trivia_token() => 0,
+            _ => 1,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_bazel::APIContext;
+    use starpls_common::Db;
+    use starpls_common::Dialect;
+    use starpls_common::FileId;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
+    use crate::Analysis;
+    use crate::CallHierarchyCall;
+    use crate::FilePosition;
+
+    fn render_calls(analysis: &Analysis, calls: Vec<CallHierarchyCall>, file_id: FileId) -> String {
+        let contents = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+        calls
+            .into_iter()
+            .map(|call| {
+                format!(
+                    "{} {:?} {:?}\n",
+                    call.item.name.as_deref().unwrap_or("<file>"),
+                    call.item.kind,
+                    call.ranges
+                        .iter()
+                        .map(|range| &contents[*range])
+                        .collect::<Vec<_>>()
+                )
+            })
+            .collect()
+    }
+
+    fn check_call_hierarchy(
+        analysis: &Analysis,
+        fixture: &Fixture,
+        expect_incoming: Expect,
+        expect_outgoing: Expect,
+    ) {
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        let snapshot = analysis.snapshot();
+        let items = snapshot
+            .prepare_call_hierarchy(FilePosition { file_id, pos })
+            .unwrap()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        let pos = FilePosition {
+            file_id: item.file_id,
+            pos: item.selection_range.start(),
+        };
+
+        let incoming = snapshot
+            .call_hierarchy_incoming_calls(pos)
+            .unwrap()
+            .unwrap();
+        let actual = incoming
+            .into_iter()
+            .map(|call| {
+                let file_id = call.item.file_id;
+                render_calls(analysis, vec![call], file_id)
+            })
+            .collect::<String>();
+        expect_incoming.assert_eq(&actual);
+
+        let outgoing = snapshot
+            .call_hierarchy_outgoing_calls(pos.file_id, Some(pos.pos))
+            .unwrap()
+            .unwrap();
+        expect_outgoing.assert_eq(&render_calls(analysis, outgoing, pos.file_id));
+    }
+
+    #[test]
+    fn test_function_calls() {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(
+            r#"
+def _helper():
+    pass
+
+def f$0oo():
+    _helper()
+    len([_helper()])
+
+def bar():
+    return foo()
+
+foo()
+"#,
+        );
+        check_call_hierarchy(
+            &analysis,
+            &fixture,
+            expect![[r#"
+                bar Function ["foo"]
+                <file> File ["foo"]
+            "#]],
+            expect![[r#"
+                _helper Function ["_helper", "_helper"]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_calls_across_loads() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:defs.bzl",
+            r#"
+def _impl(ctx):
+    pass
+
+my_rule = rule(implementation = _impl)
+
+def my_m$0acro(name):
+    my_rule(name = name)
+    my_rule(name = name + "_test")
+"#,
+        );
+        let build_file_id = fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:defs.bzl", "my_macro")
+
+my_macro(name = "foo")
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+        check_call_hierarchy(
+            &analysis,
+            &fixture,
+            expect![[r#"
+                <file> File ["my_macro"]
+            "#]],
+            expect![[r#"
+                my_rule Class ["my_rule", "my_rule"]
+            "#]],
+        );
+
+        let outgoing = analysis
+            .snapshot()
+            .call_hierarchy_outgoing_calls(build_file_id, None)
+            .unwrap()
+            .unwrap();
+        expect![[r#"
+            my_macro Function ["my_macro"]
+        "#]]
+        .assert_eq(&render_calls(&analysis, outgoing, build_file_id));
+    }
+
+    #[test]
+    fn test_rule_calls() {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(
+            r#"
+def _impl(ctx):
+    pass
+
+my_rule = rule(implementation = _impl)
+
+def my_macro(name):
+    my_r$0ule(name = name)
+"#,
+        );
+        check_call_hierarchy(
+            &analysis,
+            &fixture,
+            expect![[r#"
+                my_macro Function ["my_rule"]
+            "#]],
+            expect![""],
+        );
+    }
+}
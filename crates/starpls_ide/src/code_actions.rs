@@ -0,0 +1,640 @@
+use starpls_common::parse;
+use starpls_common::Db;
+use starpls_common::Diagnostic;
+use starpls_common::DiagnosticTag;
+use starpls_common::File;
+use starpls_common::FileId;
+use starpls_hir::Name;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::SyntaxKind::*;
+use starpls_syntax::SyntaxNode;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
+
+use crate::diagnostics::diagnostics;
+use crate::Database;
+use crate::SourceChange;
+use crate::SymbolIndex;
+use crate::TextEdit;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeActionKind {
+    QuickFix,
+    SourceFixAll,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeAction {
+    pub title: String,
+    pub kind: CodeActionKind,
+    /// The diagnostic fixed by this action, if any.
+    pub diagnostic: Option<Diagnostic>,
+    pub change: SourceChange,
+    pub is_preferred: bool,
+}
+
+pub(crate) fn code_actions(
+    db: &Database,
+    file_id: FileId,
+    range: TextRange,
+    symbol_index: &SymbolIndex,
+) -> Option<Vec<CodeAction>> {
+    let sema = Semantics::new(db);
+    let file = db.get_file(file_id)?;
+    let diagnostics = diagnostics(db, file_id);
+    let mut actions = Vec::new();
+    let mut has_unused_symbol = false;
+    for diagnostic in diagnostics.iter() {
+        if diagnostic.range.range.intersect(range).is_none() {
+            continue;
+        }
+        add_missing_load(db, &sema, file, diagnostic, symbol_index, &mut actions);
+        has_unused_symbol |= remove_unused_symbol(db, file, diagnostic, &mut actions).is_some();
+    }
+    if has_unused_symbol {
+        remove_all_unused_symbols(db, file, &diagnostics, &mut actions);
+    }
+    Some(actions)
+}
+
+/// Offers to load an undefined name from the workspace `.bzl` files that export it, extending an
+/// existing `load` statement for the same module if possible.
+fn add_missing_load(
+    db: &Database,
+    sema: &Semantics,
+    file: File,
+    diagnostic: &Diagnostic,
+    symbol_index: &SymbolIndex,
+    actions: &mut Vec<CodeAction>,
+) -> Option<()> {
+    let tree = parse(db, file).tree(db);
+    let name_ref = tree
+        .syntax()
+        .token_at_offset(diagnostic.range.range.start())
+        .right_biased()?
+        .parent()
+        .and_then(ast::NameRef::cast)?;
+    let name = Name::from_ast_name_ref(name_ref);
+    if diagnostic.message != format!("\"{}\" is not defined", name.as_str()) {
+        return None;
+    }
+
+    let candidates = load_candidates(db, file, &name, symbol_index);
+    let is_preferred = candidates.len() == 1;
+    for (label, candidate) in candidates {
+        let Some(edit) = load_edit(db, sema, file, &tree, &label, candidate, name.as_str()) else {
+            continue;
+        };
+        actions.push(CodeAction {
+            title: format!("Load \"{}\" from \"{}\"", name.as_str(), label),
+            kind: CodeActionKind::QuickFix,
+            diagnostic: Some(diagnostic.clone()),
+            change: SourceChange {
+                file_edits: [(file.id(db), vec![edit])].into_iter().collect(),
+            },
+            is_preferred,
+        });
+    }
+
+    Some(())
+}
+
+/// A symbol reported as unused that can be safely deleted.
+enum UnusedSymbol {
+    LoadItem(ast::LoadStmt, ast::LoadItem),
+    Assignment(ast::AssignStmt),
+}
+
+impl UnusedSymbol {
+    fn from_diagnostic(root: &SyntaxNode, diagnostic: &Diagnostic) -> Option<UnusedSymbol> {
+        if !diagnostic.message.ends_with(" is not accessed")
+            || !diagnostic
+                .tags
+                .as_ref()
+                .is_some_and(|tags| tags.contains(&DiagnosticTag::Unnecessary))
+        {
+            return None;
+        }
+
+        let parent = root
+            .token_at_offset(diagnostic.range.range.start())
+            .right_biased()?
+            .parent()?;
+        if let Some(load_item) = parent.ancestors().find_map(ast::LoadItem::cast) {
+            let load_stmt = load_item.syntax().parent().and_then(ast::LoadStmt::cast)?;
+            return Some(UnusedSymbol::LoadItem(load_stmt, load_item));
+        }
+
+        // Only plain assignments to a single name can be removed, and only if evaluating the
+        // right-hand side can't have any side effects.
+        let name_ref = ast::NameRef::cast(parent)?;
+        let stmt = name_ref.syntax().parent().and_then(ast::AssignStmt::cast)?;
+        if stmt.lhs()?.syntax() != name_ref.syntax()
+            || !matches!(stmt.assign_op_info()?.1, ast::AssignOp::Normal)
+            || !is_side_effect_free(stmt.rhs()?.syntax())
+        {
+            return None;
+        }
+        Some(UnusedSymbol::Assignment(stmt))
+    }
+}
+
+/// Whether evaluating the given expression can neither have side effects nor fail. Besides
+/// calls, this rules out e.g. indexing, field accesses and operators, which may fail at runtime
+/// depending on their operands. Lambdas are fine, since their bodies aren't evaluated.
+fn is_side_effect_free(expr: &SyntaxNode) -> bool {
+    match expr.kind() {
+        LITERAL_EXPR | NAME_REF | LAMBDA_EXPR => true,
+        LIST_EXPR | TUPLE_EXPR | PAREN_EXPR | DICT_EXPR | DICT_ENTRY => {
+            expr.children().all(|child| is_side_effect_free(&child))
+        }
+        _ => false,
+    }
+}
+
+/// Offers to delete an unused load item or a side-effect free assignment to an unused variable.
+fn remove_unused_symbol(
+    db: &Database,
+    file: File,
+    diagnostic: &Diagnostic,
+    actions: &mut Vec<CodeAction>,
+) -> Option<()> {
+    let root = parse(db, file).syntax(db);
+    let symbol = UnusedSymbol::from_diagnostic(&root, diagnostic)?;
+    let name = diagnostic.message.strip_suffix(" is not accessed")?;
+    let title = match &symbol {
+        UnusedSymbol::LoadItem(..) => format!("Remove unused load of {}", name),
+        UnusedSymbol::Assignment(_) => format!("Remove unused variable {}", name),
+    };
+    let edits = unused_symbol_removal_edits(file.contents(db), &[symbol]);
+    if edits.is_empty() {
+        return None;
+    }
+
+    actions.push(CodeAction {
+        title,
+        kind: CodeActionKind::QuickFix,
+        diagnostic: Some(diagnostic.clone()),
+        change: SourceChange {
+            file_edits: [(file.id(db), edits)].into_iter().collect(),
+        },
+        is_preferred: true,
+    });
+    Some(())
+}
+
+/// Offers to delete every removable unused symbol in the file at once.
+fn remove_all_unused_symbols(
+    db: &Database,
+    file: File,
+    diagnostics: &[Diagnostic],
+    actions: &mut Vec<CodeAction>,
+) {
+    let root = parse(db, file).syntax(db);
+    let symbols = diagnostics
+        .iter()
+        .filter_map(|diagnostic| UnusedSymbol::from_diagnostic(&root, diagnostic))
+        .collect::<Vec<_>>();
+
+    // This is only useful if there is more than one symbol to remove.
+    if symbols.len() < 2 {
+        return;
+    }
+
+    actions.push(CodeAction {
+        title: "Remove all unused loads and variables".to_string(),
+        kind: CodeActionKind::SourceFixAll,
+        diagnostic: None,
+        change: SourceChange {
+            file_edits: [(
+                file.id(db),
+                unused_symbol_removal_edits(file.contents(db), &symbols),
+            )]
+            .into_iter()
+            .collect(),
+        },
+        is_preferred: false,
+    });
+}
+
+/// Computes the edits needed to delete the given symbols. Load statements whose items are all
+/// being deleted are removed entirely, and suites that would otherwise become empty are left
+/// with a `pass` statement.
+fn unused_symbol_removal_edits(text: &str, symbols: &[UnusedSymbol]) -> Vec<TextEdit> {
+    let mut edits = Vec::new();
+    let mut removed_stmts: Vec<SyntaxNode> = Vec::new();
+    let mut load_stmts: Vec<(&ast::LoadStmt, Vec<&ast::LoadItem>)> = Vec::new();
+
+    for symbol in symbols {
+        match symbol {
+            UnusedSymbol::LoadItem(load_stmt, load_item) => {
+                match load_stmts
+                    .iter_mut()
+                    .find(|(other, _)| other.syntax() == load_stmt.syntax())
+                {
+                    Some((_, load_items)) => load_items.push(load_item),
+                    None => load_stmts.push((load_stmt, vec![load_item])),
+                }
+            }
+            UnusedSymbol::Assignment(stmt) => removed_stmts.push(stmt.syntax().clone()),
+        }
+    }
+
+    for (load_stmt, load_items) in load_stmts {
+        if load_stmt.items().count() == load_items.len() {
+            removed_stmts.push(load_stmt.syntax().clone());
+            continue;
+        }
+
+        // Delete each item along with the comma separating it from the preceding item or module.
+        for load_item in load_items {
+            if let Some(prev) = load_item.syntax().prev_sibling() {
+                edits.push(TextEdit {
+                    range: TextRange::new(
+                        prev.text_range().end(),
+                        load_item.syntax().text_range().end(),
+                    ),
+                    new_text: String::new(),
+                });
+            }
+        }
+    }
+
+    let mut replaced_suites: Vec<SyntaxNode> = Vec::new();
+    for stmt in removed_stmts.iter() {
+        let Some(range) = statement_removal_range(text, stmt.text_range()) else {
+            continue;
+        };
+
+        // Replace the first statement of a suite with `pass` if all of its statements are being
+        // removed, since suites can't be empty.
+        if let Some(suite) = stmt.parent().and_then(ast::Suite::cast) {
+            if !replaced_suites.contains(suite.syntax())
+                && suite
+                    .statements()
+                    .all(|other| removed_stmts.contains(other.syntax()))
+            {
+                replaced_suites.push(suite.syntax().clone());
+                edits.push(TextEdit {
+                    range: stmt.text_range(),
+                    new_text: "pass".to_string(),
+                });
+                continue;
+            }
+        }
+
+        edits.push(TextEdit {
+            range,
+            new_text: String::new(),
+        });
+    }
+
+    edits.sort_by_key(|edit| edit.range.start());
+    edits
+}
+
+/// Returns the range spanning the lines occupied by the given statement, including the trailing
+/// newline and any trailing comment. Statements that share their lines with other code aren't
+/// removed, since doing so could leave behind stray semicolons.
+fn statement_removal_range(text: &str, range: TextRange) -> Option<TextRange> {
+    let start = usize::from(range.start());
+    let end = usize::from(range.end());
+    let line_start = text[..start].rfind('\n').map_or(0, |pos| pos + 1);
+    let line_end = text[end..]
+        .find('\n')
+        .map_or(text.len(), |pos| end + pos + 1);
+    let prefix = &text[line_start..start];
+    let suffix = text[end..line_end].trim();
+    if !prefix.trim().is_empty() || !(suffix.is_empty() || suffix.starts_with('#')) {
+        return None;
+    }
+    Some(TextRange::new(
+        range.start() - TextSize::of(prefix),
+        TextSize::of(&text[..line_end]),
+    ))
+}
+
+/// Finds the `.bzl` files across the workspace that export the given name, along with the labels
+/// used to load them. Workspace files are listed before those from external repositories.
+fn load_candidates(
+    db: &Database,
+    file: File,
+    name: &Name,
+    symbol_index: &SymbolIndex,
+) -> Vec<(String, FileId)> {
+    let file_id = file.id(db);
+    let mut candidates = symbol_index
+        .search_exports(name.as_str())
+        .into_iter()
+        .filter(|symbol| symbol.name == name.as_str() && symbol.file_id != file_id)
+        .filter_map(|symbol| {
+            let label = db.loader.label_for_file(symbol.file_id, file_id)?;
+            Some((label, symbol.file_id))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|(label1, _), (label2, _)| {
+        (label1.starts_with('@'), label1).cmp(&(label2.starts_with('@'), label2))
+    });
+    candidates.dedup();
+    candidates
+}
+
+/// Computes the edit that loads `name` from the given file, extending an existing `load`
+/// statement for the same file if there is one.
+pub(crate) fn load_edit(
+    db: &Database,
+    sema: &Semantics,
+    file: File,
+    tree: &ast::Module,
+    label: &str,
+    candidate: FileId,
+    name: &str,
+) -> Option<TextEdit> {
+    let existing_load_stmt = tree
+        .statements()
+        .filter_map(|stmt| match stmt {
+            ast::Statement::Load(load_stmt) => Some(load_stmt),
+            _ => None,
+        })
+        .find(|load_stmt| {
+            sema.resolve_load_stmt(file, load_stmt)
+                .map(|loaded_file| loaded_file.id(db))
+                == Some(candidate)
+        });
+
+    Some(match existing_load_stmt {
+        Some(load_stmt) => {
+            let end = match load_stmt.items().last() {
+                Some(item) => item.syntax().text_range().end(),
+                None => load_stmt.module()?.syntax().text_range().end(),
+            };
+            TextEdit {
+                range: TextRange::empty(end),
+                new_text: format!(", \"{}\"", name),
+            }
+        }
+        None => new_load_stmt_edit(tree, label, name),
+    })
+}
+
+/// Inserts a new `load` statement after the existing ones, or at the top of the file after its
+/// docstring if there are none.
+fn new_load_stmt_edit(tree: &ast::Module, label: &str, name: &str) -> TextEdit {
+    let load_stmt = format!("load(\"{}\", \"{}\")", label, name);
+    let last_load_stmt = tree
+        .statements()
+        .filter_map(|stmt| match stmt {
+            ast::Statement::Load(load_stmt) => Some(load_stmt),
+            _ => None,
+        })
+        .last();
+    if let Some(last_load_stmt) = last_load_stmt {
+        return TextEdit {
+            range: TextRange::empty(last_load_stmt.syntax().text_range().end()),
+            new_text: format!("\n{}", load_stmt),
+        };
+    }
+
+    let mut statements = tree.statements().peekable();
+    if let Some(ast::Statement::Expr(ast::Expression::Literal(_))) = statements.peek() {
+        statements.next();
+    }
+    let (offset, new_text) = match statements.next() {
+        Some(stmt) => (
+            stmt.syntax().text_range().start(),
+            format!("{}\n\n", load_stmt),
+        ),
+        None => (tree.syntax().text_range().end(), format!("{}\n", load_stmt)),
+    };
+    TextEdit {
+        range: TextRange::empty(offset),
+        new_text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_bazel::APIContext;
+    use starpls_common::Db;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
+    use crate::Analysis;
+    use crate::FileSymbols;
+    use crate::InferenceOptions;
+    use crate::SymbolIndex;
+
+    fn check_code_actions(build_file: &str, expect: Expect) {
+        check_code_actions_with_unopened_defs(build_file, false, expect)
+    }
+
+    fn check_code_actions_with_unopened_defs(
+        build_file: &str,
+        is_defs_file_unopened: bool,
+        expect: Expect,
+    ) {
+        let foo_contents = r#"
+def foo():
+    pass
+
+def bar():
+    pass
+"#;
+        let defs_contents = r#"
+def foo():
+    pass
+"#;
+        let (mut analysis, loader) = Analysis::new_for_test();
+        analysis.set_inference_options(InferenceOptions {
+            report_unused_loads: true,
+            ..Default::default()
+        });
+        let mut fixture = Fixture::new(&mut analysis.db);
+        let foo_file_id = fixture.add_file(&mut analysis.db, "//:foo.bzl", foo_contents);
+        let defs_file_id = fixture.add_file(&mut analysis.db, "//lib:defs.bzl", defs_contents);
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            build_file,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        let mut symbol_index = SymbolIndex::default();
+        symbol_index.insert(
+            foo_file_id,
+            FileSymbols::new(foo_file_id, foo_contents, APIContext::Bzl, "//:foo.bzl"),
+        );
+        symbol_index.insert(
+            defs_file_id,
+            FileSymbols::new(
+                defs_file_id,
+                defs_contents,
+                APIContext::Bzl,
+                "//lib:defs.bzl",
+            ),
+        );
+
+        // Simulate a file that was only indexed while walking the workspace, and was never
+        // opened or loaded.
+        if is_defs_file_unopened {
+            analysis.db.files.remove(&defs_file_id);
+        }
+
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        let actions = analysis
+            .snapshot()
+            .code_actions(
+                file_id,
+                starpls_syntax::TextRange::empty(pos),
+                &symbol_index,
+            )
+            .unwrap()
+            .unwrap();
+        let contents = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+
+        let mut actual = String::new();
+        for action in actions {
+            let mut contents = contents.clone();
+            let mut edits = action.change.file_edits[&file_id].clone();
+            edits.sort_by_key(|edit| edit.range.start());
+            for edit in edits.into_iter().rev() {
+                contents.replace_range(std::ops::Range::<usize>::from(edit.range), &edit.new_text);
+            }
+            actual.push_str(&format!("{}:\n{}\n\n", action.title, contents.trim()));
+        }
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn test_add_missing_load() {
+        check_code_actions(
+            r#"
+b$0ar()
+"#,
+            expect![[r#"
+                Load "bar" from "//:foo.bzl":
+                load("//:foo.bzl", "bar")
+
+                bar()
+
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_add_missing_load_multiple_candidates() {
+        check_code_actions(
+            r#"
+load("//:foo.bzl", "bar")
+
+bar()
+f$0oo()
+"#,
+            expect![[r#"
+                Load "foo" from "//:foo.bzl":
+                load("//:foo.bzl", "bar", "foo")
+
+                bar()
+                foo()
+
+                Load "foo" from "//lib:defs.bzl":
+                load("//:foo.bzl", "bar")
+                load("//lib:defs.bzl", "foo")
+
+                bar()
+                foo()
+
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_add_missing_load_from_unopened_file() {
+        check_code_actions_with_unopened_defs(
+            r#"
+load("//:foo.bzl", "bar")
+
+bar()
+f$0oo()
+"#,
+            true,
+            expect![[r#"
+                Load "foo" from "//:foo.bzl":
+                load("//:foo.bzl", "bar", "foo")
+
+                bar()
+                foo()
+
+                Load "foo" from "//lib:defs.bzl":
+                load("//:foo.bzl", "bar")
+                load("//lib:defs.bzl", "foo")
+
+                bar()
+                foo()
+
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_remove_unused_load_item() {
+        check_code_actions(
+            r#"
+load("//:foo.bzl", "foo", b$0 = "bar")
+
+foo()
+"#,
+            expect![[r#"
+                Remove unused load of "b":
+                load("//:foo.bzl", "foo")
+
+                foo()
+
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_remove_unused_assignment_and_empty_load() {
+        check_code_actions(
+            r#"
+load("//:foo.bzl", "bar")
+load("//lib:defs.bzl", "foo")
+
+_$0x = [1, 2]
+_y = foo()
+_z = {"a": 1}["b"]
+"#,
+            expect![[r#"
+                Remove unused variable "_x":
+                load("//:foo.bzl", "bar")
+                load("//lib:defs.bzl", "foo")
+
+                _y = foo()
+                _z = {"a": 1}["b"]
+
+                Remove all unused loads and variables:
+                load("//lib:defs.bzl", "foo")
+
+                _y = foo()
+                _z = {"a": 1}["b"]
+
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use rustc_hash::FxHashSet;
+use starpls_bazel::APIContext;
+use starpls_common::parse;
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_common::FileId;
+use starpls_hir::Name;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::TextRange;
+
+use crate::document_symbols::target_name;
+use crate::Database;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodeLensKind {
+    /// A target declared in a BUILD file. `references` is the number of labels within the same
+    /// BUILD file that refer to the target.
+    Target {
+        rule: String,
+        name: String,
+        references: usize,
+    },
+
+    /// A public function declared in a `.bzl` file. `loaders` is the number of known files that
+    /// load the function.
+    ExportedFunction { name: String, loaders: usize },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeLens {
+    pub range: TextRange,
+    pub kind: CodeLensKind,
+}
+
+pub(crate) fn code_lenses(db: &Database, file_id: FileId) -> Option<Vec<CodeLens>> {
+    let file = db.get_file(file_id)?;
+    match file.api_context(db)? {
+        APIContext::Build => Some(target_lenses(db, file)),
+        APIContext::Bzl => Some(exported_function_lenses(db, file)),
+        _ => None,
+    }
+}
+
+fn target_lenses(db: &Database, file: File) -> Vec<CodeLens> {
+    let root = parse(db, file).syntax(db);
+    let labels = root
+        .descendants()
+        .filter_map(ast::LiteralExpr::cast)
+        .filter_map(|lit| match lit.kind() {
+            ast::LiteralKind::String(s) => s.value(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let package = db.resolve_build_file(file.id(db));
+
+    root.children()
+        .filter_map(ast::CallExpr::cast)
+        .filter_map(|call_expr| {
+            let name = target_name(&call_expr)?;
+            let rule = call_expr.callee()?.syntax().text().to_string();
+            let references = labels
+                .iter()
+                .filter(|label| refers_to_target(label, package.as_deref(), &name))
+                .count();
+            Some(CodeLens {
+                range: call_expr.syntax().text_range(),
+                kind: CodeLensKind::Target {
+                    rule,
+                    name: name.to_string(),
+                    references,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Whether `label` refers to the target `name` declared in the BUILD file of `package`.
+fn refers_to_target(label: &str, package: Option<&str>, name: &str) -> bool {
+    if label.strip_prefix(':') == Some(name) {
+        return true;
+    }
+    let Some(package) = package else {
+        return false;
+    };
+    let Some(label) = label.strip_prefix("//") else {
+        return false;
+    };
+    match label.split_once(':') {
+        Some((label_package, target)) => label_package == package && target == name,
+        // `//foo/bar` is shorthand for `//foo/bar:bar`.
+        None => label == package && package.rsplit('/').next() == Some(name),
+    }
+}
+
+fn exported_function_lenses(db: &Database, file: File) -> Vec<CodeLens> {
+    parse(db, file)
+        .tree(db)
+        .statements()
+        .filter_map(|stmt| match stmt {
+            ast::Statement::Def(def_stmt) => Some(def_stmt),
+            _ => None,
+        })
+        .filter_map(|def_stmt| {
+            let name = def_stmt.name()?;
+            let name = Name::from_ast_name(name);
+            if name.as_str().starts_with('_') {
+                return None;
+            }
+            let loaders = db
+                .load_graph
+                .loaders(db, file, &name)
+                .into_iter()
+                .map(|(loader, _)| loader)
+                .collect::<FxHashSet<_>>()
+                .len();
+            Some(CodeLens {
+                range: def_stmt.syntax().text_range(),
+                kind: CodeLensKind::ExportedFunction {
+                    name: name.as_str().to_string(),
+                    loaders,
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_bazel::APIContext;
+    use starpls_common::Dialect;
+    use starpls_common::FileId;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
+    use crate::Analysis;
+
+    fn check_code_lenses(analysis: &Analysis, file_id: FileId, expect: Expect) {
+        let actual = analysis
+            .snapshot()
+            .code_lenses(file_id)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|lens| format!("{:?}\n", lens.kind))
+            .collect::<String>();
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn test_code_lenses() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        let defs_file_id = fixture.add_file(
+            &mut analysis.db,
+            "//:defs.bzl",
+            r#"
+def my_macro(name):
+    pass
+
+def _helper():
+    pass
+"#,
+        );
+        let build_file_id = fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:defs.bzl", "my_macro")
+
+my_macro(name = "foo")
+
+cc_library(
+    name = "bar",
+    deps = [":foo"],
+)
+
+cc_binary(
+    name = "main",
+    deps = [":foo", ":bar"],
+)
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        check_code_lenses(
+            &analysis,
+            build_file_id,
+            expect![[r#"
+                Target { rule: "my_macro", name: "foo", references: 2 }
+                Target { rule: "cc_library", name: "bar", references: 1 }
+                Target { rule: "cc_binary", name: "main", references: 0 }
+            "#]],
+        );
+        check_code_lenses(
+            &analysis,
+            defs_file_id,
+            expect![[r#"
+                ExportedFunction { name: "my_macro", loaders: 1 }
+            "#]],
+        );
+    }
+}
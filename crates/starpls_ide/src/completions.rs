@@ -3,9 +3,11 @@
 use std::collections::HashSet;
 
 use rustc_hash::FxHashMap;
+use starpls_common::Dialect;
 use starpls_common::FileId;
 use starpls_common::LoadItemCandidateKind;
 use starpls_hir::Db;
+use starpls_hir::DisplayWithDb;
 use starpls_hir::Name;
 use starpls_hir::Param;
 use starpls_hir::ScopeDef;
@@ -20,7 +22,12 @@ use starpls_syntax::SyntaxNode;
 use starpls_syntax::TextRange;
 use starpls_syntax::TextSize;
 
+use crate::code_actions::load_edit;
+use crate::util::unindent_doc;
+use crate::Database;
 use crate::FilePosition;
+use crate::SymbolIndex;
+use crate::SymbolKind;
 
 const COMPLETION_MARKER: &str = "__STARPLS_COMPLETION_MARKER";
 
@@ -34,6 +41,9 @@ pub struct CompletionItem {
     pub kind: CompletionItemKind,
     pub mode: Option<CompletionMode>,
     pub filter_text: Option<String>,
+    /// The `.bzl` file to load the symbol from, if it isn't in scope yet. The edit that loads
+    /// it is computed by [`resolve_auto_import`].
+    pub auto_import: Option<FileId>,
     relevance: CompletionRelevance,
 }
 
@@ -43,6 +53,21 @@ impl CompletionItem {
     }
 }
 
+/// The edit that loads a symbol that isn't in scope yet from the `.bzl` file labeled `module`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AutoImport {
+    pub module: String,
+    pub edit: TextEdit,
+}
+
+/// Details about a completion item that are only computed once the client resolves the item,
+/// since computing them for every item in a large completion list is expensive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompletionItemDetails {
+    pub detail: Option<String>,
+    pub documentation: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Edit {
     TextEdit(TextEdit),
@@ -66,6 +91,10 @@ pub struct InsertReplaceEdit {
 pub enum CompletionMode {
     InsertText(String),
     TextEdit(Edit),
+
+    /// Text in the LSP snippet syntax, i.e. containing tab stops like `$1`. Clients without
+    /// snippet support should fall back to inserting the label.
+    Snippet(String),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -87,6 +116,7 @@ enum CompletionRelevance {
     Parameter,
     VariableOrKeyword,
     Builtin,
+    AutoImport,
 }
 
 enum CompletionAnalysis {
@@ -169,30 +199,39 @@ pub(crate) fn completions(
                     mode: Some(CompletionMode::InsertText(format!("{} = ", name.as_str()))),
                     relevance: CompletionRelevance::Parameter,
                     filter_text: None,
+                    auto_import: None,
                 });
             }
 
             if !is_loop_variable {
                 add_globals(&mut items);
                 for (name, def) in names {
+                    let ty = def.ty(db);
                     items.push(CompletionItem {
                         label: name.to_string(),
                         kind: match &def {
                             ScopeDef::Callable(_) => CompletionItemKind::Function,
-                            def if def.ty(db).is_callable() => CompletionItemKind::Function,
+                            _ if ty.is_callable() => CompletionItemKind::Function,
                             // All the global values in the Bazel builtins are modules.
                             ScopeDef::Variable(it) if !it.is_user_defined() => {
                                 CompletionItemKind::Module
                             }
                             _ => CompletionItemKind::Variable,
                         },
-                        mode: None,
+                        // Only offer to insert the full call if the completion is for a new
+                        // statement, e.g. a target declaration in a BUILD file.
+                        mode: if is_lone_expr {
+                            target_call_snippet(db, &name, &ty).map(CompletionMode::Snippet)
+                        } else {
+                            None
+                        },
                         relevance: if def.is_user_defined() {
                             CompletionRelevance::VariableOrKeyword
                         } else {
                             CompletionRelevance::Builtin
                         },
                         filter_text: None,
+                        auto_import: None,
                     });
                 }
 
@@ -213,6 +252,7 @@ pub(crate) fn completions(
                     mode: None,
                     relevance: CompletionRelevance::VariableOrKeyword,
                     filter_text: None,
+                    auto_import: None,
                 })
             }
         }
@@ -224,6 +264,7 @@ pub(crate) fn completions(
                     mode: None,
                     relevance: CompletionRelevance::VariableOrKeyword,
                     filter_text: None,
+                    auto_import: None,
                 })
             }
         }
@@ -272,6 +313,7 @@ pub(crate) fn completions(
                     mode: Some(CompletionMode::TextEdit(edit)),
                     relevance: CompletionRelevance::VariableOrKeyword,
                     filter_text,
+                    auto_import: None,
                 });
             }
         }
@@ -299,6 +341,7 @@ pub(crate) fn completions(
                     mode: None,
                     relevance: CompletionRelevance::VariableOrKeyword,
                     filter_text: None,
+                    auto_import: None,
                 });
             }
         }
@@ -314,6 +357,7 @@ pub(crate) fn completions(
                     mode: None,
                     relevance: CompletionRelevance::VariableOrKeyword,
                     filter_text: None,
+                    auto_import: None,
                 });
             }
         }
@@ -349,6 +393,7 @@ pub(crate) fn completions(
                         mode: None,
                         relevance: CompletionRelevance::VariableOrKeyword,
                         filter_text: None,
+                        auto_import: None,
                     });
                 } else if let Some(index) = remaining.find(['/', ':']) {
                     let package = &remaining[..index];
@@ -360,6 +405,7 @@ pub(crate) fn completions(
                             mode: None,
                             relevance: CompletionRelevance::VariableOrKeyword,
                             filter_text: None,
+                            auto_import: None,
                         });
                     }
                 }
@@ -371,6 +417,130 @@ pub(crate) fn completions(
     Some(items)
 }
 
+/// Computes the details of the completion item with the given label, as returned by
+/// [`completions`] for the same position.
+pub(crate) fn resolve_completion_item(
+    db: &dyn Db,
+    pos: FilePosition,
+    label: &str,
+) -> Option<CompletionItemDetails> {
+    let ctx = CompletionContext::new(db, pos, None)?;
+    let (ty, doc) = match ctx.analysis {
+        CompletionAnalysis::NameRef(NameRefContext { names, .. }) => {
+            let (_, def) = names.into_iter().find(|(name, _)| name.as_str() == label)?;
+            let ty = def.ty(db);
+            let doc = ty.doc(db);
+            (ty, doc)
+        }
+        CompletionAnalysis::Name(NameContext::Dot { receiver_ty }) => {
+            let (field, ty) = receiver_ty
+                .fields(db)
+                .into_iter()
+                .find(|(field, _)| field.name(db).as_str() == label)?;
+            let doc = field.doc(db);
+            (ty, Some(doc).filter(|doc| !doc.is_empty()))
+        }
+        CompletionAnalysis::String(StringContext::LoadItem { file_id, load_stmt }) => {
+            let sema = Semantics::new(db);
+            let file = db.get_file(file_id)?;
+            let loaded_file = sema.resolve_load_stmt(file, &load_stmt)?;
+            let (_, def) = sema
+                .scope_for_module(loaded_file)
+                .exports()
+                .find(|(name, _)| name.as_str() == label)?;
+            let ty = def.ty(db);
+            let doc = ty.doc(db);
+            (ty, doc)
+        }
+        _ => return None,
+    };
+
+    Some(CompletionItemDetails {
+        detail: Some(ty.display(db).to_string()),
+        documentation: doc
+            .filter(|doc| !doc.is_empty())
+            .map(|doc| unindent_doc(&doc)),
+    })
+}
+
+/// Offers the symbols exported by `.bzl` files across the workspace that aren't in scope yet.
+/// Only symbols matching the name being typed are offered, to keep the number of items
+/// manageable. The edits that load the symbols are only computed once an item is resolved, see
+/// [`resolve_auto_import`].
+pub(crate) fn auto_import_completions(
+    db: &Database,
+    pos: FilePosition,
+    symbol_index: &SymbolIndex,
+) -> Option<Vec<CompletionItem>> {
+    let file = db.get_file(pos.file_id)?;
+    if file.dialect(db) != Dialect::Bazel {
+        return None;
+    }
+
+    let names = match CompletionContext::new(db, pos, None)?.analysis {
+        CompletionAnalysis::NameRef(NameRefContext {
+            names,
+            is_loop_variable: false,
+            ..
+        }) => names,
+        _ => return None,
+    };
+
+    let sema = Semantics::new(db);
+    let tree = sema.parse(file).tree(db);
+    let token = tree.syntax().token_at_offset(pos.pos).left_biased()?;
+    if token.kind() != IDENT {
+        return None;
+    }
+    let prefix = &token.text()[..usize::from(pos.pos - token.text_range().start())];
+    if prefix.is_empty() {
+        return None;
+    }
+
+    let names = names.keys().map(Name::as_str).collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for symbol in symbol_index.search_exports(prefix) {
+        if symbol.file_id == pos.file_id
+            || names.contains(symbol.name.as_str())
+            || !seen.insert((symbol.file_id, symbol.name.clone()))
+        {
+            continue;
+        }
+
+        items.push(CompletionItem {
+            label: symbol.name,
+            kind: match symbol.kind {
+                SymbolKind::Function | SymbolKind::Class => CompletionItemKind::Function,
+                SymbolKind::Struct => CompletionItemKind::Class,
+                _ => CompletionItemKind::Variable,
+            },
+            mode: None,
+            filter_text: None,
+            auto_import: Some(symbol.file_id),
+            relevance: CompletionRelevance::AutoImport,
+        });
+    }
+
+    Some(items)
+}
+
+/// Computes the edit that loads the symbol with the given name from `file_id`, for a completion
+/// item returned by [`auto_import_completions`].
+pub(crate) fn resolve_auto_import(
+    db: &Database,
+    pos: FilePosition,
+    file_id: FileId,
+    name: &str,
+) -> Option<AutoImport> {
+    let file = db.get_file(pos.file_id)?;
+    let sema = Semantics::new(db);
+    let tree = sema.parse(file).tree(db);
+    let module = db.loader.label_for_file(file_id, pos.file_id)?;
+    let edit = load_edit(db, &sema, file, &tree, &module, file_id, name)?;
+    Some(AutoImport { module, edit })
+}
+
 pub(crate) fn add_globals(items: &mut Vec<CompletionItem>) {
     let add_global = &mut |global: &'static str| {
         items.push(CompletionItem {
@@ -379,6 +549,7 @@ pub(crate) fn add_globals(items: &mut Vec<CompletionItem>) {
             mode: None,
             relevance: CompletionRelevance::VariableOrKeyword,
             filter_text: None,
+            auto_import: None,
         })
     };
     add_global("True");
@@ -386,6 +557,48 @@ pub(crate) fn add_globals(items: &mut Vec<CompletionItem>) {
     add_global("None");
 }
 
+/// Builds a snippet for calling a rule or macro, with a tab stop for the `name` argument and for
+/// each mandatory attribute, e.g. `cc_library(name = "$1", srcs = [$2])$0`.
+fn target_call_snippet(db: &dyn Db, name: &Name, ty: &Type) -> Option<String> {
+    let params = ty.params(db);
+    let has_name_param = params
+        .iter()
+        .any(|(param, _)| param.name(db).as_ref().map(Name::as_str) == Some("name"));
+    if !ty.is_rule() && !ty.is_macro() && !has_name_param {
+        return None;
+    }
+
+    // Symbolic macros don't declare the `name` attribute, but still require it.
+    let mut args = Vec::new();
+    if !has_name_param {
+        args.push("name = \"$1\"".to_string());
+    }
+    for (param, param_ty) in params
+        .iter()
+        .filter(|(param, _)| !param.is_args_list(db) && !param.is_kwargs_dict(db))
+    {
+        let param_name = match param.name(db) {
+            Some(param_name) if param_name.as_str() == "name" || param.is_mandatory(db) => {
+                param_name
+            }
+            _ => continue,
+        };
+        let tab_stop = args.len() + 1;
+        let placeholder = if param_ty.is_string() {
+            format!("\"${}\"", tab_stop)
+        } else if param_ty.is_list() {
+            format!("[${}]", tab_stop)
+        } else if param_ty.is_dict() {
+            format!("{{${}}}", tab_stop)
+        } else {
+            format!("${}", tab_stop)
+        };
+        args.push(format!("{} = {}", param_name.as_str(), placeholder));
+    }
+
+    Some(format!("{}({})$0", name.as_str(), args.join(", ")))
+}
+
 fn add_keywords(items: &mut Vec<CompletionItem>, is_in_def: bool, is_in_for: bool) {
     let add_keyword = &mut |keyword: &'static str| {
         items.push(CompletionItem {
@@ -394,6 +607,7 @@ fn add_keywords(items: &mut Vec<CompletionItem>, is_in_def: bool, is_in_for: boo
             mode: None,
             relevance: CompletionRelevance::VariableOrKeyword,
             filter_text: None,
+            auto_import: None,
         })
     };
     add_keyword("def");
@@ -582,12 +796,18 @@ mod tests {
 
     use expect_test::expect;
     use expect_test::Expect;
+    use starpls_bazel::APIContext;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
     use starpls_hir::Db;
+    use starpls_hir::Fixture;
 
     use crate::completions::CompletionRelevance;
     use crate::Analysis;
     use crate::CompletionItemKind;
     use crate::FilePosition;
+    use crate::FileSymbols;
+    use crate::SymbolIndex;
 
     fn check_completions(fixture: &str, expect: Expect) {
         check_completions_with_options(fixture, false, expect);
@@ -645,6 +865,84 @@ mod tests {
         expect.assert_eq(&expected);
     }
 
+    fn check_auto_import_completions(build_file: &str, expect: Expect) {
+        let defs_contents = r#"
+def my_macro(name):
+    pass
+
+def _my_private_macro(name):
+    pass
+
+MY_VALUE = 1
+"#;
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        let defs_file_id = fixture.add_file(&mut analysis.db, "//:defs.bzl", defs_contents);
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            build_file,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        let mut symbol_index = SymbolIndex::default();
+        symbol_index.insert(
+            defs_file_id,
+            FileSymbols::new(defs_file_id, defs_contents, APIContext::Bzl, "//:defs.bzl"),
+        );
+
+        let pos = fixture
+            .cursor_pos
+            .map(|(file_id, pos)| FilePosition { file_id, pos })
+            .unwrap();
+        let snapshot = analysis.snapshot();
+        let mut completions = snapshot
+            .auto_import_completions(pos, &symbol_index)
+            .unwrap()
+            .unwrap_or_default();
+        completions.sort_by(|item1, item2| item1.label.cmp(&item2.label));
+
+        let actual = completions
+            .into_iter()
+            .fold(String::new(), |mut acc, item| {
+                let auto_import = snapshot
+                    .resolve_auto_import(pos, item.auto_import.unwrap(), &item.label)
+                    .unwrap()
+                    .unwrap();
+                writeln!(
+                    acc,
+                    "{} {} {:?} {:?}",
+                    item.label,
+                    auto_import.module,
+                    auto_import.edit.range,
+                    auto_import.edit.new_text
+                )
+                .unwrap();
+                acc
+            });
+        expect.assert_eq(&actual);
+    }
+
+    fn check_resolve_completion_item(fixture: &str, label: &str, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let details = analysis
+            .snapshot()
+            .resolve_completion_item(
+                fixture
+                    .cursor_pos
+                    .map(|(file_id, pos)| FilePosition { file_id, pos })
+                    .unwrap(),
+                label,
+            )
+            .unwrap();
+        expect.assert_eq(&format!("{:?}", details));
+    }
+
     #[test]
     fn test_empty() {
         check_completions_with_options(
@@ -653,51 +951,51 @@ $0
 "#,
             true,
             expect![[r#"
-                CompletionItem { label: "False", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "None", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "True", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "abs", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "all", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "any", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "attr", kind: Module, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "bool", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "bytes", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "def", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "dict", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "dir", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "enumerate", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "fail", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "float", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "for", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "getattr", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "hasattr", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "hash", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "if", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "int", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "lambda", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "len", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "licenses", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "list", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "load", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "max", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "min", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "module_extension", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "package", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "pass", kind: Keyword, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "print", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "provider", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "range", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "repository_rule", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "repr", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "reversed", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "rule", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "sorted", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "str", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "struct", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "tag_class", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "tuple", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "type", kind: Function, mode: None, filter_text: None, relevance: Builtin }
-                CompletionItem { label: "zip", kind: Function, mode: None, filter_text: None, relevance: Builtin }
+                CompletionItem { label: "False", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "None", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "True", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "abs", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "all", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "any", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "attr", kind: Module, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "bool", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "bytes", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "def", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "dict", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "dir", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "enumerate", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "fail", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "float", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "for", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "getattr", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "hasattr", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "hash", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "if", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "int", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "lambda", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "len", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "licenses", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "list", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "load", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "max", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "min", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "module_extension", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "package", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "pass", kind: Keyword, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "print", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "provider", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "range", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "repository_rule", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "repr", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "reversed", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "rule", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "sorted", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "str", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "struct", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "tag_class", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "tuple", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "type", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
+                CompletionItem { label: "zip", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: Builtin }
             "#]],
         );
     }
@@ -712,10 +1010,10 @@ def foo(x, y):
     x + $0
 "#,
             expect![[r#"
-                CompletionItem { label: "abc", kind: Variable, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "foo", kind: Function, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "x", kind: Variable, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "y", kind: Variable, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "abc", kind: Variable, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "foo", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "x", kind: Variable, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "y", kind: Variable, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -732,9 +1030,9 @@ foo(
 )
 "#,
             expect![[r#"
-                CompletionItem { label: "foo", kind: Function, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "x=", kind: Variable, mode: Some(InsertText("x = ")), filter_text: None, relevance: Parameter }
-                CompletionItem { label: "y=", kind: Variable, mode: Some(InsertText("y = ")), filter_text: None, relevance: Parameter }
+                CompletionItem { label: "foo", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "x=", kind: Variable, mode: Some(InsertText("x = ")), filter_text: None, auto_import: None, relevance: Parameter }
+                CompletionItem { label: "y=", kind: Variable, mode: Some(InsertText("y = ")), filter_text: None, auto_import: None, relevance: Parameter }
             "#]],
         );
     }
@@ -748,8 +1046,8 @@ y = 2
 $0
 "#,
             expect![[r#"
-                CompletionItem { label: "x", kind: Variable, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "y", kind: Variable, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "x", kind: Variable, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "y", kind: Variable, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -762,8 +1060,8 @@ foo = struct(x = 1, y = 2)
 foo.$0
 "#,
             expect![[r#"
-                CompletionItem { label: "x", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "y", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "x", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "y", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -776,8 +1074,8 @@ d = {"a": 1, "b": 2}
 d["$0"]
 "#,
             expect![[r#"
-                CompletionItem { label: "a", kind: Constant, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "b", kind: Constant, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "a", kind: Constant, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "b", kind: Constant, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -789,8 +1087,8 @@ d["$0"]
 label = "//$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "bar", kind: Folder, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "foo", kind: Folder, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "bar", kind: Folder, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "foo", kind: Folder, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -802,8 +1100,8 @@ label = "//$0"
 label = "//fo$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "bar", kind: Folder, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "foo", kind: Folder, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "bar", kind: Folder, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "foo", kind: Folder, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -815,8 +1113,8 @@ label = "//fo$0"
 label = "//:$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "bar", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "foo", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "bar", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "foo", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -828,8 +1126,8 @@ label = "//:$0"
 label = "//:f$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "bar", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "foo", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "bar", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "foo", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -841,7 +1139,7 @@ label = "//:f$0"
 label = "//foo:$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "foo", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "foo", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -853,7 +1151,7 @@ label = "//foo:$0"
 label = "//foo/$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "bar", kind: Folder, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "bar", kind: Folder, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
@@ -865,9 +1163,102 @@ label = "//foo/$0"
 label = "//foo/bar:b$0"
 "#,
             expect![[r#"
-                CompletionItem { label: "bar", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
-                CompletionItem { label: "baz", kind: Field, mode: None, filter_text: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "bar", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "baz", kind: Field, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
             "#]],
         );
     }
+
+    #[test]
+    fn test_rule_call_snippet() {
+        check_completions(
+            r#"
+def _impl(ctx):
+    pass
+
+my_rule = rule(
+    implementation = _impl,
+    attrs = {
+        "srcs": attr.label_list(mandatory = True),
+        "out": attr.string(),
+    },
+)
+
+my_$0
+"#,
+            expect![[r#"
+                CompletionItem { label: "_impl", kind: Function, mode: None, filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+                CompletionItem { label: "my_rule", kind: Function, mode: Some(Snippet("my_rule(name = \"$1\", srcs = [$2])$0")), filter_text: None, auto_import: None, relevance: VariableOrKeyword }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_auto_import_new_load() {
+        check_auto_import_completions(
+            r#"
+my_$0
+"#,
+            expect![[r#"
+                MY_VALUE //:defs.bzl 1..1 "load(\"//:defs.bzl\", \"MY_VALUE\")\n\n"
+                my_macro //:defs.bzl 1..1 "load(\"//:defs.bzl\", \"my_macro\")\n\n"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_auto_import_extend_load() {
+        check_auto_import_completions(
+            r#"
+load("//:defs.bzl", "my_macro")
+
+MY$0
+"#,
+            expect![[r#"
+                MY_VALUE //:defs.bzl 31..31 ", \"MY_VALUE\""
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_resolve_function() {
+        check_resolve_completion_item(
+            r#"
+def foo(x, y):
+    """Doc string"""
+    pass
+
+f$0
+"#,
+            "foo",
+            expect![[
+                r#"Some(CompletionItemDetails { detail: Some("def foo(x, y) -> Unknown"), documentation: Some("Doc string  ") })"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn test_resolve_field() {
+        check_resolve_completion_item(
+            r#"
+foo = struct(bar = 123)
+foo.b$0
+"#,
+            "bar",
+            expect![[
+                r#"Some(CompletionItemDetails { detail: Some("Literal[123]"), documentation: None })"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn test_resolve_unknown_label() {
+        check_resolve_completion_item(
+            r#"
+f$0
+"#,
+            "foo",
+            expect![[r#"None"#]],
+        );
+    }
 }
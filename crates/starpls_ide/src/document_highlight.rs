@@ -0,0 +1,295 @@
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_hir::Name;
+use starpls_hir::ScopeDef;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::match_ast;
+use starpls_syntax::SyntaxKind::*;
+use starpls_syntax::SyntaxNode;
+use starpls_syntax::TextRange;
+use starpls_syntax::T;
+
+use crate::find_references::FindReferencesHandler;
+use crate::util::pick_best_token;
+use crate::util::string_contents_range;
+use crate::Database;
+use crate::FilePosition;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentHighlightKind {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentHighlight {
+    pub range: TextRange,
+    pub kind: DocumentHighlightKind,
+}
+
+pub(crate) fn document_highlight(
+    db: &Database,
+    FilePosition { file_id, pos }: FilePosition,
+) -> Option<Vec<DocumentHighlight>> {
+    let sema = Semantics::new(db);
+    let file = db.get_file(file_id)?;
+    let root = sema.parse(file).syntax(db);
+    let token = pick_best_token(root.token_at_offset(pos), |kind| match kind {
+        T![ident] => 2,
+        T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}'] => 0,
+        kind if kind.is_trivia_token() => 0,
+        _ => 1,
+    })?;
+    let node = token.parent()?;
+
+    let (name, defs) = if let Some(node) = ast::NameRef::cast(node.clone()) {
+        let name = Name::from_ast_name_ref(node.clone());
+        let scope = sema.scope_for_expr(file, &ast::Expression::cast(node.syntax().clone())?)?;
+        (name.clone(), scope.resolve_name(&name))
+    } else if let Some(node) = ast::Name::cast(node.clone()) {
+        let parent = node.syntax().parent()?;
+        let def = match_ast! {
+            match parent {
+                ast::DefStmt(stmt) => ScopeDef::Callable(sema.resolve_def_stmt(file, &stmt)?),
+                ast::Parameter(param) => ScopeDef::Parameter(sema.resolve_param(file, &param)?.0),
+                ast::LoadItem(item) => ScopeDef::LoadItem(sema.resolve_load_item(file, &item)?),
+                _ => return None,
+            }
+        };
+        (Name::from_ast_name(node), vec![def])
+    } else if let Some(node) = ast::LoadItem::cast(node) {
+        // Only the name of a direct load item is bound in the current file.
+        let ast::LoadItem::Direct(_) = node else {
+            return None;
+        };
+        let load_item = sema.resolve_load_item(file, &node)?;
+        (load_item.name(db), vec![ScopeDef::LoadItem(load_item)])
+    } else {
+        return None;
+    };
+
+    if defs.is_empty() {
+        return None;
+    }
+
+    let mut highlights = declaration_highlights(&sema, &root, file, &defs);
+    highlights.extend(
+        FindReferencesHandler::new(&sema, file, name, defs)
+            .handle()
+            .into_iter()
+            .filter_map(|location| {
+                // The covering element of a reference is its identifier token.
+                let node = root
+                    .covering_element(location.range)
+                    .into_token()?
+                    .parent()?;
+                Some(DocumentHighlight {
+                    range: location.range,
+                    kind: if is_write(&node) {
+                        DocumentHighlightKind::Write
+                    } else {
+                        DocumentHighlightKind::Read
+                    },
+                })
+            }),
+    );
+    highlights.sort_by_key(|highlight| highlight.range.start());
+    highlights.dedup_by_key(|highlight| highlight.range);
+    Some(highlights)
+}
+
+/// Finds the declarations of parameters and load items, which aren't covered by
+/// [`FindReferencesHandler`].
+fn declaration_highlights(
+    sema: &Semantics,
+    root: &SyntaxNode,
+    file: File,
+    defs: &[ScopeDef],
+) -> Vec<DocumentHighlight> {
+    let mut highlights = Vec::new();
+    for def in defs {
+        let range = match def {
+            ScopeDef::Parameter(param) => root
+                .descendants()
+                .filter_map(ast::Parameter::cast)
+                .find(|node| {
+                    sema.resolve_param(file, node)
+                        .map_or(false, |(other, _)| &other == param)
+                })
+                .and_then(|node| {
+                    let name = match node {
+                        ast::Parameter::Simple(param) => param.name(),
+                        ast::Parameter::ArgsList(param) => param.name(),
+                        ast::Parameter::KwargsDict(param) => param.name(),
+                    };
+                    name.map(|name| name.syntax().text_range())
+                }),
+            ScopeDef::LoadItem(load_item) => root
+                .descendants()
+                .filter_map(ast::LoadItem::cast)
+                .find(|node| sema.resolve_load_item(file, node).as_ref() == Some(load_item))
+                .and_then(|node| match node {
+                    ast::LoadItem::Direct(item) => string_contents_range(&item.name()?),
+                    ast::LoadItem::Aliased(item) => Some(item.alias()?.syntax().text_range()),
+                }),
+            _ => None,
+        };
+        if let Some(range) = range {
+            highlights.push(DocumentHighlight {
+                range,
+                kind: DocumentHighlightKind::Write,
+            });
+        }
+    }
+    highlights
+}
+
+/// Whether the given `Name` or `NameRef` node binds a value, i.e. whether it is a function name,
+/// or the target of an assignment or a loop.
+fn is_write(node: &SyntaxNode) -> bool {
+    if node.kind() == NAME {
+        return true;
+    }
+
+    // Walk up through any tuples, lists, or parentheses that the target is nested in.
+    let mut node = node.clone();
+    let parent = loop {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        match parent.kind() {
+            TUPLE_EXPR | LIST_EXPR | PAREN_EXPR => node = parent,
+            _ => break parent,
+        }
+    };
+
+    match_ast! {
+        match parent {
+            ast::AssignStmt(stmt) => stmt.lhs().map_or(false, |lhs| lhs.syntax() == &node),
+            ast::LoopVariables(_) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_common::Db;
+
+    use crate::Analysis;
+    use crate::DocumentHighlightKind;
+    use crate::FilePosition;
+
+    fn check_document_highlight(fixture: &str, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        let mut actual = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+        let highlights = analysis
+            .snapshot()
+            .document_highlight(FilePosition { file_id, pos })
+            .unwrap()
+            .unwrap();
+
+        // Mark each highlight inline, starting from the end of the file so that earlier offsets
+        // remain valid.
+        for highlight in highlights.into_iter().rev() {
+            let marker = match highlight.kind {
+                DocumentHighlightKind::Read => "R",
+                DocumentHighlightKind::Write => "W",
+            };
+            actual.insert_str(highlight.range.end().into(), ">");
+            actual.insert_str(highlight.range.start().into(), &format!("<{}:", marker));
+        }
+        expect.assert_eq(actual.trim());
+    }
+
+    #[test]
+    fn test_variable_reads_and_writes() {
+        check_document_highlight(
+            r#"
+def f():
+    x = 1
+    for x in [x]:
+        pass
+    (y, x) = (x, 2)
+    x += 1
+    print(x$0)
+"#,
+            expect![[r#"
+                def f():
+                    <W:x> = 1
+                    for <W:x> in [<R:x>]:
+                        pass
+                    (y, <W:x>) = (<R:x>, 2)
+                    <W:x> += 1
+                    print(<R:x>)"#]],
+        );
+    }
+
+    #[test]
+    fn test_function_and_parameter() {
+        check_document_highlight(
+            r#"
+def foo(a$0, b):
+    return a + b
+
+foo(1, 2)
+"#,
+            expect![[r#"
+                def foo(<W:a>, b):
+                    return <R:a> + b
+
+                foo(1, 2)"#]],
+        );
+        check_document_highlight(
+            r#"
+def f$0oo():
+    pass
+
+foo()
+"#,
+            expect![[r#"
+                def <W:foo>():
+                    pass
+
+                <R:foo>()"#]],
+        );
+    }
+
+    #[test]
+    fn test_load_items() {
+        check_document_highlight(
+            r#"
+load("//:foo.bzl", "foo", bar = "baz")
+
+foo()
+b$0ar()
+"#,
+            expect![[r#"
+                load("//:foo.bzl", "foo", <W:bar> = "baz")
+
+                foo()
+                <R:bar>()"#]],
+        );
+        check_document_highlight(
+            r#"
+load("//:foo.bzl", "f$0oo")
+
+foo()
+"#,
+            expect![[r#"
+                load("//:foo.bzl", "<W:foo>")
+
+                <R:foo>()"#]],
+        );
+    }
+}
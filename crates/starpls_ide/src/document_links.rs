@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use starpls_common::parse;
+use starpls_common::Db;
+use starpls_common::Dialect;
+use starpls_common::FileId;
+use starpls_common::ResolvedPath;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::TextRange;
+
+use crate::document_symbols::target_name;
+use crate::Database;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocumentLinkTarget {
+    /// A target declared in a BUILD file known to the database. `range` is the range of the call
+    /// expression declaring the target.
+    Target { file_id: FileId, range: TextRange },
+
+    /// A source file, which might not be known to the database.
+    Path(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentLink {
+    pub range: TextRange,
+    pub target: DocumentLinkTarget,
+    pub tooltip: Option<String>,
+}
+
+/// Turns every string literal in a Bazel file that resolves to either a source file or a target,
+/// e.g. the module of a `load()` statement or the entries of `srcs` and `deps`, into a link.
+pub(crate) fn document_links(db: &Database, file_id: FileId) -> Option<Vec<DocumentLink>> {
+    let file = db.get_file(file_id)?;
+    if file.dialect(db) != Dialect::Bazel {
+        return None;
+    }
+
+    let links = parse(db, file)
+        .syntax(db)
+        .descendants()
+        .filter_map(ast::LiteralExpr::cast)
+        .filter(|lit| !is_target_name(lit))
+        .filter_map(|lit| {
+            let value = match lit.kind() {
+                ast::LiteralKind::String(s) => s.value()?,
+                _ => return None,
+            };
+            let (target, canonical_repo) =
+                match db.resolve_path(&value, Dialect::Bazel, file_id).ok()?? {
+                    ResolvedPath::Source {
+                        path,
+                        canonical_repo,
+                    } => (DocumentLinkTarget::Path(path), canonical_repo),
+                    ResolvedPath::BuildTarget {
+                        build_file,
+                        target,
+                        canonical_repo,
+                        ..
+                    } => (
+                        DocumentLinkTarget::Target {
+                            file_id: build_file,
+                            range: find_target(db, build_file, &target)?,
+                        },
+                        canonical_repo,
+                    ),
+                };
+            Some(DocumentLink {
+                range: lit.syntax().text_range(),
+                target,
+                tooltip: canonical_repo.map(|repo| format!("Canonical repository: @@{}", repo)),
+            })
+        })
+        .collect();
+    Some(links)
+}
+
+/// Whether the given literal is the `name` argument of a call, i.e. it declares a target instead
+/// of referring to one.
+fn is_target_name(lit: &ast::LiteralExpr) -> bool {
+    lit.syntax()
+        .parent()
+        .and_then(ast::KeywordArgument::cast)
+        .and_then(|arg| arg.name()?.name())
+        .map(|name| name.text() == "name")
+        .unwrap_or_default()
+}
+
+fn find_target(db: &Database, build_file: FileId, target: &str) -> Option<TextRange> {
+    let build_file = db.get_file(build_file)?;
+    parse(db, build_file)
+        .syntax(db)
+        .children()
+        .filter_map(ast::CallExpr::cast)
+        .find(|call_expr| target_name(call_expr).as_deref() == Some(target))
+        .map(|call_expr| call_expr.syntax().text_range())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use starpls_bazel::APIContext;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+    use starpls_syntax::TextRange;
+    use starpls_syntax::TextSize;
+
+    use crate::Analysis;
+    use crate::DocumentLinkTarget;
+
+    #[test]
+    fn test_document_links() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(&mut analysis.db, "//:defs.bzl", "");
+        let build_file_id = fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:defs.bzl", "my_macro")
+
+cc_library(
+    name = "foo",
+)
+
+cc_binary(
+    name = "main",
+    deps = [":foo", ":missing"],
+)
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        let links = analysis
+            .snapshot()
+            .document_links(build_file_id)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|link| (link.range, link.target))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    TextRange::at(TextSize::new(6), TextSize::new(13)),
+                    DocumentLinkTarget::Path(PathBuf::from("//:defs.bzl")),
+                ),
+                (
+                    TextRange::at(TextSize::new(109), TextSize::new(6)),
+                    DocumentLinkTarget::Target {
+                        file_id: build_file_id,
+                        range: TextRange::at(TextSize::new(34), TextSize::new(31)),
+                    },
+                ),
+            ]
+        );
+    }
+}
@@ -8,6 +8,7 @@ use starpls_hir::ScopeDef;
 use starpls_hir::Semantics;
 use starpls_syntax::ast::AstNode;
 use starpls_syntax::ast::{self};
+use starpls_syntax::SyntaxNode;
 use starpls_syntax::TextRange;
 
 use crate::Database;
@@ -98,30 +99,16 @@ pub(crate) fn document_symbols(db: &Database, file_id: FileId) -> Option<Vec<Doc
 }
 
 fn add_target_symbols(db: &Database, file: File, acc: &mut Vec<DocumentSymbol>) {
-    let root = parse(db, file).syntax(db);
-    let targets = root.children().filter_map(|child| {
+    acc.extend(target_symbols(&parse(db, file).syntax(db)));
+}
+
+/// Returns a symbol for each target declared at the top level of a BUILD file, i.e. each call
+/// expression with a string `name` argument.
+pub(crate) fn target_symbols(root: &SyntaxNode) -> impl Iterator<Item = DocumentSymbol> {
+    root.children().filter_map(|child| {
         let expr = ast::CallExpr::cast(child)?;
         let range = expr.syntax().text_range();
-        let name = expr
-            .arguments()
-            .into_iter()
-            .flat_map(|args| args.arguments())
-            .find_map(|arg| match arg {
-                ast::Argument::Keyword(arg) => {
-                    if arg.name()?.name()?.text() != "name" {
-                        return None;
-                    }
-                    let lit = match arg.expr()? {
-                        ast::Expression::Literal(lit) => lit,
-                        _ => return None,
-                    };
-                    match lit.kind() {
-                        ast::LiteralKind::String(s) => s.value(),
-                        _ => None,
-                    }
-                }
-                _ => None,
-            })?;
+        let name = target_name(&expr)?;
         Some(DocumentSymbol {
             name: format!(":{}", name),
             detail: None,
@@ -131,8 +118,31 @@ fn add_target_symbols(db: &Database, file: File, acc: &mut Vec<DocumentSymbol>)
             selection_range: range,
             children: None,
         })
-    });
-    acc.extend(targets);
+    })
+}
+
+/// Returns the value of the string `name` argument of a call expression, i.e. the name of the
+/// target declared by a rule or macro call in a BUILD file.
+pub(crate) fn target_name(expr: &ast::CallExpr) -> Option<Box<str>> {
+    expr.arguments()
+        .into_iter()
+        .flat_map(|args| args.arguments())
+        .find_map(|arg| match arg {
+            ast::Argument::Keyword(arg) => {
+                if arg.name()?.name()?.text() != "name" {
+                    return None;
+                }
+                let lit = match arg.expr()? {
+                    ast::Expression::Literal(lit) => lit,
+                    _ => return None,
+                };
+                match lit.kind() {
+                    ast::LiteralKind::String(s) => s.value(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 use memchr::memmem::Finder;
 use starpls_common::Db;
 use starpls_common::File;
+use starpls_hir::LoadItem;
 use starpls_hir::Name;
 use starpls_hir::ScopeDef;
 use starpls_hir::Semantics;
@@ -11,11 +12,12 @@ use starpls_syntax::TextSize;
 use starpls_syntax::T;
 
 use crate::util::pick_best_token;
+use crate::util::string_contents_range;
 use crate::Database;
 use crate::FilePosition;
 use crate::Location;
 
-struct FindReferencesHandler<'a> {
+pub(crate) struct FindReferencesHandler<'a> {
     sema: &'a Semantics<'a>,
     file: File,
     name: Name,
@@ -24,7 +26,22 @@ struct FindReferencesHandler<'a> {
 }
 
 impl<'a> FindReferencesHandler<'a> {
-    fn handle(mut self) -> Vec<Location> {
+    pub(crate) fn new(
+        sema: &'a Semantics<'a>,
+        file: File,
+        name: Name,
+        defs: Vec<ScopeDef>,
+    ) -> Self {
+        Self {
+            sema,
+            file,
+            name,
+            defs,
+            locations: vec![],
+        }
+    }
+
+    pub(crate) fn handle(mut self) -> Vec<Location> {
         let name = self.name.clone();
         let finder = Finder::new(name.as_str());
         let offsets = finder
@@ -110,54 +127,126 @@ pub(crate) fn find_references(
     })?;
     let node = token.parent()?;
 
-    let (name, defs) = if let Some(node) = ast::NameRef::cast(node.clone()) {
+    let (file, name, defs) = if let Some(node) = ast::NameRef::cast(node.clone()) {
         let name = Name::from_ast_name_ref(node.clone());
         let scope = sema.scope_for_expr(file, &ast::Expression::cast(node.syntax().clone())?)?;
-        let defs = scope
-            .resolve_name(&name)
-            .into_iter()
-            .flat_map(|def| match &def {
-                ScopeDef::Variable(_) => Some(def),
-                ScopeDef::Callable(ref callable) if callable.is_user_defined() => Some(def),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
-
-        if defs.is_empty() {
-            return None;
+        let defs = scope.resolve_name(&name);
+        match defs.first()? {
+            // Fall back to references within the current file if the loaded symbol can't be
+            // resolved.
+            ScopeDef::LoadItem(load_item) => resolve_loaded_defs(&sema, load_item)
+                .unwrap_or_else(|| (file, name, vec![ScopeDef::LoadItem(load_item.clone())])),
+            _ => (file, name, user_defined_defs(defs)?),
         }
-
-        (name, defs)
-    } else if let Some(node) = ast::Name::cast(node) {
+    } else if let Some(node) = ast::Name::cast(node.clone()) {
         let def_stmt = ast::DefStmt::cast(node.syntax().parent()?)?;
         let callable = sema.resolve_def_stmt(file, &def_stmt)?;
         (
+            file,
             Name::from_ast_name(node),
             vec![ScopeDef::Callable(callable)],
         )
+    } else if let Some(node) = ast::LoadItem::cast(node) {
+        let load_item = sema.resolve_load_item(file, &node)?;
+        resolve_loaded_defs(&sema, &load_item)?
     } else {
         return None;
     };
 
-    Some(
-        FindReferencesHandler {
-            sema: &sema,
-            file,
-            name,
-            defs,
-            locations: vec![],
+    let mut locations =
+        FindReferencesHandler::new(&sema, file, name.clone(), defs.clone()).handle();
+    if is_exported(&sema, file, &name, &defs) {
+        for (loader, item) in db.load_graph.loaders(db, file, &name) {
+            let loader_id = loader.id(db);
+            if let Some(range) = item.name().as_ref().and_then(string_contents_range) {
+                locations.push(Location {
+                    file_id: loader_id,
+                    range,
+                });
+            }
+
+            // Uses of an aliased item refer to the alias instead of the original name.
+            let local_name = match &item {
+                ast::LoadItem::Direct(_) => name.clone(),
+                ast::LoadItem::Aliased(item) => match item.alias() {
+                    Some(alias) => Name::from_ast_name(alias),
+                    None => continue,
+                },
+            };
+            let Some(load_item) = sema.resolve_load_item(loader, &item) else {
+                continue;
+            };
+            locations.extend(
+                FindReferencesHandler::new(
+                    &sema,
+                    loader,
+                    local_name,
+                    vec![ScopeDef::LoadItem(load_item)],
+                )
+                .handle(),
+            );
         }
-        .handle(),
-    )
+    }
+
+    Some(locations)
+}
+
+/// Resolves a loaded symbol to its definitions in the loaded file.
+pub(crate) fn resolve_loaded_defs(
+    sema: &Semantics,
+    load_item: &LoadItem,
+) -> Option<(File, Name, Vec<ScopeDef>)> {
+    let def = sema.def_for_load_item(load_item)?;
+    let file = def.syntax_node_ptr(sema.db)?.file;
+    let name = load_item.name(sema.db);
+    let defs = user_defined_defs(sema.scope_for_module(file).resolve_name(&name))?;
+    Some((file, name, defs))
+}
+
+/// Filters out all definitions other than user-defined variables and functions, returning
+/// `None` if none remain.
+pub(crate) fn user_defined_defs(defs: Vec<ScopeDef>) -> Option<Vec<ScopeDef>> {
+    let defs = defs
+        .into_iter()
+        .filter(|def| match def {
+            ScopeDef::Variable(variable) => variable.is_user_defined(),
+            ScopeDef::Callable(callable) => callable.is_user_defined(),
+            _ => false,
+        })
+        .collect::<Vec<_>>();
+    (!defs.is_empty()).then_some(defs)
+}
+
+/// Whether any of the given definitions can be loaded from other files, i.e. whether they are
+/// public, top-level definitions.
+pub(crate) fn is_exported(sema: &Semantics, file: File, name: &Name, defs: &[ScopeDef]) -> bool {
+    !name.as_str().starts_with('_')
+        && sema
+            .scope_for_module(file)
+            .resolve_name(name)
+            .iter()
+            .any(|def| defs.contains(def))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+
+    use starpls_bazel::APIContext;
+    use starpls_common::Db;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
     use crate::Analysis;
     use crate::FilePosition;
 
     fn check_find_references(fixture: &str) {
         let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        check_find_references_with_fixture(&analysis, &fixture);
+    }
+
+    fn check_find_references_with_fixture(analysis: &Analysis, fixture: &Fixture) {
         let references = analysis
             .snapshot()
             .find_references(
@@ -252,4 +341,122 @@ f$0oo
 "#,
         );
     }
+
+    #[test]
+    fn test_references_across_loads() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:foo.bzl",
+            r#"
+def f$0oo():
+    #^^
+    pass
+"#,
+        );
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:foo.bzl", "foo")
+                    #^^
+foo()
+#^^
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        fixture.add_file(
+            &mut analysis.db,
+            "//:bar.bzl",
+            r#"
+load("//:foo.bzl", baz = "foo")
+                          #^^
+baz()
+#^^
+"#,
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+        check_find_references_with_fixture(&analysis, &fixture);
+    }
+
+    #[test]
+    fn test_references_from_unopened_loader() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:foo.bzl",
+            r#"
+def f$0oo():
+    #^^
+    pass
+"#,
+        );
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:foo.bzl", "foo")
+                    #^^
+foo()
+#^^
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        // Replace the BUILD file with one that was only indexed from disk, e.g. while walking
+        // the workspace, and was never opened or loaded.
+        let build_file_id = fixture.path_to_file_id[&PathBuf::from("//:BUILD.bazel")];
+        let build_file = analysis.db.get_file(build_file_id).unwrap();
+        let contents = build_file.contents(&analysis.db).clone();
+        analysis.db.files.remove(&build_file_id);
+        analysis.db.load_graph.remove_file(build_file_id);
+        analysis.load_graph().index_file(
+            build_file_id,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+            &contents,
+        );
+
+        check_find_references_with_fixture(&analysis, &fixture);
+    }
+
+    #[test]
+    fn test_references_from_load_item() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:foo.bzl",
+            r#"
+FOO = 123
+#^^
+"#,
+        );
+        fixture.add_file(
+            &mut analysis.db,
+            "//:bar.bzl",
+            r#"
+load("//:foo.bzl", "FOO")
+                    #^^
+BAR = F$0OO
+      #^^
+"#,
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+        check_find_references_with_fixture(&analysis, &fixture);
+    }
 }
@@ -0,0 +1,199 @@
+use std::iter;
+
+use starpls_common::parse;
+use starpls_common::Db;
+use starpls_common::FileId;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::SyntaxKind::*;
+use starpls_syntax::SyntaxNode;
+use starpls_syntax::TextRange;
+use starpls_syntax::T;
+
+use crate::Database;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldingRangeKind {
+    Comment,
+    Imports,
+    Region,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub range: TextRange,
+    pub kind: FoldingRangeKind,
+}
+
+pub(crate) fn folding_ranges(db: &Database, file_id: FileId) -> Option<Vec<FoldingRange>> {
+    let file = db.get_file(file_id)?;
+    let text = file.contents(db);
+    let tree = parse(db, file).tree(db);
+    let mut folds = Vec::new();
+    let mut add_fold = |range: TextRange, kind: FoldingRangeKind| {
+        // Ranges that fit on a single line can't be folded.
+        if text[range].contains('\n') {
+            folds.push(FoldingRange { range, kind });
+        }
+    };
+
+    // Consecutive top-level `load` statements are folded together.
+    let mut load_block: Option<TextRange> = None;
+    for stmt in tree.statements() {
+        match stmt {
+            ast::Statement::Load(stmt) => {
+                let range = stmt.syntax().text_range();
+                load_block = Some(load_block.map_or(range, |block| block.cover(range)));
+            }
+            _ => {
+                if let Some(block) = load_block.take() {
+                    add_fold(block, FoldingRangeKind::Imports);
+                }
+            }
+        }
+    }
+    if let Some(block) = load_block {
+        add_fold(block, FoldingRangeKind::Imports);
+    }
+
+    // Blocks of comments on consecutive lines are folded together.
+    let mut comment_block: Option<TextRange> = None;
+    for token in tree
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|el| el.into_token())
+        .filter(|token| token.kind() == COMMENT)
+    {
+        let range = token.text_range();
+        let line_prefix = text[..usize::from(range.start())]
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default();
+        if !line_prefix.trim().is_empty() {
+            // Trailing comments end any existing block.
+            if let Some(block) = comment_block.take() {
+                add_fold(block, FoldingRangeKind::Comment);
+            }
+            continue;
+        }
+
+        comment_block = match comment_block {
+            Some(block) if is_on_next_line(text, block, range) => Some(block.cover(range)),
+            Some(block) => {
+                add_fold(block, FoldingRangeKind::Comment);
+                Some(range)
+            }
+            None => Some(range),
+        };
+    }
+    if let Some(block) = comment_block {
+        add_fold(block, FoldingRangeKind::Comment);
+    }
+
+    for node in tree.syntax().descendants() {
+        match node.kind() {
+            DEF_STMT => add_fold(node.text_range(), FoldingRangeKind::Region),
+            ARGUMENTS | LIST_EXPR | LIST_COMP | DICT_EXPR | DICT_COMP => {
+                if let Some(range) = bracketed_fold_range(&node) {
+                    add_fold(range, FoldingRangeKind::Region);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    folds.sort_by_key(|fold| fold.range.start());
+    Some(folds)
+}
+
+/// Whether `next` starts on the line immediately following the end of `prev`.
+fn is_on_next_line(text: &str, prev: TextRange, next: TextRange) -> bool {
+    let between = &text[TextRange::new(prev.end(), next.start())];
+    between.trim().is_empty() && between.matches('\n').count() == 1
+}
+
+/// Returns the range from the opening bracket of `node` to the end of its last element. This keeps
+/// the closing bracket visible when the range is folded.
+fn bracketed_fold_range(node: &SyntaxNode) -> Option<TextRange> {
+    let open = node
+        .first_token()
+        .filter(|token| matches!(token.kind(), T!['('] | T!['['] | T!['{']))?;
+    let close = node
+        .last_token()
+        .filter(|token| matches!(token.kind(), T![')'] | T![']'] | T!['}']))?;
+    let last = iter::successors(close.prev_token(), |token| token.prev_token())
+        .find(|token| !matches!(token.kind(), WHITESPACE | NEWLINE))?;
+    (last.text_range().end() > open.text_range().end())
+        .then(|| TextRange::new(open.text_range().start(), last.text_range().end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_common::Db;
+
+    use crate::Analysis;
+
+    fn check_folding_ranges(fixture: &str, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let file_id = *fixture.path_to_file_id.values().next().unwrap();
+        let contents = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+        let line = |offset: usize| contents[..offset].matches('\n').count();
+        let actual = analysis
+            .snapshot()
+            .folding_ranges(file_id)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|fold| {
+                format!(
+                    "{:?} {}:{}\n",
+                    fold.kind,
+                    line(fold.range.start().into()),
+                    line(fold.range.end().into())
+                )
+            })
+            .collect::<String>();
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        check_folding_ranges(
+            r#"
+load("//:a.bzl", "a")
+load(
+    "//:b.bzl",
+    "b",
+)
+
+# Comment one.
+# Comment two.
+def foo():
+    x = [
+        1,
+        2,
+    ]
+    return x  # Not folded.
+
+foo(
+    name = "foo",
+)
+bar([1, 2])
+"#,
+            expect![[r#"
+                Imports 1:5
+                Comment 7:8
+                Region 9:14
+                Region 10:12
+                Region 16:17
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,1619 @@
+use starpls_bazel::APIContext;
+use starpls_common::FileId;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::LiteralKind;
+use starpls_syntax::ast::{self};
+use starpls_syntax::parse_module;
+use starpls_syntax::SyntaxKind;
+use starpls_syntax::SyntaxKind::*;
+use starpls_syntax::SyntaxNode;
+use starpls_syntax::SyntaxToken;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
+use starpls_syntax::T;
+
+use crate::Database;
+use crate::TextEdit;
+
+const INDENT: &str = "    ";
+
+/// Determines which of buildifier's layout rules are applied to a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatMode {
+    /// `BUILD`, `WORKSPACE`, `MODULE.bazel` and similar files. Top-level calls and collections
+    /// with more than one element are printed one element per line, and the keyword arguments
+    /// to rule calls are sorted.
+    Build,
+    /// `.bzl` files and other Starlark files. The original layout of calls and collections is
+    /// kept, with only whitespace and indentation being normalized.
+    Default,
+}
+
+impl FormatMode {
+    pub fn from_api_context(api_context: Option<APIContext>) -> FormatMode {
+        match api_context {
+            Some(
+                APIContext::Build
+                | APIContext::Module
+                | APIContext::Repo
+                | APIContext::Vendor
+                | APIContext::Workspace,
+            ) => FormatMode::Build,
+            _ => FormatMode::Default,
+        }
+    }
+}
+
+/// Formats the given Starlark source. Returns `None` if the source contains syntax errors.
+pub fn format_source(text: &str, mode: FormatMode) -> Option<String> {
+    let root = parse_source(text)?;
+    let mut printer = Printer::new(text, mode, &root);
+    let stmts = ast::Module::cast(root.clone())?.statements().collect();
+    printer.stmt_list(stmts, TextSize::from(0), TextSize::of(text), None);
+
+    let mut formatted = printer.out.trim_start_matches('\n').to_string();
+    let trimmed_len = formatted.trim_end().len();
+    formatted.truncate(trimmed_len);
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+    check_formatted(&root, &formatted, mode)?;
+    Some(formatted)
+}
+
+/// Formats the top-level statements intersecting the given range, returning the range of the
+/// original text to replace along with the formatted text. Returns `None` if the source contains
+/// syntax errors, or if the formatted statements don't pass the same checks as [`format_source`].
+fn format_source_range(
+    text: &str,
+    mode: FormatMode,
+    range: TextRange,
+) -> Option<(TextRange, String)> {
+    let root = parse_source(text)?;
+    let stmts = ast::Module::cast(root.clone())?
+        .statements()
+        .filter(|stmt| stmt.syntax().text_range().intersect(range).is_some())
+        .collect::<Vec<_>>();
+    let start = stmts.first()?.syntax().text_range().start();
+    let end = stmts.last()?.syntax().text_range().end();
+
+    let mut printer = Printer::new(text, mode, &root);
+    printer.stmt_list(stmts, start, end, None);
+    let range = TextRange::new(start, printer.last_comment_end.max(end));
+    let formatted = printer.out.trim().to_string();
+
+    // The replaced range consists of whole top-level statements and comments, so it can be
+    // checked on its own.
+    check_formatted(&parse_source(&text[range])?, &formatted, mode)?;
+    Some((range, formatted))
+}
+
+pub(crate) fn format(db: &Database, file_id: FileId) -> Option<Vec<TextEdit>> {
+    let file = db.get_file(file_id)?;
+    let text = file.contents(db);
+    let formatted = format_source(text, FormatMode::from_api_context(file.api_context(db)))?;
+    Some(
+        minimal_edit(text, TextRange::up_to(TextSize::of(text)), &formatted)
+            .into_iter()
+            .collect(),
+    )
+}
+
+pub(crate) fn format_range(
+    db: &Database,
+    file_id: FileId,
+    range: TextRange,
+) -> Option<Vec<TextEdit>> {
+    let file = db.get_file(file_id)?;
+    let text = file.contents(db);
+    let (range, formatted) = format_source_range(
+        text,
+        FormatMode::from_api_context(file.api_context(db)),
+        range,
+    )?;
+    Some(minimal_edit(text, range, &formatted).into_iter().collect())
+}
+
+fn parse_source(text: &str) -> Option<SyntaxNode> {
+    let mut has_errors = false;
+    let root = parse_module(text, &mut |_| has_errors = true).syntax();
+    (!has_errors).then_some(root)
+}
+
+/// Guards against formatter bugs by making sure that the output still parses, and that it
+/// consists of the same tokens and comments in the same order as the original. Only whitespace,
+/// commas and semicolons may be added or removed, and tokens may only be reordered within the
+/// load statements, arguments and lists that the formatter sorts.
+fn check_formatted(original: &SyntaxNode, formatted: &str, mode: FormatMode) -> Option<()> {
+    let root = parse_source(formatted)?;
+    if significant_tokens(original, mode, false) != significant_tokens(&root, mode, false) {
+        return None;
+    }
+    Some(())
+}
+
+/// Collects the tokens of the given node that formatting must preserve. The children of nodes
+/// sorted by the formatter are put into a canonical order, so that only reordering them is
+/// allowed. Comments within sorted nodes may move along with the elements they are attached to,
+/// so they are compared separately from the elements, as is the case within an enclosing sorted
+/// node if `is_in_sorted_node` is set.
+fn significant_tokens(
+    node: &SyntaxNode,
+    mode: FormatMode,
+    is_in_sorted_node: bool,
+) -> Vec<(SyntaxKind, String)> {
+    let is_sorted = is_sorted_by_formatter(node, mode);
+    let mut tokens = Vec::new();
+    let mut children = Vec::new();
+    for el in node.children_with_tokens() {
+        if let Some(child) = el.as_node() {
+            let child_tokens = significant_tokens(child, mode, is_in_sorted_node || is_sorted);
+            if is_sorted {
+                children.push(child_tokens);
+            } else {
+                tokens.extend(child_tokens);
+            }
+        } else if let Some(token) = el.as_token() {
+            if !is_comment(token.kind()) || !is_in_sorted_node {
+                tokens.extend(significant_token(token));
+            }
+        }
+    }
+
+    if is_sorted {
+        if !is_in_sorted_node {
+            tokens.extend(
+                node.descendants_with_tokens()
+                    .filter_map(|el| el.into_token())
+                    .filter(|token| token.parent().as_ref() != Some(node))
+                    .filter(|token| is_comment(token.kind()))
+                    .filter_map(|token| significant_token(&token)),
+            );
+        }
+        tokens.sort();
+        children.sort();
+        tokens.extend(children.into_iter().flatten());
+    }
+    tokens
+}
+
+fn significant_token(token: &SyntaxToken) -> Option<(SyntaxKind, String)> {
+    if matches!(
+        token.kind(),
+        WHITESPACE | NEWLINE | INDENT | DEDENT | T![,] | T![;]
+    ) {
+        return None;
+    }
+
+    // Strings may be printed with different quotes.
+    let text = match ast::String::cast(token.clone()) {
+        Some(string) => string.value().map_or_else(String::new, String::from),
+        None => token.text().trim_end().to_string(),
+    };
+    Some((token.kind(), text))
+}
+
+fn is_comment(kind: SyntaxKind) -> bool {
+    matches!(kind, COMMENT | TYPE_COMMENT)
+}
+
+/// Whether the formatter may reorder the children of the given node, i.e. the items of load
+/// statements, the keyword arguments of top-level rule calls and lists of labels in `BUILD`
+/// files.
+fn is_sorted_by_formatter(node: &SyntaxNode, mode: FormatMode) -> bool {
+    let is_build_layout =
+        || mode == FormatMode::Build && !node.ancestors().any(|node| node.kind() == SUITE);
+    match node.kind() {
+        LOAD_STMT => true,
+        ARGUMENTS => {
+            is_build_layout()
+                && node
+                    .parent()
+                    .and_then(|call| call.parent())
+                    .is_some_and(|parent| parent.kind() == MODULE)
+        }
+        LIST_EXPR => {
+            is_build_layout()
+                && node
+                    .parent()
+                    .and_then(ast::KeywordArgument::cast)
+                    .and_then(|arg| arg.name())
+                    .is_some_and(|name| is_sortable_list_arg(&name.syntax().text().to_string()))
+        }
+        _ => false,
+    }
+}
+
+/// Computes a single edit that replaces only the portion of `range` that actually changed.
+fn minimal_edit(text: &str, range: TextRange, formatted: &str) -> Option<TextEdit> {
+    let original = &text[range];
+    if original == formatted {
+        return None;
+    }
+
+    let prefix_len = original
+        .char_indices()
+        .zip(formatted.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(original.len().min(formatted.len()), |((pos, _), _)| pos);
+    let suffix_len = original[prefix_len..]
+        .chars()
+        .rev()
+        .zip(formatted[prefix_len..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+
+    let start = range.start() + TextSize::try_from(prefix_len).ok()?;
+    let end = range.end() - TextSize::try_from(suffix_len).ok()?;
+    Some(TextEdit {
+        range: TextRange::new(start, end),
+        new_text: formatted[prefix_len..formatted.len() - suffix_len].to_string(),
+    })
+}
+
+/// The priorities used by buildifier to order the keyword arguments of rule calls in `BUILD`
+/// files. Arguments not listed here have a priority of zero.
+const ARGUMENT_PRIORITIES: &[(&str, i32)] = &[
+    ("name", -99),
+    ("gwt_name", -98),
+    ("package_name", -97),
+    ("visible_node_name", -96),
+    ("size", -95),
+    ("timeout", -94),
+    ("testonly", -93),
+    ("src", -92),
+    ("srcdir", -91),
+    ("srcs", -90),
+    ("out", -89),
+    ("outs", -88),
+    ("hdrs", -87),
+    ("has_services", -86),
+    ("include", -85),
+    ("of", -84),
+    ("baseline", -83),
+    ("destdir", 1),
+    ("exports", 2),
+    ("runtime_deps", 3),
+    ("deps", 4),
+    ("implementation", 5),
+    ("implements", 6),
+    ("alwayslink", 7),
+];
+
+fn argument_priority(name: &str) -> i32 {
+    ARGUMENT_PRIORITIES
+        .iter()
+        .find(|(other, _)| *other == name)
+        .map_or(0, |(_, priority)| *priority)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SeqKind {
+    Call,
+    Def,
+    Dict,
+    List,
+    Load,
+    Tuple,
+}
+
+struct Comment {
+    range: TextRange,
+    is_emitted: bool,
+}
+
+/// The comments attached to an element of a sequence, e.g. the arguments of a call.
+struct AttachedComments {
+    leading: Vec<usize>,
+    trailing: Option<usize>,
+}
+
+struct Printer<'a> {
+    text: &'a str,
+    mode: FormatMode,
+    comments: Vec<Comment>,
+    out: String,
+    indent: usize,
+    /// The nesting depth of the statement currently being printed. Like buildifier, we only
+    /// apply the `BUILD` file layout rules to top-level statements.
+    level: usize,
+    last_comment_end: TextSize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(text: &'a str, mode: FormatMode, root: &SyntaxNode) -> Self {
+        let comments = root
+            .descendants_with_tokens()
+            .filter(|el| is_comment(el.kind()))
+            .map(|el| Comment {
+                range: el.text_range(),
+                is_emitted: false,
+            })
+            .collect();
+        Printer {
+            text,
+            mode,
+            comments,
+            out: String::new(),
+            indent: 0,
+            level: 0,
+            last_comment_end: TextSize::from(0),
+        }
+    }
+
+    fn stmt_list(
+        &mut self,
+        stmts: Vec<ast::Statement>,
+        lower: TextSize,
+        upper: TextSize,
+        min_column: Option<usize>,
+    ) {
+        let mut prev_start = lower;
+        let mut is_first = true;
+        for stmt in stmts {
+            let start = stmt.syntax().text_range().start();
+            for index in self.pending_comments(prev_start, start) {
+                self.own_line_comment(index, is_first);
+                is_first = false;
+            }
+            if !is_first && self.is_preceded_by_blank_line(start) {
+                self.newline();
+            }
+            is_first = false;
+            self.stmt(&stmt);
+            prev_start = start;
+        }
+
+        // Comments following the last statement are kept at this level only if they are
+        // indented at least as far as the statements themselves. Otherwise, they are left to
+        // be printed by an enclosing statement list.
+        for index in self.pending_comments(prev_start, upper) {
+            if min_column.map_or(true, |min_column| {
+                self.column(self.comments[index].range.start()) >= min_column
+            }) {
+                self.own_line_comment(index, is_first);
+                is_first = false;
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ast::Statement) {
+        match stmt {
+            ast::Statement::Def(stmt) => self.def_stmt(stmt),
+            ast::Statement::If(stmt) => self.if_stmt(stmt, "if"),
+            ast::Statement::For(stmt) => self.for_stmt(stmt),
+            _ => {
+                // Fall back to printing the original text of statements containing comments
+                // that we don't know how to place.
+                let out_len = self.out.len();
+                let emitted = self.emitted_comments();
+                self.write_indent();
+                self.simple_stmt(stmt);
+                let range = stmt.syntax().text_range();
+                if !self.pending_comments(range.start(), range.end()).is_empty() {
+                    self.out.truncate(out_len);
+                    self.restore_emitted_comments(emitted);
+                    self.write_indent();
+                    self.out.push_str(&self.text[range]);
+                    for index in self.pending_comments(range.start(), range.end()) {
+                        self.mark_emitted(index);
+                    }
+                }
+                self.trailing_comment(range.end());
+                self.newline();
+            }
+        }
+    }
+
+    fn simple_stmt(&mut self, stmt: &ast::Statement) {
+        match stmt {
+            ast::Statement::Return(stmt) => {
+                self.write("return");
+                if let Some(expr) = stmt.expr() {
+                    self.write(" ");
+                    self.expr(&expr);
+                }
+            }
+            ast::Statement::Break(_) => self.write("break"),
+            ast::Statement::Continue(_) => self.write("continue"),
+            ast::Statement::Pass(_) => self.write("pass"),
+            ast::Statement::Assign(stmt) => {
+                if let Some(lhs) = stmt.lhs() {
+                    self.expr(&lhs);
+                }
+                if let Some((op, _)) = stmt.assign_op_info() {
+                    self.write(" ");
+                    self.write(op.text());
+                    self.write(" ");
+                }
+                if let Some(rhs) = stmt.rhs() {
+                    self.expr(&rhs);
+                }
+            }
+            ast::Statement::Load(stmt) => self.load_stmt(stmt),
+            ast::Statement::Expr(expr) => self.expr(expr),
+            ast::Statement::Def(_) | ast::Statement::If(_) | ast::Statement::For(_) => {
+                unreachable!()
+            }
+        }
+    }
+
+    fn def_stmt(&mut self, stmt: &ast::DefStmt) {
+        self.write_indent();
+        self.write("def ");
+        if let Some(name) = stmt.name() {
+            self.write(&name.syntax().text().to_string());
+        }
+        if let Some(params) = stmt.parameters() {
+            let elements = params
+                .parameters()
+                .map(|param| param.syntax().clone())
+                .collect();
+            self.seq(params.syntax(), elements, None, SeqKind::Def);
+        }
+        self.write(":");
+        self.suite(stmt.syntax(), stmt.suite());
+    }
+
+    fn if_stmt(&mut self, stmt: &ast::IfStmt, keyword: &str) {
+        self.write_indent();
+        self.write(keyword);
+        self.write(" ");
+        if let Some(test) = stmt.test() {
+            self.expr(&test);
+        }
+        self.write(":");
+        self.suite(stmt.syntax(), stmt.if_suite());
+
+        if let Some(elif_stmt) = stmt.elif_stmt() {
+            self.if_stmt(&elif_stmt, "elif");
+        } else if let Some(else_suite) = stmt.else_suite() {
+            let colon_end = stmt
+                .syntax()
+                .children_with_tokens()
+                .skip_while(|el| el.kind() != T![else])
+                .find(|el| el.kind() == T![:])
+                .map_or_else(
+                    || else_suite.syntax().text_range().start(),
+                    |el| el.text_range().end(),
+                );
+            self.write_indent();
+            self.write("else:");
+            self.suite_after(colon_end, Some(else_suite));
+        }
+    }
+
+    fn for_stmt(&mut self, stmt: &ast::ForStmt) {
+        self.write_indent();
+        self.write("for ");
+        if let Some(targets) = stmt.targets() {
+            self.comma_separated(targets.exprs());
+        }
+        self.write(" in ");
+        if let Some(iterable) = stmt.iterable() {
+            self.expr(&iterable);
+        }
+        self.write(":");
+        self.suite(stmt.syntax(), stmt.suite());
+    }
+
+    /// Prints the suite of a compound statement, along with any comment following the colon
+    /// that ends the statement's header.
+    fn suite(&mut self, stmt: &SyntaxNode, suite: Option<ast::Suite>) {
+        let colon_end = stmt
+            .children_with_tokens()
+            .find(|el| el.kind() == T![:])
+            .map_or_else(|| stmt.text_range().end(), |el| el.text_range().end());
+        self.suite_after(colon_end, suite);
+    }
+
+    fn suite_after(&mut self, colon_end: TextSize, suite: Option<ast::Suite>) {
+        self.trailing_comment(colon_end);
+        self.newline();
+        let Some(suite) = suite else {
+            return;
+        };
+
+        let stmts = suite.statements().collect::<Vec<_>>();
+        let min_column = stmts
+            .first()
+            .map(|stmt| self.column(stmt.syntax().text_range().start()));
+        self.indent += 1;
+        self.level += 1;
+        self.stmt_list(
+            stmts,
+            colon_end,
+            suite.syntax().text_range().end(),
+            min_column,
+        );
+        self.level -= 1;
+        self.indent -= 1;
+    }
+
+    fn load_stmt(&mut self, stmt: &ast::LoadStmt) {
+        self.write("load");
+        let mut elements = Vec::new();
+        if let Some(module) = stmt.module() {
+            elements.push(module.syntax().clone());
+        }
+        let items = stmt.items().collect::<Vec<_>>();
+        elements.extend(items.iter().map(|item| item.syntax().clone()));
+
+        // Like buildifier, list the items that are loaded under their original names first,
+        // followed by aliased items, with each group sorted by the name it is bound to.
+        let mut order = (0..items.len()).collect::<Vec<_>>();
+        let sort_key = |item: &ast::LoadItem| {
+            let name = item
+                .name()
+                .map(|name| unquote(name.text()).to_string())
+                .unwrap_or_default();
+            match item {
+                ast::LoadItem::Direct(_) => (false, name),
+                ast::LoadItem::Aliased(item) => {
+                    let alias = item
+                        .alias()
+                        .map(|alias| alias.syntax().text().to_string())
+                        .unwrap_or_default();
+                    (alias != name, alias)
+                }
+            }
+        };
+        order.sort_by_cached_key(|&index| sort_key(&items[index]));
+        let order = std::iter::once(0)
+            .chain(order.into_iter().map(|index| index + 1))
+            .collect();
+
+        self.seq(stmt.syntax(), elements, Some(order), SeqKind::Load);
+    }
+
+    fn expr(&mut self, expr: &ast::Expression) {
+        match expr {
+            ast::Expression::Name(name_ref) => self.write(&name_ref.syntax().text().to_string()),
+            ast::Expression::Literal(lit) => self.literal(lit),
+            ast::Expression::If(expr) => {
+                if let Some(if_expr) = expr.if_expr() {
+                    self.expr(&if_expr);
+                }
+                self.write(" if ");
+                if let Some(test) = expr.test() {
+                    self.expr(&test);
+                }
+                self.write(" else ");
+                if let Some(else_expr) = expr.else_expr() {
+                    self.expr(&else_expr);
+                }
+            }
+            ast::Expression::Unary(expr) => {
+                if let Some((op, _)) = expr.unary_op_info() {
+                    self.write(op.text());
+                    if op.kind() == T![not] {
+                        self.write(" ");
+                    }
+                }
+                if let Some(expr) = expr.expr() {
+                    self.expr(&expr);
+                }
+            }
+            ast::Expression::Binary(expr) => self.binary_expr(expr),
+            ast::Expression::Lambda(expr) => {
+                self.write("lambda");
+                if let Some(params) = expr.parameters() {
+                    let mut params = params.parameters().peekable();
+                    if params.peek().is_some() {
+                        self.write(" ");
+                        for (i, param) in params.enumerate() {
+                            if i > 0 {
+                                self.write(", ");
+                            }
+                            self.parameter(&param);
+                        }
+                    }
+                }
+                self.write(": ");
+                if let Some(body) = expr.body() {
+                    self.expr(&body);
+                }
+            }
+            ast::Expression::List(expr) => {
+                let elements = expr.elements().map(|expr| expr.syntax().clone()).collect();
+                self.seq(expr.syntax(), elements, None, SeqKind::List);
+            }
+            ast::Expression::ListComp(expr) => {
+                self.write("[");
+                if let Some(expr) = expr.expr() {
+                    self.expr(&expr);
+                }
+                self.comp_clauses(expr.comp_clauses());
+                self.write("]");
+            }
+            ast::Expression::Dict(expr) => {
+                let elements = expr.entries().map(|entry| entry.syntax().clone()).collect();
+                self.seq(expr.syntax(), elements, None, SeqKind::Dict);
+            }
+            ast::Expression::DictComp(expr) => {
+                self.write("{");
+                if let Some(entry) = expr.entry() {
+                    self.dict_entry(&entry);
+                }
+                self.comp_clauses(expr.comp_clauses());
+                self.write("}");
+            }
+            ast::Expression::Tuple(expr) => {
+                if expr
+                    .syntax()
+                    .first_token()
+                    .is_some_and(|token| token.kind() == T!['('])
+                {
+                    let elements = expr.elements().map(|expr| expr.syntax().clone()).collect();
+                    self.seq(expr.syntax(), elements, None, SeqKind::Tuple);
+                } else {
+                    let mut elements = expr.elements().peekable();
+                    let first = elements.next();
+                    let is_single = elements.peek().is_none();
+                    self.comma_separated(first.into_iter().chain(elements));
+                    if is_single {
+                        self.write(",");
+                    }
+                }
+            }
+            ast::Expression::Paren(expr) => {
+                let open_end = expr.syntax().text_range().start() + TextSize::of('(');
+                let inner = expr.expr();
+                let is_multiline = inner.as_ref().is_some_and(|inner| {
+                    self.has_newline(open_end, inner.syntax().text_range().start())
+                });
+                self.write("(");
+                if let Some(inner) = inner {
+                    if is_multiline {
+                        self.indent += 1;
+                        self.newline();
+                        self.write_indent();
+                        self.expr(&inner);
+                        self.indent -= 1;
+                        self.newline();
+                        self.write_indent();
+                    } else {
+                        self.expr(&inner);
+                    }
+                }
+                self.write(")");
+            }
+            ast::Expression::Dot(expr) => {
+                if let Some(expr) = expr.expr() {
+                    self.expr(&expr);
+                }
+                self.write(".");
+                if let Some(field) = expr.field() {
+                    self.write(&field.syntax().text().to_string());
+                }
+            }
+            ast::Expression::Call(expr) => {
+                if let Some(callee) = expr.callee() {
+                    self.expr(&callee);
+                }
+                if let Some(arguments) = expr.arguments() {
+                    self.arguments(expr, &arguments);
+                }
+            }
+            ast::Expression::Index(expr) => {
+                if let Some(lhs) = expr.lhs() {
+                    self.expr(&lhs);
+                }
+                self.write("[");
+                if let Some(index) = expr.index() {
+                    self.expr(&index);
+                }
+                self.write("]");
+            }
+            ast::Expression::Slice(expr) => {
+                if let Some(expr) = expr.expr() {
+                    self.expr(&expr);
+                }
+                self.write("[");
+                if let Some(start) = expr.start() {
+                    self.expr(&start);
+                }
+                self.write(":");
+                if let Some(end) = expr.end() {
+                    self.expr(&end);
+                }
+                let num_colons = expr
+                    .syntax()
+                    .children_with_tokens()
+                    .filter(|el| el.kind() == T![:])
+                    .count();
+                if num_colons > 1 {
+                    self.write(":");
+                    if let Some(step) = expr.step() {
+                        self.expr(&step);
+                    }
+                }
+                self.write("]");
+            }
+        }
+    }
+
+    fn literal(&mut self, lit: &ast::LiteralExpr) {
+        let token = lit.token();
+        if token.kind() == STRING {
+            self.string(token.text());
+        } else {
+            self.write(token.text());
+        }
+    }
+
+    fn string(&mut self, text: &str) {
+        // Prefer double quotes for simple single-quoted strings, as buildifier does.
+        if text.starts_with('\'') && !text.starts_with("'''") {
+            let contents = &text[1..text.len() - 1];
+            if !contents.contains(['"', '\\']) {
+                self.write("\"");
+                self.write(contents);
+                self.write("\"");
+                return;
+            }
+        }
+        self.write(text);
+    }
+
+    fn binary_expr(&mut self, expr: &ast::BinaryExpr) {
+        let (Some(lhs), Some(rhs)) = (expr.lhs(), expr.rhs()) else {
+            self.write(&expr.syntax().text().to_string());
+            return;
+        };
+
+        // The operator consists of every token between the operands, e.g. `not in`.
+        let lhs_end = lhs.syntax().text_range().end();
+        let rhs_start = rhs.syntax().text_range().start();
+        let op = expr
+            .syntax()
+            .children_with_tokens()
+            .filter_map(|el| el.into_token())
+            .filter(|token| {
+                !token.kind().is_trivia_token()
+                    && token.text_range().start() >= lhs_end
+                    && token.text_range().end() <= rhs_start
+            })
+            .map(|token| token.text().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.expr(&lhs);
+        self.write(" ");
+        self.write(&op);
+
+        // Keep line breaks between operands, which are only possible within brackets.
+        if self.has_newline(lhs_end, rhs_start) {
+            self.newline();
+            self.indent += 1;
+            self.write_indent();
+            self.indent -= 1;
+        } else {
+            self.write(" ");
+        }
+        self.expr(&rhs);
+    }
+
+    fn comp_clauses(&mut self, clauses: impl Iterator<Item = ast::CompClause>) {
+        for clause in clauses {
+            match clause {
+                ast::CompClause::For(clause) => {
+                    self.write(" for ");
+                    if let Some(targets) = clause.targets() {
+                        self.comma_separated(targets.exprs());
+                    }
+                    self.write(" in ");
+                    if let Some(iterable) = clause.iterable() {
+                        self.expr(&iterable);
+                    }
+                }
+                ast::CompClause::If(clause) => {
+                    self.write(" if ");
+                    if let Some(test) = clause.test() {
+                        self.expr(&test);
+                    }
+                }
+            }
+        }
+    }
+
+    fn comma_separated(&mut self, exprs: impl Iterator<Item = ast::Expression>) {
+        for (i, expr) in exprs.enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.expr(&expr);
+        }
+    }
+
+    fn arguments(&mut self, call: &ast::CallExpr, arguments: &ast::Arguments) {
+        let args = arguments.arguments().collect::<Vec<_>>();
+        let elements = args.iter().map(|arg| arg.syntax().clone()).collect();
+
+        // Sort the keyword arguments of top-level rule calls in `BUILD` files by priority,
+        // leaving positional arguments and anything following `*args` or `**kwargs` in place.
+        let is_rule_call = self.is_build_layout()
+            && call
+                .syntax()
+                .parent()
+                .is_some_and(|parent| parent.kind() == MODULE);
+        let order = is_rule_call.then(|| {
+            let mut order = (0..args.len()).collect::<Vec<_>>();
+            let start = args
+                .iter()
+                .position(|arg| matches!(arg, ast::Argument::Keyword(_)))
+                .unwrap_or(args.len());
+            let end = args[start..]
+                .iter()
+                .position(|arg| !matches!(arg, ast::Argument::Keyword(_)))
+                .map_or(args.len(), |pos| start + pos);
+            order[start..end].sort_by_key(|&index| match &args[index] {
+                ast::Argument::Keyword(arg) => arg.name().map_or(0, |name| {
+                    argument_priority(&name.syntax().text().to_string())
+                }),
+                _ => 0,
+            });
+            order
+        });
+
+        self.seq(arguments.syntax(), elements, order, SeqKind::Call);
+    }
+
+    fn argument(&mut self, arg: &ast::Argument) {
+        match arg {
+            ast::Argument::Simple(arg) => {
+                if let Some(expr) = arg.expr() {
+                    self.expr(&expr);
+                }
+            }
+            ast::Argument::Keyword(arg) => {
+                let name = arg.name().map(|name| name.syntax().text().to_string());
+                if let Some(name) = &name {
+                    self.write(name);
+                }
+                self.write(" = ");
+                match arg.expr() {
+                    Some(ast::Expression::List(list))
+                        if self.is_build_layout()
+                            && name.as_deref().is_some_and(is_sortable_list_arg) =>
+                    {
+                        let elements = list.elements().map(|expr| expr.syntax().clone()).collect();
+                        let order = self.sorted_string_list_order(&list);
+                        self.seq(list.syntax(), elements, order, SeqKind::List);
+                    }
+                    Some(expr) => self.expr(&expr),
+                    None => {}
+                }
+            }
+            ast::Argument::UnpackedList(arg) => {
+                self.write("*");
+                if let Some(expr) = arg.expr() {
+                    self.expr(&expr);
+                }
+            }
+            ast::Argument::UnpackedDict(arg) => {
+                self.write("**");
+                if let Some(expr) = arg.expr() {
+                    self.expr(&expr);
+                }
+            }
+        }
+    }
+
+    /// Computes the order in which to print a list of strings, e.g. the labels passed to `deps`,
+    /// following buildifier. Comments on their own lines split the list into blocks that are
+    /// sorted separately. Lists containing anything other than strings are left alone.
+    fn sorted_string_list_order(&self, list: &ast::ListExpr) -> Option<Vec<usize>> {
+        let elements = list.elements().collect::<Vec<_>>();
+        let keys = elements
+            .iter()
+            .map(|element| match element {
+                ast::Expression::Literal(lit) => match lit.kind() {
+                    LiteralKind::String(s) => s.value().map(|value| label_sort_key(&value)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut order = (0..elements.len()).collect::<Vec<_>>();
+        let mut block_start = 0;
+        for i in 1..=elements.len() {
+            let starts_block = elements.get(i).is_some_and(|element| {
+                let prev_end = elements[i - 1].syntax().text_range().end();
+                self.pending_comments(prev_end, element.syntax().text_range().start())
+                    .into_iter()
+                    .any(|index| self.has_newline(prev_end, self.comments[index].range.start()))
+            });
+            if starts_block || i == elements.len() {
+                order[block_start..i].sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+                block_start = i;
+            }
+        }
+        Some(order)
+    }
+
+    fn parameter(&mut self, param: &ast::Parameter) {
+        match param {
+            ast::Parameter::Simple(param) => {
+                if let Some(name) = param.name() {
+                    self.write(&name.syntax().text().to_string());
+                }
+                if let Some(default) = param.default() {
+                    self.write(" = ");
+                    self.expr(&default);
+                }
+            }
+            ast::Parameter::ArgsList(param) => {
+                self.write("*");
+                if let Some(name) = param.name() {
+                    self.write(&name.syntax().text().to_string());
+                }
+            }
+            ast::Parameter::KwargsDict(param) => {
+                self.write("**");
+                if let Some(name) = param.name() {
+                    self.write(&name.syntax().text().to_string());
+                }
+            }
+        }
+    }
+
+    fn dict_entry(&mut self, entry: &ast::DictEntry) {
+        if let Some(key) = entry.key() {
+            self.expr(&key);
+        }
+        self.write(": ");
+        if let Some(value) = entry.value() {
+            self.expr(&value);
+        }
+    }
+
+    fn element(&mut self, node: &SyntaxNode) {
+        if let Some(expr) = ast::Expression::cast(node.clone()) {
+            self.expr(&expr);
+        } else if let Some(arg) = ast::Argument::cast(node.clone()) {
+            self.argument(&arg);
+        } else if let Some(param) = ast::Parameter::cast(node.clone()) {
+            self.parameter(&param);
+        } else if let Some(entry) = ast::DictEntry::cast(node.clone()) {
+            self.dict_entry(&entry);
+        } else if let Some(ast::LoadItem::Aliased(item)) = ast::LoadItem::cast(node.clone()) {
+            if let Some(alias) = item.alias() {
+                self.write(&alias.syntax().text().to_string());
+            }
+            self.write(" = ");
+            if let Some(name) = item.name() {
+                self.string(name.text());
+            }
+        } else {
+            // Load modules and direct load items are plain strings.
+            self.string(&node.text().to_string());
+        }
+    }
+
+    /// Prints a bracketed, comma-separated sequence. `container` is the node containing the
+    /// opening and closing brackets, and `order` optionally specifies a new order in which to
+    /// print the elements. Comments are moved along with the elements they are attached to,
+    /// except for comments on their own lines in lists.
+    fn seq(
+        &mut self,
+        container: &SyntaxNode,
+        elements: Vec<SyntaxNode>,
+        order: Option<Vec<usize>>,
+        kind: SeqKind,
+    ) {
+        let brackets = container
+            .children_with_tokens()
+            .filter_map(|el| el.into_token())
+            .filter(|token| {
+                matches!(
+                    token.kind(),
+                    T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}']
+                )
+            })
+            .collect::<Vec<_>>();
+        let (Some(open), Some(close)) = (brackets.first(), brackets.last()) else {
+            self.write(&container.text().to_string());
+            return;
+        };
+        let open_end = open.text_range().end();
+        let close_start = close.text_range().start();
+
+        // Attach each comment within the brackets to the closest element. Comments on the
+        // same line as an element are trailing comments, and comments on their own lines are
+        // printed before the following element. Any remaining comments are printed before the
+        // closing bracket.
+        let mut attached = Vec::with_capacity(elements.len());
+        let mut prev_end = open_end;
+        for (i, element) in elements.iter().enumerate() {
+            let range = element.text_range();
+            let next_start = elements
+                .get(i + 1)
+                .map_or(close_start, |next| next.text_range().start());
+            let leading = self.pending_comments(prev_end, range.start());
+            let trailing = self
+                .pending_comments(range.end(), next_start)
+                .into_iter()
+                .find(|index| !self.has_newline(range.end(), self.comments[*index].range.start()));
+            attached.push(AttachedComments { leading, trailing });
+            prev_end = trailing.map_or(range.end(), |index| self.comments[index].range.end());
+        }
+        let dangling = self.pending_comments(prev_end, close_start);
+
+        let has_comments = !dangling.is_empty()
+            || attached
+                .iter()
+                .any(|comments| !comments.leading.is_empty() || comments.trailing.is_some());
+        let is_multiline =
+            has_comments || !self.use_compact_layout(open_end, &elements, close_start, kind);
+
+        let order = order.unwrap_or_else(|| (0..elements.len()).collect());
+        self.write(open.text());
+        if is_multiline {
+            self.indent += 1;
+            for (pos, index) in order.into_iter().enumerate() {
+                // Sorted lists are split into blocks by comments on their own lines, so these
+                // comments stay in place instead of moving along with the elements.
+                let leading_index = if kind == SeqKind::List { pos } else { index };
+                self.newline();
+                for comment in std::mem::take(&mut attached[leading_index].leading) {
+                    self.write_indent();
+                    self.write_comment(comment);
+                    self.newline();
+                }
+                self.write_indent();
+                self.element(&elements[index]);
+                self.write(",");
+                if let Some(comment) = attached[index].trailing {
+                    self.write("  ");
+                    self.write_comment(comment);
+                }
+            }
+            for comment in dangling {
+                self.newline();
+                self.write_indent();
+                self.write_comment(comment);
+            }
+            self.indent -= 1;
+            self.newline();
+            self.write_indent();
+        } else {
+            let is_single = elements.len() == 1;
+            for (i, index) in order.into_iter().enumerate() {
+                if i > 0 {
+                    self.write(", ");
+                }
+                self.element(&elements[index]);
+            }
+            if kind == SeqKind::Tuple && is_single {
+                self.write(",");
+            }
+        }
+        self.write(close.text());
+    }
+
+    /// Decides whether a sequence should be printed on a single line, following buildifier's
+    /// rules. Outside of top-level statements in `BUILD` files, the original layout is kept.
+    fn use_compact_layout(
+        &self,
+        open_end: TextSize,
+        elements: &[SyntaxNode],
+        close_start: TextSize,
+        kind: SeqKind,
+    ) -> bool {
+        let is_single_line = {
+            let mut prev_end = open_end;
+            let mut is_single_line = true;
+            for element in elements {
+                let range = element.text_range();
+                is_single_line &= !self.has_newline(prev_end, range.start());
+                prev_end = range.end();
+            }
+            is_single_line && !self.has_newline(prev_end, close_start)
+        };
+
+        if kind == SeqKind::Load || !self.is_build_layout() || kind == SeqKind::Def {
+            return is_single_line;
+        }
+
+        // Sequences with at most one element are printed on a single line, unless they were
+        // explicitly split across multiple lines.
+        if elements.len() <= 1 {
+            return is_single_line;
+        }
+
+        // Calls and tuples with only positional arguments stay on a single line if they were
+        // written that way.
+        matches!(kind, SeqKind::Call | SeqKind::Tuple)
+            && is_single_line
+            && !elements
+                .iter()
+                .any(|element| element.kind() == KEYWORD_ARGUMENT)
+    }
+
+    fn is_build_layout(&self) -> bool {
+        self.mode == FormatMode::Build && self.level == 0
+    }
+
+    fn trailing_comment(&mut self, offset: TextSize) {
+        let Some(index) = self
+            .comments
+            .iter()
+            .position(|comment| !comment.is_emitted && comment.range.start() >= offset)
+        else {
+            return;
+        };
+        let between = &self.text[TextRange::new(offset, self.comments[index].range.start())];
+        if between.trim().is_empty() && !between.contains('\n') {
+            self.write("  ");
+            self.write_comment(index);
+        }
+    }
+
+    fn own_line_comment(&mut self, index: usize, is_first: bool) {
+        if !is_first && self.is_preceded_by_blank_line(self.comments[index].range.start()) {
+            self.newline();
+        }
+        self.write_indent();
+        self.write_comment(index);
+        self.newline();
+    }
+
+    fn write_comment(&mut self, index: usize) {
+        let range = self.comments[index].range;
+        self.out.push_str(self.text[range].trim_end());
+        self.mark_emitted(index);
+    }
+
+    fn mark_emitted(&mut self, index: usize) {
+        let comment = &mut self.comments[index];
+        comment.is_emitted = true;
+        self.last_comment_end = self.last_comment_end.max(comment.range.end());
+    }
+
+    fn emitted_comments(&self) -> (Vec<bool>, TextSize) {
+        (
+            self.comments
+                .iter()
+                .map(|comment| comment.is_emitted)
+                .collect(),
+            self.last_comment_end,
+        )
+    }
+
+    fn restore_emitted_comments(&mut self, (emitted, last_comment_end): (Vec<bool>, TextSize)) {
+        for (comment, is_emitted) in self.comments.iter_mut().zip(emitted) {
+            comment.is_emitted = is_emitted;
+        }
+        self.last_comment_end = last_comment_end;
+    }
+
+    /// Returns the comments that haven't been printed yet and start within the given range.
+    fn pending_comments(&self, start: TextSize, end: TextSize) -> Vec<usize> {
+        self.comments
+            .iter()
+            .enumerate()
+            .filter(|(_, comment)| {
+                !comment.is_emitted && comment.range.start() >= start && comment.range.start() < end
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn has_newline(&self, start: TextSize, end: TextSize) -> bool {
+        start < end && self.text[TextRange::new(start, end)].contains('\n')
+    }
+
+    fn is_preceded_by_blank_line(&self, offset: TextSize) -> bool {
+        let before = &self.text[..usize::from(offset)];
+        let Some(line_start) = before.rfind('\n') else {
+            return false;
+        };
+        let before = &before[..line_start];
+        match before.rfind('\n') {
+            Some(prev_line_start) => before[prev_line_start + 1..].trim().is_empty(),
+            None => false,
+        }
+    }
+
+    fn column(&self, offset: TextSize) -> usize {
+        let before = &self.text[..usize::from(offset)];
+        before.len() - before.rfind('\n').map_or(0, |pos| pos + 1)
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn newline(&mut self) {
+        let len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(len);
+        self.out.push('\n');
+    }
+}
+
+/// The keyword arguments whose values buildifier sorts when they are lists of strings.
+const SORTABLE_LIST_ARGS: &[&str] = &[
+    "cc_deps",
+    "common_deps",
+    "compatible_with",
+    "data",
+    "default_visibility",
+    "deps",
+    "exec_compatible_with",
+    "exports",
+    "hdrs",
+    "implementation_deps",
+    "includes",
+    "outs",
+    "plugins",
+    "resources",
+    "runtime_deps",
+    "srcs",
+    "tags",
+    "target_compatible_with",
+    "textual_hdrs",
+    "tools",
+    "visibility",
+];
+
+fn is_sortable_list_arg(name: &str) -> bool {
+    SORTABLE_LIST_ARGS.contains(&name)
+}
+
+/// Relative paths and other plain strings sort first, followed by local labels like `:foo`,
+/// labels in the main repository and finally labels in external repositories. Within each group, labels are
+/// compared by their `:` and `.` separated components, so that the targets of a package, e.g.
+/// `//foo:bar`, sort before its subpackages, e.g. `//foo/baz`.
+fn label_sort_key(value: &str) -> (u8, Vec<String>) {
+    let phase = if value.starts_with(':') {
+        1
+    } else if value.starts_with("//") {
+        2
+    } else if value.starts_with('@') {
+        3
+    } else {
+        0
+    };
+    let components = value
+        .replace(':', ".")
+        .split('.')
+        .map(String::from)
+        .collect();
+    (phase, components)
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim_matches(|c| c == '"' || c == '\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    use super::*;
+
+    fn check(input: &str, mode: FormatMode, expect: Expect) {
+        let formatted = format_source(input.trim_start(), mode).unwrap();
+        expect.assert_eq(&formatted);
+
+        // Formatting should be idempotent.
+        assert_eq!(format_source(&formatted, mode).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_build_file_layout() {
+        check(
+            r#"
+load("//:b.bzl", "b", a_alias="a")
+load(":defs.bzl","foo")
+
+# Comments are kept.
+cc_library(deps=["//b", "//a"], name='lib', srcs = ["lib.cc"],  # trailing
+    visibility = ["//visibility:public"])
+
+
+
+foo(name = "foo", srcs = glob(["*.cc"]))
+"#,
+            FormatMode::Build,
+            expect![[r#"
+                load("//:b.bzl", "b", a_alias = "a")
+                load(":defs.bzl", "foo")
+
+                # Comments are kept.
+                cc_library(
+                    name = "lib",
+                    srcs = ["lib.cc"],  # trailing
+                    visibility = ["//visibility:public"],
+                    deps = [
+                        "//a",
+                        "//b",
+                    ],
+                )
+
+                foo(
+                    name = "foo",
+                    srcs = glob(["*.cc"]),
+                )
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_sorted_label_lists() {
+        check(
+            r#"
+cc_library(
+    name = "lib",
+    srcs = ["b.cc", "a.cc"],
+    deps = [
+        "@c//:c",
+        "//foo/bar",
+        # Comments on their own lines split the list into blocks.
+        "//foo:foo",
+        ":local",
+        "//foo:bar",  # trailing
+    ],
+    args = ["--b", "--a"],
+)
+"#,
+            FormatMode::Build,
+            expect![[r#"
+                cc_library(
+                    name = "lib",
+                    srcs = [
+                        "a.cc",
+                        "b.cc",
+                    ],
+                    args = [
+                        "--b",
+                        "--a",
+                    ],
+                    deps = [
+                        "//foo/bar",
+                        "@c//:c",
+                        # Comments on their own lines split the list into blocks.
+                        ":local",
+                        "//foo:bar",  # trailing
+                        "//foo:foo",
+                    ],
+                )
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        check(
+            r#"
+# Leading comment.
+foo(
+  # Comment before the name.
+  name='foo', # Trailing comment.
+      srcs = ["a.cc"],
+        # Dangling comment.
+)   # After the call.
+
+# Comment at the end of the file.
+"#,
+            FormatMode::Build,
+            expect![[r#"
+                # Leading comment.
+                foo(
+                    # Comment before the name.
+                    name = "foo",  # Trailing comment.
+                    srcs = ["a.cc"],
+                    # Dangling comment.
+                )  # After the call.
+
+                # Comment at the end of the file.
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_nested_calls_and_trailing_commas() {
+        check(
+            r#"
+foo(name = "foo", srcs = glob(["*.cc"], exclude = ["a.cc"],), tags = ["b",],)
+bar(
+    "x",
+)
+baz("x", "y",)
+"#,
+            FormatMode::Build,
+            expect![[r#"
+                foo(
+                    name = "foo",
+                    srcs = glob(
+                        ["*.cc"],
+                        exclude = ["a.cc"],
+                    ),
+                    tags = ["b"],
+                )
+                bar(
+                    "x",
+                )
+                baz("x", "y")
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_line_wrapping() {
+        // Like buildifier, long lines aren't wrapped, and elements are only split across lines
+        // if they were written that way.
+        check(
+            r#"
+def foo():
+    return a_function_with_a_long_name(first_argument, second_argument)
+
+X = [1,
+     2]
+Y = {"a": [1, 2], "b": call(x,
+    y)}
+"#,
+            FormatMode::Default,
+            expect![[r#"
+                def foo():
+                    return a_function_with_a_long_name(first_argument, second_argument)
+
+                X = [
+                    1,
+                    2,
+                ]
+                Y = {"a": [1, 2], "b": call(
+                    x,
+                    y,
+                )}
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_check_formatted() {
+        let root = parse_source("foo(a, 'b')  # comment\n").unwrap();
+        let check = |formatted, mode| check_formatted(&root, formatted, mode);
+        assert_eq!(
+            check("foo(a, \"b\")  # comment\n", FormatMode::Default),
+            Some(())
+        );
+        assert_eq!(check("foo(a)  # comment\n", FormatMode::Default), None);
+        assert_eq!(check("foo(a, \"b\")\n", FormatMode::Default), None);
+        assert_eq!(
+            check("foo(a, \"c\")  # comment\n", FormatMode::Default),
+            None
+        );
+        assert_eq!(
+            check("foo(a, \"b\"  # comment\n", FormatMode::Default),
+            None
+        );
+
+        // The arguments of top-level calls may only be reordered in `BUILD` files.
+        assert_eq!(
+            check("foo(\"b\", a)  # comment\n", FormatMode::Default),
+            None
+        );
+        assert_eq!(
+            check("foo(\"b\", a)  # comment\n", FormatMode::Build),
+            Some(())
+        );
+    }
+
+    #[test]
+    fn test_check_formatted_sorted_spans() {
+        let root = parse_source("foo(deps = [\"b\", \"a\"], srcs = [x + y])\n").unwrap();
+        let check = |formatted| check_formatted(&root, formatted, FormatMode::Build);
+        assert_eq!(
+            check("foo(srcs = [x + y], deps = [\"a\", \"b\"])\n"),
+            Some(())
+        );
+        assert_eq!(check("foo(deps = [\"b\", \"a\"], srcs = [y + x])\n"), None);
+        assert_eq!(check("foo(deps = [\"a\"], srcs = [x + y, \"b\"])\n"), None);
+
+        let root = parse_source("def f():\n    foo(deps = [\"b\", \"a\"])\n").unwrap();
+        assert_eq!(
+            check_formatted(
+                &root,
+                "def f():\n    foo(deps = [\"a\", \"b\"])\n",
+                FormatMode::Build
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_bzl_file_layout() {
+        check(
+            r#"
+load("//:defs.bzl", "c", "b",
+  "a")
+
+def foo(x,y = 1, *args, **kwargs):
+  # Leading comment.
+  if x and not y:
+      return [x, y]  # type: list[int]
+  elif x:
+    pass
+  else:
+        z = {'a':1,
+          "b": 2}
+  return x[1:2]+y
+
+_BAR = [
+    1, # one
+    2,
+]
+"#,
+            FormatMode::Default,
+            expect![[r#"
+                load(
+                    "//:defs.bzl",
+                    "a",
+                    "b",
+                    "c",
+                )
+
+                def foo(x, y = 1, *args, **kwargs):
+                    # Leading comment.
+                    if x and not y:
+                        return [x, y]  # type: list[int]
+                    elif x:
+                        pass
+                    else:
+                        z = {
+                            "a": 1,
+                            "b": 2,
+                        }
+                    return x[1:2] + y
+
+                _BAR = [
+                    1,  # one
+                    2,
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(format_source("def foo(:\n", FormatMode::Default), None);
+    }
+
+    #[test]
+    fn test_format_range() {
+        let text = "x  =  1\ny  =  2\nz  =  3\n";
+        let (range, formatted) = format_source_range(
+            text,
+            FormatMode::Default,
+            TextRange::new(9.into(), 10.into()),
+        )
+        .unwrap();
+        assert_eq!(&text[range], "y  =  2");
+        assert_eq!(formatted, "y = 2");
+
+        // Only the formatted statements are checked.
+        let text = "load(':b.bzl', 'b')\nload(':a.bzl', 'a')\nfoo( deps = ['b', 'a'] )\n";
+        let (range, formatted) = format_source_range(
+            text,
+            FormatMode::Build,
+            TextRange::new(42.into(), 43.into()),
+        )
+        .unwrap();
+        assert_eq!(&text[range], "foo( deps = ['b', 'a'] )");
+        assert_eq!(
+            formatted,
+            "foo(\n    deps = [\n        \"a\",\n        \"b\",\n    ],\n)"
+        );
+    }
+}
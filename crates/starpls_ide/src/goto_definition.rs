@@ -187,7 +187,7 @@ impl<'a> GotoDefinitionHandler<'a> {
             .ok()??;
 
         match resolved_path {
-            ResolvedPath::Source { path } => path.try_exists().ok()?.then(|| {
+            ResolvedPath::Source { path, .. } => path.try_exists().ok()?.then(|| {
                 vec![LocationLink::External {
                     origin_selection_range: Some(self.token.text_range()),
                     target_path: path,
@@ -0,0 +1,240 @@
+use std::iter;
+
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_common::InFile;
+use starpls_hir::Name;
+use starpls_hir::ScopeDef;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::T;
+
+use crate::util::pick_best_token;
+use crate::Database;
+use crate::FilePosition;
+use crate::LocationLink;
+
+/// Navigates from a rule, repository rule, aspect, module extension or symbolic macro to the
+/// function passed as its `implementation`. Anywhere within a target, e.g. a `my_rule(...)` call
+/// in a BUILD file, navigates to the implementation of the corresponding rule.
+pub(crate) fn goto_implementation(
+    db: &Database,
+    FilePosition { file_id, pos }: FilePosition,
+) -> Option<Vec<LocationLink>> {
+    let sema = Semantics::new(db);
+    let file = db.get_file(file_id)?;
+    let parse = sema.parse(file);
+    let token = pick_best_token(parse.syntax(db).token_at_offset(pos), |kind| match kind {
+        T![ident] => 2,
+        T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}'] => 0,
+        kind if kind.is_trivia_token() => 0,
+        _ => 1,
+    })?;
+
+    let expr = token.parent_ancestors().find_map(ast::Expression::cast)?;
+    let callees = expr
+        .syntax()
+        .ancestors()
+        .filter_map(ast::CallExpr::cast)
+        .filter_map(|call_expr| call_expr.callee());
+    let declaration = iter::once(expr.clone())
+        .chain(callees)
+        .find_map(|expr| declaring_call(&sema, file, &expr))?;
+
+    let impl_expr = declaration
+        .value
+        .arguments()?
+        .arguments()
+        .find_map(|arg| match arg {
+            ast::Argument::Keyword(arg) if arg.name()?.name()?.text() == "implementation" => {
+                arg.expr()
+            }
+            _ => None,
+        })?;
+    let ast::Expression::Name(name_ref) = &impl_expr else {
+        return None;
+    };
+    let scope = sema.scope_for_expr(declaration.file, &impl_expr)?;
+    Some(
+        scope
+            .resolve_name(&Name::from_ast_name_ref(name_ref.clone()))
+            .into_iter()
+            .filter_map(|def| match def {
+                ScopeDef::LoadItem(load_item) => sema.def_for_load_item(&load_item),
+                _ => Some(def),
+            })
+            .filter_map(|def| def_stmt_location(&sema, def))
+            .collect(),
+    )
+}
+
+/// Finds the call that declared the value of the given expression, e.g. the `rule()` call for a
+/// rule. Aspects don't have a dedicated type, so they are found through the variable they are
+/// assigned to instead.
+fn declaring_call(
+    sema: &Semantics,
+    file: File,
+    expr: &ast::Expression,
+) -> Option<InFile<ast::CallExpr>> {
+    if let Some(source) = sema
+        .type_of_expr(file, expr)
+        .and_then(|ty| ty.declaration_source(sema.db))
+    {
+        return Some(source);
+    }
+
+    let ast::Expression::Name(name_ref) = expr else {
+        return None;
+    };
+    let scope = sema.scope_for_expr(file, expr)?;
+    scope
+        .resolve_name(&Name::from_ast_name_ref(name_ref.clone()))
+        .into_iter()
+        .filter_map(|def| match def {
+            ScopeDef::LoadItem(load_item) => sema.def_for_load_item(&load_item),
+            _ => Some(def),
+        })
+        .find_map(|def| {
+            let ScopeDef::Variable(_) = def else {
+                return None;
+            };
+            let InFile { file, value: ptr } = def.syntax_node_ptr(sema.db)?;
+            let node = ptr.try_to_node(&sema.parse(file).syntax(sema.db))?;
+            let call_expr = match ast::AssignStmt::cast(node.parent()?)?.rhs()? {
+                ast::Expression::Call(call_expr) => call_expr,
+                _ => return None,
+            };
+            let ast::Expression::Name(callee) = call_expr.callee()? else {
+                return None;
+            };
+            (callee.name()?.text() == "aspect").then_some(InFile {
+                file,
+                value: call_expr,
+            })
+        })
+}
+
+fn def_stmt_location(sema: &Semantics, def: ScopeDef) -> Option<LocationLink> {
+    let ScopeDef::Callable(ref callable) = def else {
+        return None;
+    };
+    if !callable.is_user_defined() {
+        return None;
+    }
+    let InFile { file, value: ptr } = def.syntax_node_ptr(sema.db)?;
+    let def_stmt = ptr
+        .try_to_node(&sema.parse(file).syntax(sema.db))
+        .and_then(ast::DefStmt::cast)?;
+    let range = def_stmt.name()?.syntax().text_range();
+    Some(LocationLink::Local {
+        origin_selection_range: None,
+        target_range: range,
+        target_selection_range: range,
+        target_file_id: file.id(sema.db),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use starpls_bazel::APIContext;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
+    use crate::Analysis;
+    use crate::FilePosition;
+    use crate::LocationLink;
+
+    fn check_goto_implementation(fixture: &str) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        check_goto_implementation_from_fixture(analysis, fixture);
+    }
+
+    fn check_goto_implementation_from_fixture(analysis: Analysis, fixture: Fixture) {
+        let actual = analysis
+            .snapshot()
+            .goto_implementation(
+                fixture
+                    .cursor_pos
+                    .map(|(file_id, pos)| FilePosition { file_id, pos })
+                    .unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|loc| match loc {
+                LocationLink::Local {
+                    target_range,
+                    target_file_id,
+                    ..
+                } => (target_file_id, target_range),
+                _ => panic!("expected local location"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fixture.selected_ranges, actual);
+    }
+
+    #[test]
+    fn test_rule() {
+        check_goto_implementation(
+            r#"
+def _my_rule_impl(ctx):
+    #^^^^^^^^^^^^
+    pass
+
+my_r$0ule = rule(implementation = _my_rule_impl)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_aspect() {
+        check_goto_implementation(
+            r#"
+def _my_aspect_impl(target, ctx):
+    #^^^^^^^^^^^^^^
+    pass
+
+my_aspect = aspect(implementation = _my_aspect_impl)
+
+print(my_a$0spect)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_rule_target() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:defs.bzl",
+            r#"
+def _impl(ctx):
+    #^^^^
+    pass
+
+my_rule = rule(implementation = _impl)
+"#,
+        );
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:defs.bzl", "my_rule")
+
+my_rule(
+    name = "f$0oo",
+)
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+        check_goto_implementation_from_fixture(analysis, fixture);
+    }
+}
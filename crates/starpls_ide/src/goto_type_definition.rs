@@ -0,0 +1,155 @@
+use starpls_common::Db;
+use starpls_common::InFile;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::T;
+
+use crate::util::pick_best_token;
+use crate::Database;
+use crate::FilePosition;
+use crate::LocationLink;
+
+/// Navigates from the expression at the given position to the call that declared its type, e.g.
+/// from a provider instance to the corresponding `provider()` call. Targets declared by rules are
+/// treated as having the type of the rule, so that anywhere within e.g. a `my_rule(...)` call
+/// navigates to the corresponding `rule()` call.
+pub(crate) fn goto_type_definition(
+    db: &Database,
+    FilePosition { file_id, pos }: FilePosition,
+) -> Option<Vec<LocationLink>> {
+    let sema = Semantics::new(db);
+    let file = db.get_file(file_id)?;
+    let parse = sema.parse(file);
+    let token = pick_best_token(parse.syntax(db).token_at_offset(pos), |kind| match kind {
+        T![ident] => 2,
+        T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}'] => 0,
+        kind if kind.is_trivia_token() => 0,
+        _ => 1,
+    })?;
+
+    let expr = token.parent_ancestors().find_map(ast::Expression::cast)?;
+    let source = sema
+        .type_of_expr(file, &expr)
+        .and_then(|ty| ty.declaration_source(db))
+        .or_else(|| {
+            expr.syntax()
+                .ancestors()
+                .filter_map(ast::CallExpr::cast)
+                .find_map(|call_expr| {
+                    let ty = sema.type_of_expr(file, &call_expr.callee()?)?;
+                    if !ty.is_rule() {
+                        return None;
+                    }
+                    ty.declaration_source(db)
+                })
+        })?;
+
+    let InFile { file, value } = source;
+    let range = value.syntax().text_range();
+    Some(vec![LocationLink::Local {
+        origin_selection_range: None,
+        target_range: range,
+        target_selection_range: range,
+        target_file_id: file.id(db),
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use starpls_bazel::APIContext;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
+    use crate::Analysis;
+    use crate::FilePosition;
+    use crate::LocationLink;
+
+    fn check_goto_type_definition(fixture: &str) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        check_goto_type_definition_from_fixture(analysis, fixture);
+    }
+
+    fn check_goto_type_definition_from_fixture(analysis: Analysis, fixture: Fixture) {
+        let actual = analysis
+            .snapshot()
+            .goto_type_definition(
+                fixture
+                    .cursor_pos
+                    .map(|(file_id, pos)| FilePosition { file_id, pos })
+                    .unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|loc| match loc {
+                LocationLink::Local {
+                    target_range,
+                    target_file_id,
+                    ..
+                } => (target_file_id, target_range),
+                _ => panic!("expected local location"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fixture.selected_ranges, actual);
+    }
+
+    #[test]
+    fn test_provider_instance() {
+        check_goto_type_definition(
+            r#"
+GoInfo = provider(fields = ["foo"])
+         #^^^^^^^^^^^^^^^^^^^^^^^^^
+info = GoInfo(foo = 123)
+in$0fo.foo
+"#,
+        );
+    }
+
+    #[test]
+    fn test_struct() {
+        check_goto_type_definition(
+            r#"
+s = struct(foo = "bar")
+    #^^^^^^^^^^^^^^^^^^
+print(s$0)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_rule_target() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:defs.bzl",
+            r#"
+def _impl(ctx):
+    pass
+
+my_rule = rule(implementation = _impl)
+          #^^^^^^^^^^^^^^^^^^^^^^^^^^^
+"#,
+        );
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:defs.bzl", "my_rule")
+
+my_rule(
+    name = "f$0oo",
+)
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+        check_goto_type_definition_from_fixture(analysis, fixture);
+    }
+}
@@ -0,0 +1,301 @@
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_common::FileId;
+use starpls_hir::DisplayWithDb;
+use starpls_hir::Semantics;
+use starpls_hir::Type;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::match_ast;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
+use starpls_syntax::T;
+
+use crate::Database;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InlayHintKind {
+    Type,
+    Parameter,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlayHint {
+    pub position: TextSize,
+    pub label: String,
+    pub kind: InlayHintKind,
+    pub pad_left: bool,
+    pub pad_right: bool,
+}
+
+struct InlayHintsHandler<'a> {
+    sema: Semantics<'a>,
+    file: File,
+    hints: Vec<InlayHint>,
+}
+
+impl<'a> InlayHintsHandler<'a> {
+    fn handle(mut self, range: TextRange) -> Vec<InlayHint> {
+        let root = self.sema.parse(self.file).syntax(self.sema.db);
+        for node in root
+            .descendants()
+            .filter(|node| node.text_range().intersect(range).is_some())
+        {
+            match_ast! {
+                match node {
+                    ast::AssignStmt(stmt) => self.handle_assign_stmt(stmt),
+                    ast::ForStmt(stmt) => self.handle_for_stmt(stmt),
+                    ast::DefStmt(stmt) => self.handle_def_stmt(stmt),
+                    ast::CallExpr(expr) => self.handle_call_expr(expr),
+                    _ => None
+                }
+            };
+        }
+
+        self.hints
+            .retain(|hint| range.contains_inclusive(hint.position));
+        self.hints.sort_by_key(|hint| hint.position);
+        self.hints
+    }
+
+    fn handle_assign_stmt(&mut self, stmt: ast::AssignStmt) -> Option<()> {
+        // Skip augmented assignments, annotated assignments, and assignments whose type is
+        // obvious from the right-hand side.
+        if !matches!(stmt.assign_op_info()?.1, ast::AssignOp::Normal)
+            || stmt.type_comment().is_some()
+            || matches!(stmt.rhs()?, ast::Expression::Literal(_))
+        {
+            return None;
+        }
+        self.add_target_type_hints(stmt.lhs()?);
+        Some(())
+    }
+
+    fn handle_for_stmt(&mut self, stmt: ast::ForStmt) -> Option<()> {
+        for expr in stmt.targets()?.exprs() {
+            self.add_target_type_hints(expr);
+        }
+        Some(())
+    }
+
+    fn handle_def_stmt(&mut self, stmt: ast::DefStmt) -> Option<()> {
+        // Functions with a type comment already spell out their return type.
+        if stmt.spec().is_some() {
+            return None;
+        }
+        let close_paren = stmt
+            .parameters()?
+            .syntax()
+            .last_token()
+            .filter(|token| token.kind() == T![')'])?;
+        let ty = self.sema.infer_return_type(self.file, &stmt)?;
+        self.hints.push(InlayHint {
+            position: close_paren.text_range().end(),
+            label: format!("-> {}", ty.display(self.sema.db)),
+            kind: InlayHintKind::Type,
+            pad_left: true,
+            pad_right: false,
+        });
+        Some(())
+    }
+
+    fn handle_call_expr(&mut self, expr: ast::CallExpr) -> Option<()> {
+        let callable = self.sema.resolve_call_expr(self.file, &expr)?;
+
+        // Only functions accept positional arguments. Rules, providers, etc. have keyword-only
+        // parameters.
+        if !callable.ty(self.sema.db).is_function() {
+            return None;
+        }
+
+        let params = callable.params(self.sema.db);
+        let args = expr.arguments()?.arguments().map_while(|arg| match arg {
+            ast::Argument::Simple(arg) => Some(arg),
+            _ => None,
+        });
+        for (arg, (param, _)) in args.zip(params.iter()) {
+            if param.is_args_list(self.sema.db)
+                || param.is_kwargs_dict(self.sema.db)
+                || param.is_positional_only(self.sema.db)
+            {
+                break;
+            }
+            let Some(name) = param.name(self.sema.db) else {
+                continue;
+            };
+
+            // Skip arguments that are simply named after the parameter.
+            if let Some(ast::Expression::Name(name_ref)) = arg.expr() {
+                if name_ref.name().as_ref().map(|name| name.text()) == Some(name.as_str()) {
+                    continue;
+                }
+            }
+
+            self.hints.push(InlayHint {
+                position: arg.syntax().text_range().start(),
+                label: format!("{}:", name.as_str()),
+                kind: InlayHintKind::Parameter,
+                pad_left: false,
+                pad_right: true,
+            });
+        }
+        Some(())
+    }
+
+    fn add_target_type_hints(&mut self, expr: ast::Expression) {
+        match expr {
+            ast::Expression::Name(ref name_ref) => {
+                let Some(ty) = self.sema.type_of_expr(self.file, &expr) else {
+                    return;
+                };
+                if !is_hintable_type(&ty) {
+                    return;
+                }
+                self.hints.push(InlayHint {
+                    position: name_ref.syntax().text_range().end(),
+                    label: format!(": {}", ty.display(self.sema.db)),
+                    kind: InlayHintKind::Type,
+                    pad_left: false,
+                    pad_right: false,
+                });
+            }
+            ast::Expression::Tuple(expr) => expr
+                .elements()
+                .for_each(|expr| self.add_target_type_hints(expr)),
+            ast::Expression::List(expr) => expr
+                .elements()
+                .for_each(|expr| self.add_target_type_hints(expr)),
+            ast::Expression::Paren(expr) => {
+                if let Some(expr) = expr.expr() {
+                    self.add_target_type_hints(expr);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Hints for unknown types aren't helpful, and callables are better described by their
+/// definitions.
+fn is_hintable_type(ty: &Type) -> bool {
+    !ty.is_unknown() && !ty.is_callable()
+}
+
+pub(crate) fn inlay_hints(
+    db: &Database,
+    file_id: FileId,
+    range: Option<TextRange>,
+) -> Option<Vec<InlayHint>> {
+    let file = db.get_file(file_id)?;
+    let sema = Semantics::new(db);
+    let range = range.unwrap_or_else(|| sema.parse(file).syntax(db).text_range());
+    Some(
+        InlayHintsHandler {
+            sema,
+            file,
+            hints: Vec::new(),
+        }
+        .handle(range),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_common::Db;
+
+    use crate::Analysis;
+
+    fn check_inlay_hints(fixture: &str, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let file_id = *fixture.path_to_file_id.values().next().unwrap();
+        let mut actual = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+        let hints = analysis
+            .snapshot()
+            .inlay_hints(file_id, None)
+            .unwrap()
+            .unwrap();
+
+        // Render the hints inline, starting from the end of the file so that earlier offsets
+        // remain valid.
+        for hint in hints.into_iter().rev() {
+            let label = format!(
+                "{}<{}>{}",
+                if hint.pad_left { " " } else { "" },
+                hint.label,
+                if hint.pad_right { " " } else { "" }
+            );
+            actual.insert_str(hint.position.into(), &label);
+        }
+        expect.assert_eq(actual.trim());
+    }
+
+    #[test]
+    fn test_assignment_and_loop_variable_types() {
+        check_inlay_hints(
+            r#"
+x = [1, 2]
+y = 1
+for z in x:
+    pass
+"#,
+            expect![[r#"
+                x<: list[int]> = [1, 2]
+                y = 1
+                for z<: int> in x:
+                    pass"#]],
+        );
+    }
+
+    #[test]
+    fn test_parameter_names_and_return_type() {
+        check_inlay_hints(
+            r#"
+def foo(a, b):
+    return [1]
+
+b = 1
+foo(1, b)
+"#,
+            expect![[r#"
+                def foo(a, b) <-> list[int]>:
+                    return [1]
+
+                b = 1
+                foo(<a:> 1, b)"#]],
+        );
+    }
+
+    #[test]
+    fn test_return_type_with_if_else() {
+        check_inlay_hints(
+            r#"
+def foo(x):
+    if x:
+        return [1]
+    else:
+        return ["one"]
+
+def bar(x):
+    if x:
+        return [1]
+"#,
+            expect![[r#"
+                def foo(x) <-> list[int] | list[string]>:
+                    if x:
+                        return [1]
+                    else:
+                        return ["one"]
+
+                def bar(x) <-> list[int] | None>:
+                    if x:
+                        return [1]"#]],
+        );
+    }
+}
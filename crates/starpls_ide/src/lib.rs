@@ -1,10 +1,15 @@
 use std::fmt::Debug;
+use std::mem;
 use std::panic;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::OnceLock;
 
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use rustc_hash::FxHashMap;
 use salsa::ParallelDatabase;
 use starpls_bazel::APIContext;
 use starpls_bazel::Builtins;
@@ -17,42 +22,84 @@ use starpls_common::FileInfo;
 use starpls_common::LoadItemCandidate;
 use starpls_common::ResolvedPath;
 use starpls_hir::BuiltinDefs;
+pub use starpls_hir::CancellationFlag;
 pub use starpls_hir::Cancelled;
 use starpls_hir::Db as _;
 #[cfg(test)]
 use starpls_hir::Fixture;
 use starpls_hir::GlobalContext;
 pub use starpls_hir::InferenceOptions;
+use starpls_hir::LoadResolution;
 use starpls_syntax::LineIndex;
 use starpls_syntax::TextRange;
 use starpls_syntax::TextSize;
 
+pub use crate::call_hierarchy::CallHierarchyCall;
+pub use crate::call_hierarchy::CallHierarchyItem;
+pub use crate::code_actions::CodeAction;
+pub use crate::code_actions::CodeActionKind;
+pub use crate::code_lenses::CodeLens;
+pub use crate::code_lenses::CodeLensKind;
+pub use crate::completions::AutoImport;
 pub use crate::completions::CompletionItem;
+pub use crate::completions::CompletionItemDetails;
 pub use crate::completions::CompletionItemKind;
 pub use crate::completions::CompletionMode;
 pub use crate::completions::Edit;
 pub use crate::completions::InsertReplaceEdit;
 pub use crate::completions::TextEdit;
+pub use crate::document_highlight::DocumentHighlight;
+pub use crate::document_highlight::DocumentHighlightKind;
+pub use crate::document_links::DocumentLink;
+pub use crate::document_links::DocumentLinkTarget;
 pub use crate::document_symbols::DocumentSymbol;
 pub use crate::document_symbols::SymbolKind;
 pub use crate::document_symbols::SymbolTag;
+pub use crate::folding_ranges::FoldingRange;
+pub use crate::folding_ranges::FoldingRangeKind;
+pub use crate::formatting::format_source;
+pub use crate::formatting::FormatMode;
 pub use crate::hover::Hover;
 pub use crate::hover::Markup;
+pub use crate::inlay_hints::InlayHint;
+pub use crate::inlay_hints::InlayHintKind;
+pub use crate::load_graph::LoadGraph;
+pub use crate::semantic_tokens::SemanticToken;
+pub use crate::semantic_tokens::SemanticTokenKind;
+pub use crate::semantic_tokens::SemanticTokenModifier;
 pub use crate::signature_help::ParameterInfo;
 pub use crate::signature_help::SignatureHelp;
 pub use crate::signature_help::SignatureInfo;
+pub use crate::workspace_symbols::FileSymbols;
+pub use crate::workspace_symbols::SymbolIndex;
+pub use crate::workspace_symbols::WorkspaceSymbol;
 
+mod call_hierarchy;
+mod code_actions;
+mod code_lenses;
 mod completions;
 mod diagnostics;
+mod document_highlight;
+mod document_links;
 mod document_symbols;
 mod find_references;
+mod folding_ranges;
+mod formatting;
 mod goto_definition;
+mod goto_implementation;
+mod goto_type_definition;
 mod hover;
+mod inlay_hints;
 mod line_index;
+mod load_graph;
+mod rename;
+mod selection_ranges;
+mod semantic_tokens;
 mod show_hir;
 mod show_syntax_tree;
 mod signature_help;
 mod util;
+mod workspace_symbols;
 
 pub type Cancellable<T> = Result<T, Cancelled>;
 
@@ -65,12 +112,41 @@ pub(crate) struct Database {
     gcx: Arc<GlobalContext>,
     prelude_file: Option<FileId>,
     all_workspace_targets: Arc<Vec<String>>,
+    load_graph: Arc<LoadGraph>,
+    load_resolution: Arc<OnceLock<LoadResolution>>,
+    cancellation_flag: Option<CancellationFlag>,
+    is_latency_sensitive: bool,
 }
 
 impl Database {
-    fn apply_file_changes(&mut self, changes: Vec<(FileId, FileChange)>) {
+    fn set_inference_options(&mut self, options: InferenceOptions) {
+        // Types inferred with the previous options are discarded along with the old context.
+        let prev_gcx = mem::replace(&mut self.gcx, Arc::new(GlobalContext::new(options)));
+        let _guard = prev_gcx.cancel();
+    }
+
+    /// Applies the given changes, returning whether anything changed. Updating or removing a
+    /// file that this database hasn't loaded is a no-op, e.g. for files owned by another
+    /// workspace, in which case inference isn't cancelled either.
+    fn apply_file_changes(
+        &mut self,
+        changes: Vec<(FileId, FileChange)>,
+        mut invalidate_loads: bool,
+    ) -> bool {
+        let changes = changes
+            .into_iter()
+            .filter(|(file_id, change)| {
+                matches!(change, FileChange::Create { .. }) || self.files.contains_key(file_id)
+            })
+            .collect::<Vec<_>>();
+        if changes.is_empty() && !invalidate_loads {
+            return false;
+        }
+
+        // Types inferred from the previous contents of the files are discarded.
         let gcx = self.gcx.clone();
         let _guard = gcx.cancel();
+        gcx.clear();
         for (file_id, change) in changes {
             match change {
                 FileChange::Create {
@@ -79,12 +155,50 @@ impl Database {
                     contents,
                 } => {
                     self.create_file(file_id, dialect, info, contents);
+                    invalidate_loads = true;
                 }
                 FileChange::Update { contents } => {
                     self.update_file(file_id, contents);
                 }
+                FileChange::Remove => {
+                    self.files.remove(&file_id);
+                    self.load_graph.remove_file(file_id);
+                    invalidate_loads = true;
+                }
             }
         }
+
+        // Load statements that resolved to a removed or replaced file, or that failed to resolve
+        // before a file was created, need to be resolved again.
+        if invalidate_loads {
+            let load_resolution = self.get_load_resolution();
+            let generation = load_resolution.generation(self);
+            load_resolution.set_generation(self).to(generation + 1);
+        }
+        true
+    }
+}
+
+impl Database {
+    /// Returns the file with the given ID, reading it through the [`FileLoader`] if it isn't
+    /// part of the database yet, e.g. because it was only indexed from disk.
+    fn get_or_read_file(
+        &self,
+        file_id: FileId,
+        dialect: Dialect,
+        info: Option<FileInfo>,
+    ) -> Option<File> {
+        if let Some(file) = self.get_file(file_id) {
+            return Some(file);
+        }
+        let contents = self.loader.read_file(file_id).ok()??;
+        Some(match self.files.entry(file_id) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                self.load_graph.invalidate_file(file_id);
+                *entry.insert(File::new(self, file_id, dialect, info, contents))
+            }
+        })
     }
 }
 
@@ -92,6 +206,16 @@ impl salsa::Database for Database {}
 
 impl salsa::ParallelDatabase for Database {
     fn snapshot(&self) -> salsa::Snapshot<Self> {
+        self.cancellable_snapshot(self.cancellation_flag.clone(), self.is_latency_sensitive)
+    }
+}
+
+impl Database {
+    fn cancellable_snapshot(
+        &self,
+        cancellation_flag: Option<CancellationFlag>,
+        is_latency_sensitive: bool,
+    ) -> salsa::Snapshot<Self> {
         salsa::Snapshot::new(Database {
             builtin_defs: self.builtin_defs.clone(),
             files: self.files.clone(),
@@ -100,6 +224,10 @@ impl salsa::ParallelDatabase for Database {
             storage: self.storage.snapshot(),
             prelude_file: self.prelude_file,
             all_workspace_targets: self.all_workspace_targets.clone(),
+            load_graph: self.load_graph.clone(),
+            load_resolution: self.load_resolution.clone(),
+            cancellation_flag,
+            is_latency_sensitive,
         })
     }
 }
@@ -114,12 +242,14 @@ impl starpls_common::Db for Database {
     ) -> File {
         let file = File::new(self, file_id, dialect, info, contents);
         self.files.insert(file_id, file);
+        self.load_graph.invalidate_file(file_id);
         file
     }
 
     fn update_file(&mut self, file_id: FileId, contents: String) {
         if let Some(file) = self.files.get(&file_id).map(|file_id| *file_id) {
             file.set_contents(self).to(contents);
+            self.load_graph.invalidate_file(file_id);
         }
     }
 
@@ -135,13 +265,16 @@ impl starpls_common::Db for Database {
         };
         Ok(Some(match self.files.entry(res.file_id) {
             Entry::Occupied(entry) => *entry.get(),
-            Entry::Vacant(entry) => *entry.insert(File::new(
-                self,
-                res.file_id,
-                dialect,
-                res.info,
-                res.contents.unwrap_or_default(),
-            )),
+            Entry::Vacant(entry) => {
+                self.load_graph.invalidate_file(res.file_id);
+                *entry.insert(File::new(
+                    self,
+                    res.file_id,
+                    dialect,
+                    res.info,
+                    res.contents.unwrap_or_default(),
+                ))
+            }
         }))
     }
 
@@ -239,6 +372,20 @@ impl starpls_hir::Db for Database {
         Arc::clone(&self.all_workspace_targets)
     }
 
+    fn get_load_resolution(&self) -> LoadResolution {
+        *self
+            .load_resolution
+            .get_or_init(|| LoadResolution::new(self, 0))
+    }
+
+    fn cancellation_flag(&self) -> Option<CancellationFlag> {
+        self.cancellation_flag.clone()
+    }
+
+    fn is_latency_sensitive(&self) -> bool {
+        self.is_latency_sensitive
+    }
+
     fn gcx(&self) -> &GlobalContext {
         &self.gcx
     }
@@ -254,6 +401,7 @@ enum FileChange {
     Update {
         contents: String,
     },
+    Remove,
 }
 
 /// A batch of changes to be applied to the database. For now, this consists simply of a map of changed file IDs to
@@ -261,6 +409,7 @@ enum FileChange {
 #[derive(Debug, Default)]
 pub struct Change {
     changed_files: Vec<(FileId, FileChange)>,
+    invalidate_loads: bool,
 }
 
 impl Change {
@@ -285,11 +434,29 @@ impl Change {
         self.changed_files
             .push((file_id, FileChange::Update { contents }))
     }
+
+    /// Removes a file from the database, e.g. after it was deleted from disk. Loading the file
+    /// again goes back through the [`FileLoader`].
+    pub fn remove_file(&mut self, file_id: FileId) {
+        self.changed_files.push((file_id, FileChange::Remove))
+    }
+
+    /// Resolves all `load` statements again, e.g. because files were created or deleted on disk
+    /// that the database doesn't know about. Creating and removing files in the database does
+    /// this automatically.
+    pub fn invalidate_loads(&mut self) {
+        self.invalidate_loads = true;
+    }
 }
 
+/// The source of [`Analysis::revision`]s. Revisions are unique across all analyses, so that
+/// results computed by different analyses can't be mistaken for one another.
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
 /// Provides the main API for querying facts about the source code. This wraps the main `Database` struct.
 pub struct Analysis {
     db: Database,
+    revision: u64,
 }
 
 impl Analysis {
@@ -303,32 +470,84 @@ impl Analysis {
                 loader,
                 prelude_file: None,
                 all_workspace_targets: Arc::default(),
+                load_graph: Arc::default(),
+                load_resolution: Arc::default(),
+                cancellation_flag: None,
+                is_latency_sensitive: false,
             },
+            revision: NEXT_REVISION.fetch_add(1, Ordering::Relaxed),
         }
     }
 
+    /// Identifies the current state of the analysis. The revision changes whenever files,
+    /// builtins or options change, i.e. whenever the results of queries might change.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn bump_revision(&mut self) {
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the index of `load` statements across the workspace. Files read from disk can be
+    /// added to it without being loaded into the database.
+    pub fn load_graph(&self) -> Arc<LoadGraph> {
+        self.db.load_graph.clone()
+    }
+
     pub fn apply_change(&mut self, change: Change) {
-        self.db.apply_file_changes(change.changed_files);
+        if self
+            .db
+            .apply_file_changes(change.changed_files, change.invalidate_loads)
+        {
+            self.bump_revision();
+        }
     }
 
     pub fn snapshot(&self) -> AnalysisSnapshot {
         AnalysisSnapshot {
             db: self.db.snapshot(),
+            revision: self.revision,
+        }
+    }
+
+    /// Like [`Analysis::snapshot`], but type inference for queries on the snapshot is aborted
+    /// once the given flag is cancelled, e.g. for a request cancelled by the client, in which
+    /// case the queries fail with [`Cancelled`]. Type inference for latency-sensitive snapshots
+    /// doesn't wait behind other snapshots, at the cost of possibly inferring types again.
+    pub fn cancellable_snapshot(
+        &self,
+        cancellation_flag: CancellationFlag,
+        is_latency_sensitive: bool,
+    ) -> AnalysisSnapshot {
+        AnalysisSnapshot {
+            db: self
+                .db
+                .cancellable_snapshot(Some(cancellation_flag), is_latency_sensitive),
+            revision: self.revision,
         }
     }
 
     pub fn set_builtin_defs(&mut self, builtins: Builtins, rules: Builtins) {
+        self.bump_revision();
         self.db.set_builtin_defs(Dialect::Bazel, builtins, rules);
     }
 
     pub fn set_bazel_prelude_file(&mut self, file_id: FileId) {
+        self.bump_revision();
         self.db.set_bazel_prelude_file(file_id);
     }
 
     pub fn set_all_workspace_targets(&mut self, targets: Vec<String>) {
+        self.bump_revision();
         self.db.set_all_workspace_targets(targets);
     }
 
+    pub fn set_inference_options(&mut self, options: InferenceOptions) {
+        self.bump_revision();
+        self.db.set_inference_options(options);
+    }
+
     #[cfg(test)]
     pub(crate) fn new_for_test() -> (Analysis, Arc<SimpleFileLoader>) {
         let loader = Arc::new(SimpleFileLoader::default());
@@ -347,9 +566,51 @@ impl Analysis {
 
 pub struct AnalysisSnapshot {
     db: salsa::Snapshot<Database>,
+    revision: u64,
 }
 
 impl AnalysisSnapshot {
+    /// The [`Analysis::revision`] this snapshot was taken at.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn auto_import_completions(
+        &self,
+        pos: FilePosition,
+        symbol_index: &SymbolIndex,
+    ) -> Cancellable<Option<Vec<CompletionItem>>> {
+        self.query(|db| completions::auto_import_completions(db, pos, symbol_index))
+    }
+
+    pub fn call_hierarchy_incoming_calls(
+        &self,
+        pos: FilePosition,
+    ) -> Cancellable<Option<Vec<CallHierarchyCall>>> {
+        self.query(|db| call_hierarchy::incoming_calls(db, pos))
+    }
+
+    pub fn call_hierarchy_outgoing_calls(
+        &self,
+        file_id: FileId,
+        pos: Option<TextSize>,
+    ) -> Cancellable<Option<Vec<CallHierarchyCall>>> {
+        self.query(|db| call_hierarchy::outgoing_calls(db, file_id, pos))
+    }
+
+    pub fn code_actions(
+        &self,
+        file_id: FileId,
+        range: TextRange,
+        symbol_index: &SymbolIndex,
+    ) -> Cancellable<Option<Vec<CodeAction>>> {
+        self.query(|db| code_actions::code_actions(db, file_id, range, symbol_index))
+    }
+
+    pub fn code_lenses(&self, file_id: FileId) -> Cancellable<Option<Vec<CodeLens>>> {
+        self.query(|db| code_lenses::code_lenses(db, file_id))
+    }
+
     pub fn completions(
         &self,
         pos: FilePosition,
@@ -362,6 +623,17 @@ impl AnalysisSnapshot {
         self.query(|db| diagnostics::diagnostics(db, file_id))
     }
 
+    pub fn document_highlight(
+        &self,
+        pos: FilePosition,
+    ) -> Cancellable<Option<Vec<DocumentHighlight>>> {
+        self.query(|db| document_highlight::document_highlight(db, pos))
+    }
+
+    pub fn document_links(&self, file_id: FileId) -> Cancellable<Option<Vec<DocumentLink>>> {
+        self.query(|db| document_links::document_links(db, file_id))
+    }
+
     pub fn document_symbols(&self, file_id: FileId) -> Cancellable<Option<Vec<DocumentSymbol>>> {
         self.query(|db| document_symbols::document_symbols(db, file_id))
     }
@@ -370,18 +642,101 @@ impl AnalysisSnapshot {
         self.query(|db| find_references::find_references(db, pos))
     }
 
+    pub fn folding_ranges(&self, file_id: FileId) -> Cancellable<Option<Vec<FoldingRange>>> {
+        self.query(|db| folding_ranges::folding_ranges(db, file_id))
+    }
+
+    pub fn format(&self, file_id: FileId) -> Cancellable<Option<Vec<TextEdit>>> {
+        self.query(|db| formatting::format(db, file_id))
+    }
+
+    pub fn format_range(
+        &self,
+        file_id: FileId,
+        range: TextRange,
+    ) -> Cancellable<Option<Vec<TextEdit>>> {
+        self.query(|db| formatting::format_range(db, file_id, range))
+    }
+
     pub fn goto_definition(&self, pos: FilePosition) -> Cancellable<Option<Vec<LocationLink>>> {
         self.query(|db| goto_definition::goto_definition(db, pos))
     }
 
+    pub fn goto_implementation(&self, pos: FilePosition) -> Cancellable<Option<Vec<LocationLink>>> {
+        self.query(|db| goto_implementation::goto_implementation(db, pos))
+    }
+
+    pub fn goto_type_definition(
+        &self,
+        pos: FilePosition,
+    ) -> Cancellable<Option<Vec<LocationLink>>> {
+        self.query(|db| goto_type_definition::goto_type_definition(db, pos))
+    }
+
     pub fn hover(&self, pos: FilePosition) -> Cancellable<Option<Hover>> {
         self.query(|db| hover::hover(db, pos))
     }
 
+    pub fn inlay_hints(
+        &self,
+        file_id: FileId,
+        range: Option<TextRange>,
+    ) -> Cancellable<Option<Vec<InlayHint>>> {
+        self.query(|db| inlay_hints::inlay_hints(db, file_id, range))
+    }
+
     pub fn line_index(&self, file_id: FileId) -> Cancellable<Option<&LineIndex>> {
         self.query(move |db| line_index::line_index(db, file_id))
     }
 
+    pub fn prepare_call_hierarchy(
+        &self,
+        pos: FilePosition,
+    ) -> Cancellable<Option<Vec<CallHierarchyItem>>> {
+        self.query(|db| call_hierarchy::prepare_call_hierarchy(db, pos))
+    }
+
+    pub fn prepare_rename(&self, pos: FilePosition) -> Cancellable<Option<TextRange>> {
+        self.query(|db| rename::prepare_rename(db, pos))
+    }
+
+    pub fn rename(
+        &self,
+        pos: FilePosition,
+        new_name: &str,
+    ) -> Cancellable<anyhow::Result<Option<SourceChange>>> {
+        self.query(|db| rename::rename(db, pos, new_name))
+    }
+
+    pub fn resolve_auto_import(
+        &self,
+        pos: FilePosition,
+        file_id: FileId,
+        name: &str,
+    ) -> Cancellable<Option<AutoImport>> {
+        self.query(|db| completions::resolve_auto_import(db, pos, file_id, name))
+    }
+
+    pub fn resolve_completion_item(
+        &self,
+        pos: FilePosition,
+        label: &str,
+    ) -> Cancellable<Option<CompletionItemDetails>> {
+        self.query(|db| completions::resolve_completion_item(db, pos, label))
+    }
+
+    pub fn selection_ranges(&self, pos: FilePosition) -> Cancellable<Option<Vec<TextRange>>> {
+        self.query(|db| selection_ranges::selection_ranges(db, pos))
+    }
+
+    pub fn semantic_tokens(
+        &self,
+        file_id: FileId,
+        range: Option<TextRange>,
+    ) -> Cancellable<Option<Vec<SemanticToken>>> {
+        self.query(|db| semantic_tokens::semantic_tokens(db, file_id, range))
+    }
+
     pub fn show_hir(&self, file_id: FileId) -> Cancellable<Option<String>> {
         self.query(|db| show_hir::show_hir(db, file_id))
     }
@@ -394,6 +749,23 @@ impl AnalysisSnapshot {
         self.query(|db| signature_help::signature_help(db, pos))
     }
 
+    /// Makes sure that the given files, e.g. the files found by walking the workspace, are part
+    /// of the database, reading the ones that were never opened or loaded through the
+    /// [`FileLoader`]. Returns the IDs of the files that could be read.
+    pub fn read_files(
+        &self,
+        files: Vec<(FileId, Dialect, Option<FileInfo>)>,
+    ) -> Cancellable<Vec<FileId>> {
+        self.query(|db| {
+            files
+                .into_iter()
+                .filter_map(|(file_id, dialect, info)| {
+                    db.get_or_read_file(file_id, dialect, info).map(|_| file_id)
+                })
+                .collect()
+        })
+    }
+
     /// Helper method to handle Salsa cancellations.
     fn query<'a, F, T>(&'a self, f: F) -> Cancellable<T>
     where
@@ -425,6 +797,12 @@ pub enum LocationLink {
     },
 }
 
+/// A set of text edits spanning one or more files, e.g. the result of a rename.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceChange {
+    pub file_edits: FxHashMap<FileId, Vec<TextEdit>>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FilePosition {
     pub file_id: FileId,
@@ -464,8 +842,16 @@ pub trait FileLoader: Send + Sync + 'static {
         from: FileId,
     ) -> anyhow::Result<Option<Vec<LoadItemCandidate>>>;
 
+    /// Reads the contents of a file that is only known by its ID, e.g. one indexed from disk
+    /// without having been loaded.
+    fn read_file(&self, file_id: FileId) -> anyhow::Result<Option<String>>;
+
     /// If the specified file is a BUILD file, returns its package.
     fn resolve_build_file(&self, file_id: FileId) -> Option<String>;
+
+    /// Returns a label that can be used to load the specified file from `from`, e.g.
+    /// `//foo:bar.bzl` or `@rules_python//python:defs.bzl`.
+    fn label_for_file(&self, file_id: FileId, from: FileId) -> Option<String>;
 }
 
 /// Simple implementation of [`FileLoader`] backed by a HashMap.
@@ -494,11 +880,44 @@ impl SimpleFileLoader {
 impl FileLoader for SimpleFileLoader {
     fn resolve_path(
         &self,
-        _path: &str,
+        path: &str,
         _dialect: Dialect,
-        _from: FileId,
+        from: FileId,
     ) -> anyhow::Result<Option<ResolvedPath>> {
-        Ok(None)
+        if self.0.contains_key(path) {
+            return Ok(Some(ResolvedPath::Source {
+                path: PathBuf::from(path),
+                canonical_repo: None,
+            }));
+        }
+
+        // Targets are resolved against the `BUILD.bazel` file of their package, with relative
+        // labels being resolved against the package of `from`.
+        let Some((package, target)) = path.rsplit_once(':') else {
+            return Ok(None);
+        };
+        let package = if package.is_empty() {
+            match self
+                .0
+                .iter()
+                .find(|entry| entry.value().file_id == from)
+                .and_then(|entry| Some(entry.key().split_once(':')?.0.to_string()))
+            {
+                Some(package) => package,
+                None => return Ok(None),
+            }
+        } else {
+            package.to_string()
+        };
+        Ok(self
+            .0
+            .get(&format!("{}:BUILD.bazel", package))
+            .map(|res| ResolvedPath::BuildTarget {
+                build_file: res.file_id,
+                target: target.to_string(),
+                contents: None,
+                canonical_repo: None,
+            }))
     }
 
     fn load_file(
@@ -519,7 +938,145 @@ impl FileLoader for SimpleFileLoader {
         Ok(None)
     }
 
+    fn read_file(&self, file_id: FileId) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .0
+            .iter()
+            .find(|entry| entry.value().file_id == file_id)
+            .and_then(|entry| entry.value().contents.clone()))
+    }
+
     fn resolve_build_file(&self, _file_id: FileId) -> Option<String> {
         None
     }
+
+    fn label_for_file(&self, file_id: FileId, _from: FileId) -> Option<String> {
+        self.0
+            .iter()
+            .find(|entry| entry.value().file_id == file_id)
+            .map(|entry| entry.key().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_files() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        let file_id = fixture.add_file(&mut analysis.db, "//:foo.bzl", "x = 1\n");
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        // Pretend that the file was only found on disk.
+        analysis.db.files.remove(&file_id);
+        let missing_file_id = FileId(file_id.0 + 1);
+        let snapshot = analysis.snapshot();
+        assert!(snapshot.line_index(file_id).unwrap().is_none());
+        assert_eq!(
+            snapshot
+                .read_files(vec![
+                    (file_id, Dialect::Bazel, None),
+                    (missing_file_id, Dialect::Bazel, None),
+                ])
+                .unwrap(),
+            vec![file_id]
+        );
+        assert!(snapshot.line_index(file_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_revision() {
+        let (mut analysis, _) = Analysis::new_for_test();
+        let revision = analysis.revision();
+        analysis.apply_change(Change::default());
+        assert_eq!(analysis.revision(), revision);
+
+        let mut change = Change::default();
+        change.create_file(FileId(0), Dialect::Bazel, None, "x = 1\n".to_string());
+        analysis.apply_change(change);
+        assert_ne!(analysis.revision(), revision);
+        assert_eq!(analysis.snapshot().revision(), analysis.revision());
+
+        // Revisions are unique across analyses.
+        let (other_analysis, _) = Analysis::new_for_test();
+        assert_ne!(other_analysis.revision(), analysis.revision());
+    }
+
+    #[test]
+    fn test_cancellable_snapshot() {
+        let (analysis, fixture) = Analysis::from_single_file_fixture("x = 1 + 2\n");
+        let file_id = fixture.path_to_file_id.values().next().copied().unwrap();
+
+        // Cancelling a request aborts its type inference only.
+        let cancellation_flag = CancellationFlag::default();
+        let snapshot = analysis.cancellable_snapshot(cancellation_flag.clone(), false);
+        cancellation_flag.cancel();
+        assert!(snapshot.diagnostics(file_id).is_err());
+        assert!(analysis.snapshot().diagnostics(file_id).is_ok());
+        assert!(analysis
+            .cancellable_snapshot(CancellationFlag::default(), true)
+            .diagnostics(file_id)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_apply_change_for_unloaded_files() {
+        let (mut analysis, _) = Analysis::from_single_file_fixture("x = 1\n");
+        let revision = analysis.revision();
+
+        // Changes to files owned by other workspaces leave the analysis alone.
+        let mut change = Change::default();
+        change.update_file(FileId(1000), "x = 2\n".to_string());
+        change.remove_file(FileId(1001));
+        analysis.apply_change(change);
+        analysis.apply_change(Change::default());
+        assert_eq!(analysis.revision(), revision);
+    }
+
+    #[test]
+    fn test_invalidate_loads() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        let file_id = fixture.add_file(
+            &mut analysis.db,
+            "//:bar.bzl",
+            "load(\"//:foo.bzl\", \"x\")\n",
+        );
+        let foo_file_id = fixture.add_file(&mut analysis.db, "//:foo.bzl", "x = 1\n");
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+        let resolve_load = |analysis: &Analysis| {
+            let pos = FilePosition {
+                file_id,
+                pos: TextSize::new(8),
+            };
+            match analysis
+                .snapshot()
+                .goto_definition(pos)
+                .unwrap()?
+                .as_slice()
+            {
+                [LocationLink::Local { target_file_id, .. }] => Some(*target_file_id),
+                _ => None,
+            }
+        };
+
+        // Pretend that `foo.bzl` was deleted.
+        let (path, res) = loader.0.remove("//:foo.bzl").unwrap();
+        let mut change = Change::default();
+        change.remove_file(foo_file_id);
+        analysis.apply_change(change);
+        assert_eq!(resolve_load(&analysis), None);
+
+        // Creating the file again isn't noticed until loads are invalidated.
+        loader.0.insert(path, res);
+        analysis.apply_change(Change::default());
+        assert_eq!(resolve_load(&analysis), None);
+
+        let mut change = Change::default();
+        change.invalidate_loads();
+        analysis.apply_change(change);
+        assert_eq!(resolve_load(&analysis), Some(foo_file_id));
+    }
 }
@@ -0,0 +1,255 @@
+use dashmap::DashMap;
+use dashmap::DashSet;
+use rustc_hash::FxHashSet;
+use starpls_common::parse;
+use starpls_common::Db;
+use starpls_common::Dialect;
+use starpls_common::File;
+use starpls_common::FileId;
+use starpls_common::FileInfo;
+use starpls_hir::Name;
+use starpls_syntax::ast::AstToken;
+use starpls_syntax::ast::{self};
+use starpls_syntax::parse_module;
+
+use crate::Database;
+
+/// The `load` statements of a single file, i.e. the modules it loads along with the names it
+/// imports from each of them.
+struct IndexedFile {
+    dialect: Dialect,
+    info: Option<FileInfo>,
+    loads: Vec<(Box<str>, Vec<Box<str>>)>,
+    /// Whether the file is part of the database, in which case it's indexed from the database
+    /// instead of from disk.
+    is_in_db: bool,
+}
+
+/// A reverse index of the `load` statements across the workspace, i.e. which files load a given
+/// module, and which names they import from it.
+///
+/// Files are indexed either from the database, or from disk for files that were never loaded,
+/// e.g. by walking the workspace. Files in the database are re-indexed lazily after they change.
+///
+/// Resolving a module goes through the [`FileLoader`](crate::FileLoader), which can be
+/// expensive, so successful resolutions are cached for each `(file, module)` pair. Unresolved
+/// modules aren't cached, since they may become resolvable later on, e.g. after fetching an
+/// external repository.
+#[derive(Default)]
+pub struct LoadGraph {
+    files: DashMap<FileId, IndexedFile>,
+    /// The files importing each name, used to narrow down the files to resolve.
+    importers: DashMap<Box<str>, FxHashSet<FileId>>,
+    /// Files in the database that changed since they were last indexed.
+    stale_files: DashSet<FileId>,
+    resolved_modules: DashMap<(FileId, Box<str>), FileId>,
+}
+
+impl LoadGraph {
+    /// Indexes a file read from disk that isn't necessarily part of the database. Files in the
+    /// database are skipped, since their contents may be more recent.
+    pub fn index_file(
+        &self,
+        file_id: FileId,
+        dialect: Dialect,
+        info: Option<FileInfo>,
+        contents: &str,
+    ) {
+        if self
+            .files
+            .get(&file_id)
+            .map_or(false, |indexed_file| indexed_file.is_in_db)
+        {
+            return;
+        }
+        let module = parse_module(contents, &mut |_| {}).tree();
+        self.insert(file_id, dialect, info, &module, false);
+    }
+
+    /// Forgets the given file along with the resolutions from and to it, e.g. after it was
+    /// removed from the database or deleted from disk.
+    pub fn remove_file(&self, file_id: FileId) {
+        self.remove_loads(file_id);
+        self.stale_files.remove(&file_id);
+        self.resolved_modules
+            .retain(|(from, _), loaded_file_id| *from != file_id && *loaded_file_id != file_id);
+    }
+
+    /// Marks a file in the database as changed, so that it's re-indexed before the next lookup.
+    pub(crate) fn invalidate_file(&self, file_id: FileId) {
+        self.stale_files.insert(file_id);
+    }
+
+    /// Returns the load items, across all indexed files, that import `name` from `file`. Files
+    /// that aren't part of the database yet are loaded only if they import `name` from `file`.
+    pub(crate) fn loaders(
+        &self,
+        db: &Database,
+        file: File,
+        name: &Name,
+    ) -> Vec<(File, ast::LoadItem)> {
+        self.reindex_stale_files(db);
+
+        let file_id = file.id(db);
+        let candidates = match self.importers.get(name.as_str()) {
+            Some(importers) => importers.iter().copied().collect::<Vec<_>>(),
+            None => return Vec::new(),
+        };
+
+        let mut loaders = Vec::new();
+        for candidate in candidates {
+            if candidate == file_id {
+                continue;
+            }
+
+            // Avoid resolving modules unless they're loaded along with the symbol.
+            let (dialect, info, modules) = match self.files.get(&candidate) {
+                Some(indexed_file) => (
+                    indexed_file.dialect,
+                    indexed_file.info.clone(),
+                    indexed_file
+                        .loads
+                        .iter()
+                        .filter(|(_, names)| names.iter().any(|n| &**n == name.as_str()))
+                        .map(|(module, _)| module.clone())
+                        .collect::<Vec<_>>(),
+                ),
+                None => continue,
+            };
+            let modules = modules
+                .into_iter()
+                .filter(|module| {
+                    self.resolve_module(db, candidate, dialect, module.clone()) == Some(file_id)
+                })
+                .collect::<Vec<_>>();
+            if modules.is_empty() {
+                continue;
+            }
+
+            let Some(loader) = db.get_or_read_file(candidate, dialect, info) else {
+                continue;
+            };
+            let tree = parse(db, loader).tree(db);
+            for load_stmt in tree.statements().filter_map(|stmt| match stmt {
+                ast::Statement::Load(load_stmt) => Some(load_stmt),
+                _ => None,
+            }) {
+                if !load_stmt_module(&load_stmt).is_some_and(|module| modules.contains(&module)) {
+                    continue;
+                }
+                loaders.extend(
+                    load_stmt
+                        .items()
+                        .filter(|item| load_item_name(item).as_deref() == Some(name.as_str()))
+                        .map(|item| (loader, item)),
+                );
+            }
+        }
+
+        loaders
+    }
+
+    fn reindex_stale_files(&self, db: &Database) {
+        let stale_files = self.stale_files.iter().map(|id| *id).collect::<Vec<_>>();
+        for file_id in stale_files {
+            if let Some(file) = db.get_file(file_id) {
+                let tree = parse(db, file).tree(db);
+                self.insert(file_id, file.dialect(db), file.info(db), &tree, true);
+            }
+            // Only mark the file as indexed once parsing it succeeded, since this may be
+            // cancelled.
+            self.stale_files.remove(&file_id);
+        }
+    }
+
+    fn insert(
+        &self,
+        file_id: FileId,
+        dialect: Dialect,
+        info: Option<FileInfo>,
+        module: &ast::Module,
+        is_in_db: bool,
+    ) {
+        self.remove_loads(file_id);
+
+        let loads = module
+            .statements()
+            .filter_map(|stmt| match stmt {
+                ast::Statement::Load(load_stmt) => Some(load_stmt),
+                _ => None,
+            })
+            .filter_map(|load_stmt| {
+                let module = load_stmt_module(&load_stmt)?;
+                let names = load_stmt
+                    .items()
+                    .filter_map(|item| load_item_name(&item))
+                    .collect::<Vec<_>>();
+                Some((module, names))
+            })
+            .collect::<Vec<_>>();
+        for (_, names) in &loads {
+            for name in names {
+                self.importers
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(file_id);
+            }
+        }
+        self.files.insert(
+            file_id,
+            IndexedFile {
+                dialect,
+                info,
+                loads,
+                is_in_db,
+            },
+        );
+    }
+
+    /// Removes the file's loads from the index. Resolutions from the file are forgotten as
+    /// well, since its dialect or location may have changed.
+    fn remove_loads(&self, file_id: FileId) {
+        if let Some((_, indexed_file)) = self.files.remove(&file_id) {
+            for (_, names) in indexed_file.loads {
+                for name in names {
+                    if let Some(mut importers) = self.importers.get_mut(&name) {
+                        importers.remove(&file_id);
+                    }
+                }
+            }
+        }
+        self.resolved_modules
+            .retain(|(from, _), _| *from != file_id);
+    }
+
+    fn resolve_module(
+        &self,
+        db: &Database,
+        from: FileId,
+        dialect: Dialect,
+        module: Box<str>,
+    ) -> Option<FileId> {
+        let key = (from, module);
+        if let Some(loaded_file_id) = self.resolved_modules.get(&key) {
+            return Some(*loaded_file_id);
+        }
+
+        let loaded_file_id = db.load_file(&key.1, dialect, from).ok()??.id(db);
+        self.resolved_modules.insert(key, loaded_file_id);
+        Some(loaded_file_id)
+    }
+}
+
+fn load_stmt_module(load_stmt: &ast::LoadStmt) -> Option<Box<str>> {
+    load_stmt
+        .module()
+        .and_then(|module| module.name())
+        .and_then(ast::String::cast)
+        .and_then(|module| module.value())
+}
+
+fn load_item_name(item: &ast::LoadItem) -> Option<Box<str>> {
+    item.name()
+        .and_then(ast::String::cast)
+        .and_then(|name| name.value())
+}
@@ -0,0 +1,520 @@
+use anyhow::bail;
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_hir::LoadItem;
+use starpls_hir::Name;
+use starpls_hir::ScopeDef;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::match_ast;
+use starpls_syntax::TextRange;
+use starpls_syntax::T;
+
+use crate::find_references::is_exported;
+use crate::find_references::resolve_loaded_defs;
+use crate::find_references::user_defined_defs;
+use crate::find_references::FindReferencesHandler;
+use crate::util::pick_best_token;
+use crate::util::string_contents_range;
+use crate::Database;
+use crate::FilePosition;
+use crate::SourceChange;
+use crate::TextEdit;
+
+const RESERVED_WORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda",
+    "load", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+/// The symbol being renamed.
+enum RenameTarget {
+    /// A definition in `file`. If the definition is exported, it is also renamed in every
+    /// `load` statement that imports it.
+    Def {
+        file: File,
+        name: Name,
+        defs: Vec<ScopeDef>,
+    },
+
+    /// The alias of an aliased load item, e.g. `bar` in `load(":foo.bzl", bar = "foo")`. Aliases
+    /// are only visible within the loading file.
+    Alias {
+        file: File,
+        name: Name,
+        load_item: LoadItem,
+        alias_range: TextRange,
+    },
+}
+
+pub(crate) fn prepare_rename(db: &Database, pos: FilePosition) -> Option<TextRange> {
+    let sema = Semantics::new(db);
+    resolve_rename_target(&sema, pos).map(|(_, range)| range)
+}
+
+pub(crate) fn rename(
+    db: &Database,
+    pos: FilePosition,
+    new_name: &str,
+) -> anyhow::Result<Option<SourceChange>> {
+    if !is_valid_identifier(new_name) {
+        bail!("\"{}\" is not a valid identifier", new_name);
+    }
+
+    let sema = Semantics::new(db);
+    let target = match resolve_rename_target(&sema, pos) {
+        Some((target, _)) => target,
+        None => return Ok(None),
+    };
+
+    let mut change = SourceChange::default();
+    let mut add_edit = |file: File, range: TextRange| {
+        change
+            .file_edits
+            .entry(file.id(db))
+            .or_default()
+            .push(TextEdit {
+                range,
+                new_text: new_name.to_string(),
+            })
+    };
+
+    match target {
+        RenameTarget::Def { file, name, defs } => {
+            if file.is_external(db).unwrap_or_default() {
+                bail!("Cannot rename symbols defined in external repositories");
+            }
+
+            for location in
+                FindReferencesHandler::new(&sema, file, name.clone(), defs.clone()).handle()
+            {
+                add_edit(file, location.range);
+            }
+
+            if is_exported(&sema, file, &name, &defs) {
+                for (loader, item) in db.load_graph.loaders(db, file, &name) {
+                    if !loader.is_external(db).unwrap_or_default() {
+                        rename_load_item(&sema, loader, item, &name, &mut add_edit);
+                    }
+                }
+            }
+        }
+        RenameTarget::Alias {
+            file,
+            name,
+            load_item,
+            alias_range,
+        } => {
+            add_edit(file, alias_range);
+            for location in
+                FindReferencesHandler::new(&sema, file, name, vec![ScopeDef::LoadItem(load_item)])
+                    .handle()
+            {
+                add_edit(file, location.range);
+            }
+        }
+    }
+
+    for edits in change.file_edits.values_mut() {
+        edits.sort_by_key(|edit| edit.range.start());
+        edits.dedup();
+    }
+
+    Ok(Some(change))
+}
+
+/// Renames an item in `loader` that loads the symbol being renamed, along with any uses of the
+/// item if it isn't aliased.
+fn rename_load_item(
+    sema: &Semantics,
+    loader: File,
+    item: ast::LoadItem,
+    name: &Name,
+    add_edit: &mut impl FnMut(File, TextRange),
+) {
+    if let Some(range) = item.name().as_ref().and_then(string_contents_range) {
+        add_edit(loader, range);
+    }
+
+    // Uses of an aliased item refer to the alias, so they don't need to be renamed.
+    if let ast::LoadItem::Aliased(_) = item {
+        return;
+    }
+
+    let Some(load_item) = sema.resolve_load_item(loader, &item) else {
+        return;
+    };
+    for location in FindReferencesHandler::new(
+        sema,
+        loader,
+        name.clone(),
+        vec![ScopeDef::LoadItem(load_item)],
+    )
+    .handle()
+    {
+        add_edit(loader, location.range);
+    }
+}
+
+fn resolve_rename_target(
+    sema: &Semantics,
+    FilePosition { file_id, pos }: FilePosition,
+) -> Option<(RenameTarget, TextRange)> {
+    let file = sema.db.get_file(file_id)?;
+    let parse = sema.parse(file);
+    let token = pick_best_token(
+        parse.syntax(sema.db).token_at_offset(pos),
+        |kind| match kind {
+            T![ident] => 2,
+            T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}'] => 0,
+            kind if kind.is_trivia_token() => 0,
+            _ => 1,
+        },
+    )?;
+    let parent = token.parent()?;
+
+    match_ast! {
+        match parent {
+            ast::NameRef(name_ref) => {
+                let name = Name::from_ast_name_ref(name_ref.clone());
+                let expr = ast::Expression::cast(name_ref.syntax().clone())?;
+                let defs = sema.scope_for_expr(file, &expr)?.resolve_name(&name);
+                let target = match defs.first()? {
+                    ScopeDef::LoadItem(load_item) => {
+                        target_for_load_item(sema, file, load_item.clone())?
+                    }
+                    _ => RenameTarget::Def {
+                        file,
+                        name,
+                        defs: user_defined_defs(defs)?,
+                    },
+                };
+                Some((target, name_ref.syntax().text_range()))
+            },
+            ast::Name(name) => {
+                let parent = name.syntax().parent()?;
+                match_ast! {
+                    match parent {
+                        ast::DefStmt(def_stmt) => {
+                            let callable = sema.resolve_def_stmt(file, &def_stmt)?;
+                            Some((
+                                RenameTarget::Def {
+                                    file,
+                                    name: Name::from_ast_name(name.clone()),
+                                    defs: vec![ScopeDef::Callable(callable)],
+                                },
+                                name.syntax().text_range(),
+                            ))
+                        },
+                        ast::AliasedLoadItem(item) => {
+                            let item = ast::LoadItem::Aliased(item);
+                            let load_item = sema.resolve_load_item(file, &item)?;
+                            Some((
+                                target_for_load_item(sema, file, load_item)?,
+                                name.syntax().text_range(),
+                            ))
+                        },
+                        _ => None
+                    }
+                }
+            },
+            ast::DirectLoadItem(item) => {
+                let range = string_contents_range(&item.name()?)?;
+                let load_item = sema.resolve_load_item(file, &ast::LoadItem::Direct(item))?;
+                Some((loaded_def_target(sema, &load_item)?, range))
+            },
+            ast::AliasedLoadItem(item) => {
+                let range = string_contents_range(&item.name()?)?;
+                let load_item = sema.resolve_load_item(file, &ast::LoadItem::Aliased(item))?;
+                Some((loaded_def_target(sema, &load_item)?, range))
+            },
+            _ => None
+        }
+    }
+}
+
+/// Determines what to rename when the symbol is a loaded item. Renaming an alias is local to
+/// the loading file, whereas renaming a directly loaded item renames the original definition.
+fn target_for_load_item(sema: &Semantics, file: File, load_item: LoadItem) -> Option<RenameTarget> {
+    let ptr = ScopeDef::LoadItem(load_item.clone()).syntax_node_ptr(sema.db)?;
+    let alias = ptr
+        .value
+        .try_to_node(&sema.parse(file).syntax(sema.db))
+        .and_then(ast::AliasedLoadItem::cast)
+        .and_then(|item| item.alias());
+    match alias {
+        Some(alias) => Some(RenameTarget::Alias {
+            file,
+            name: Name::from_ast_name(alias.clone()),
+            load_item,
+            alias_range: alias.syntax().text_range(),
+        }),
+        None => loaded_def_target(sema, &load_item),
+    }
+}
+
+fn loaded_def_target(sema: &Semantics, load_item: &LoadItem) -> Option<RenameTarget> {
+    let (file, name, defs) = resolve_loaded_defs(sema, load_item)?;
+    Some(RenameTarget::Def { file, name, defs })
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && !RESERVED_WORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_bazel::APIContext;
+    use starpls_common::Db;
+    use starpls_common::Dialect;
+    use starpls_common::FileInfo;
+    use starpls_hir::Fixture;
+
+    use crate::Analysis;
+    use crate::FilePosition;
+
+    fn check_rename(analysis: &Analysis, pos: FilePosition, new_name: &str, expect: Expect) {
+        let change = analysis
+            .snapshot()
+            .rename(pos, new_name)
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let mut file_edits = change.file_edits.into_iter().collect::<Vec<_>>();
+        file_edits.sort_by_key(|(file_id, _)| *file_id);
+
+        let mut actual = String::new();
+        for (file_id, edits) in file_edits {
+            let mut contents = analysis
+                .db
+                .get_file(file_id)
+                .unwrap()
+                .contents(&analysis.db)
+                .clone();
+            for edit in edits.into_iter().rev() {
+                contents.replace_range(std::ops::Range::<usize>::from(edit.range), &edit.new_text);
+            }
+            actual.push_str(&format!("file {}:\n{}\n", file_id.0, contents.trim()));
+        }
+        expect.assert_eq(&actual);
+    }
+
+    fn check_single_file_rename(fixture: &str, new_name: &str, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        check_rename(&analysis, FilePosition { file_id, pos }, new_name, expect);
+    }
+
+    #[test]
+    fn test_rename_variable() {
+        check_single_file_rename(
+            r#"
+abc = 123
+a$0bc
+print(abc)
+"#,
+            "xyz",
+            expect![[r#"
+                file 0:
+                xyz = 123
+                xyz
+                print(xyz)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_rename_function_definition() {
+        check_single_file_rename(
+            r#"
+def f$0oo():
+    pass
+
+foo()
+"#,
+            "bar",
+            expect![[r#"
+                file 0:
+                def bar():
+                    pass
+
+                bar()
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_rename_invalid_identifier() {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(
+            r#"
+a$0bc = 123
+"#,
+        );
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        assert!(analysis
+            .snapshot()
+            .rename(FilePosition { file_id, pos }, "not")
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn test_prepare_rename_builtin() {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(
+            r#"
+prov$0ider()
+"#,
+        );
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        assert_eq!(
+            analysis
+                .snapshot()
+                .prepare_rename(FilePosition { file_id, pos })
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rename_across_loads() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:foo.bzl",
+            r#"
+def f$0oo():
+    pass
+"#,
+        );
+        fixture.add_file_with_options(
+            &mut analysis.db,
+            "//:BUILD.bazel",
+            r#"
+load("//:foo.bzl", "foo")
+
+foo()
+"#,
+            Dialect::Bazel,
+            Some(FileInfo::Bazel {
+                api_context: APIContext::Build,
+                is_external: false,
+            }),
+        );
+        fixture.add_file(
+            &mut analysis.db,
+            "//:bar.bzl",
+            r#"
+load("//:foo.bzl", baz = "foo")
+
+def bar():
+    baz()
+"#,
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        check_rename(
+            &analysis,
+            FilePosition { file_id, pos },
+            "qux",
+            expect![[r#"
+                file 0:
+                def qux():
+                    pass
+                file 1:
+                load("//:foo.bzl", "qux")
+
+                qux()
+                file 2:
+                load("//:foo.bzl", baz = "qux")
+
+                def bar():
+                    baz()
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_rename_from_load_item() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:foo.bzl",
+            r#"
+FOO = 123
+"#,
+        );
+        fixture.add_file(
+            &mut analysis.db,
+            "//:bar.bzl",
+            r#"
+load("//:foo.bzl", "F$0OO")
+
+BAR = FOO
+"#,
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        check_rename(
+            &analysis,
+            FilePosition { file_id, pos },
+            "QUX",
+            expect![[r#"
+                file 0:
+                QUX = 123
+                file 1:
+                load("//:foo.bzl", "QUX")
+
+                BAR = QUX
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_rename_alias() {
+        let (mut analysis, loader) = Analysis::new_for_test();
+        let mut fixture = Fixture::new(&mut analysis.db);
+        fixture.add_file(
+            &mut analysis.db,
+            "//:foo.bzl",
+            r#"
+def foo():
+    pass
+"#,
+        );
+        fixture.add_file(
+            &mut analysis.db,
+            "//:bar.bzl",
+            r#"
+load("//:foo.bzl", baz = "foo")
+
+b$0az()
+"#,
+        );
+        loader.add_files_from_fixture(&analysis.db, &fixture);
+
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        check_rename(
+            &analysis,
+            FilePosition { file_id, pos },
+            "qux",
+            expect![[r#"
+                file 1:
+                load("//:foo.bzl", qux = "foo")
+
+                qux()
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use starpls_common::parse;
+use starpls_common::Db;
+use starpls_syntax::SyntaxKind::*;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
+use starpls_syntax::T;
+
+use crate::util::pick_best_token;
+use crate::util::string_contents_range;
+use crate::Database;
+use crate::FilePosition;
+
+/// Returns the ranges to select when repeatedly expanding the selection at the given position,
+/// ordered from innermost to outermost.
+pub(crate) fn selection_ranges(
+    db: &Database,
+    FilePosition { file_id, pos }: FilePosition,
+) -> Option<Vec<TextRange>> {
+    let file = db.get_file(file_id)?;
+    let text = file.contents(db);
+    let root = parse(db, file).syntax(db);
+    let token = pick_best_token(root.token_at_offset(pos), |kind| match kind {
+        T![ident] => 2,
+        T!['('] | T![')'] | T!['['] | T![']'] | T!['{'] | T!['}'] => 0,
+        WHITESPACE | NEWLINE | INDENT | DEDENT => 0,
+        _ => 1,
+    })?;
+
+    let mut ranges = Vec::new();
+    let mut push_range = |range: TextRange| {
+        // Don't select the whitespace surrounding a node, e.g. the newlines and indentation at the
+        // start and end of a suite.
+        let range = trim_range(text, range);
+        if !range.is_empty() && ranges.last() != Some(&range) {
+            ranges.push(range);
+        }
+    };
+
+    if token.kind() == STRING {
+        if let Some(range) = string_contents_range(&token) {
+            push_range(range);
+        }
+    }
+    push_range(token.text_range());
+    for node in token.parent_ancestors() {
+        push_range(node.text_range());
+    }
+
+    Some(ranges)
+}
+
+fn trim_range(text: &str, range: TextRange) -> TextRange {
+    let slice = &text[range];
+    let start = range.start() + TextSize::of(&slice[..slice.len() - slice.trim_start().len()]);
+    let end = range.start() + TextSize::of(slice.trim_end());
+    TextRange::new(start, end.max(start))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_common::Db;
+
+    use crate::Analysis;
+    use crate::FilePosition;
+
+    fn check_selection_ranges(fixture: &str, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let (file_id, pos) = fixture.cursor_pos.unwrap();
+        let contents = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+        let actual = analysis
+            .snapshot()
+            .selection_ranges(FilePosition { file_id, pos })
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|range| format!("{}\n---\n", &contents[range]))
+            .collect::<String>();
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn test_expand_from_expression() {
+        check_selection_ranges(
+            r#"
+def foo():
+    return bar(1, [2, 3$0])
+"#,
+            expect![[r#"
+                3
+                ---
+                [2, 3]
+                ---
+                (1, [2, 3])
+                ---
+                bar(1, [2, 3])
+                ---
+                return bar(1, [2, 3])
+                ---
+                def foo():
+                    return bar(1, [2, 3])
+                ---
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_expand_from_string() {
+        check_selection_ranges(
+            r#"
+x = "ab$0c"
+"#,
+            expect![[r#"
+                abc
+                ---
+                "abc"
+                ---
+                x = "abc"
+                ---
+            "#]],
+        );
+    }
+}
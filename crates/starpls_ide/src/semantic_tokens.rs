@@ -0,0 +1,351 @@
+use rustc_hash::FxHashSet;
+use starpls_common::Db;
+use starpls_common::File;
+use starpls_common::FileId;
+use starpls_hir::Name;
+use starpls_hir::ScopeDef;
+use starpls_hir::Semantics;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::match_ast;
+use starpls_syntax::SyntaxElement;
+use starpls_syntax::TextRange;
+
+use crate::util::string_contents_range;
+use crate::Database;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SemanticTokenKind {
+    Function,
+    Macro,
+    Parameter,
+    Provider,
+    Rule,
+    Variable,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SemanticTokenModifier {
+    /// The symbol is provided by Starlark or Bazel.
+    Builtin,
+    /// The token is the definition of the symbol.
+    Declaration,
+    /// The symbol's documentation marks it as deprecated.
+    Deprecated,
+    /// The symbol can't be reassigned, e.g. a builtin or a loaded symbol.
+    Readonly,
+    /// The symbol is defined but never used.
+    Unused,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: TextRange,
+    pub kind: SemanticTokenKind,
+    pub modifiers: Vec<SemanticTokenModifier>,
+}
+
+struct SemanticTokensHandler<'a> {
+    sema: Semantics<'a>,
+    file: File,
+    unused_ranges: FxHashSet<TextRange>,
+    tokens: Vec<SemanticToken>,
+}
+
+impl<'a> SemanticTokensHandler<'a> {
+    fn handle(mut self, range: Option<TextRange>) -> Vec<SemanticToken> {
+        let root = self.sema.parse(self.file).syntax(self.sema.db);
+        let Some(range) = range.map_or(Some(root.text_range()), |range| {
+            range.intersect(root.text_range())
+        }) else {
+            return Vec::new();
+        };
+
+        // Start from the smallest node covering the requested range, and skip any subtrees
+        // that lie entirely outside of it.
+        let mut stack = vec![match root.covering_element(range) {
+            SyntaxElement::Node(node) => node,
+            SyntaxElement::Token(token) => token.parent().unwrap_or(root),
+        }];
+        while let Some(node) = stack.pop() {
+            match_ast! {
+                match node {
+                    ast::NameRef(name_ref) => self.handle_name_ref(name_ref),
+                    ast::Name(name) => self.handle_name(name),
+                    ast::LoadItem(item) => self.handle_load_item(item),
+                    _ => None
+                }
+            };
+            stack.extend(
+                node.children()
+                    .filter(|child| child.text_range().intersect(range).is_some()),
+            );
+        }
+
+        self.tokens.sort_by_key(|token| token.range.start());
+        self.tokens
+    }
+
+    fn handle_name_ref(&mut self, name_ref: ast::NameRef) -> Option<()> {
+        let name = Name::from_ast_name_ref(name_ref.clone());
+        let range = name_ref.syntax().text_range();
+        let def = self
+            .sema
+            .scope_for_expr(
+                self.file,
+                &ast::Expression::cast(name_ref.syntax().clone())?,
+            )?
+            .resolve_name(&name)
+            .into_iter()
+            .next()?;
+        let (kind, mut modifiers) = self.classify_def(&def);
+
+        // Assignments resolve to the variable being defined.
+        if def.syntax_node_ptr(self.sema.db).map_or(false, |ptr| {
+            ptr.file == self.file && ptr.value.text_range() == range
+        }) {
+            modifiers.push(SemanticTokenModifier::Declaration);
+        }
+
+        self.add_token(range, kind, modifiers);
+        Some(())
+    }
+
+    fn handle_name(&mut self, name: ast::Name) -> Option<()> {
+        let range = name.syntax().text_range();
+        let parent = name.syntax().parent()?;
+        let (kind, mut modifiers) = match_ast! {
+            match parent {
+                ast::DefStmt(def_stmt) => {
+                    let callable = self.sema.resolve_def_stmt(self.file, &def_stmt)?;
+                    self.classify_def(&ScopeDef::Callable(callable))
+                },
+                ast::SimpleParameter(_) => (SemanticTokenKind::Parameter, Vec::new()),
+                ast::ArgsListParameter(_) => (SemanticTokenKind::Parameter, Vec::new()),
+                ast::KwargsDictParameter(_) => (SemanticTokenKind::Parameter, Vec::new()),
+                ast::KeywordArgument(_) => {
+                    self.add_token(range, SemanticTokenKind::Parameter, Vec::new());
+                    return Some(());
+                },
+                ast::AliasedLoadItem(item) => {
+                    let load_item = self
+                        .sema
+                        .resolve_load_item(self.file, &ast::LoadItem::Aliased(item))?;
+                    self.classify_def(&ScopeDef::LoadItem(load_item))
+                },
+                _ => return None,
+            }
+        };
+
+        modifiers.push(SemanticTokenModifier::Declaration);
+        self.add_token(range, kind, modifiers);
+        Some(())
+    }
+
+    fn handle_load_item(&mut self, item: ast::LoadItem) -> Option<()> {
+        let range = string_contents_range(&item.name()?)?;
+        let load_item = self.sema.resolve_load_item(self.file, &item)?;
+        let (kind, mut modifiers) = self.classify_def(&ScopeDef::LoadItem(load_item));
+
+        // Unused load items without an alias span the entire string, including the quotes.
+        if self.unused_ranges.contains(&item.syntax().text_range()) {
+            modifiers.push(SemanticTokenModifier::Unused);
+        }
+        self.add_token(range, kind, modifiers);
+        Some(())
+    }
+
+    fn classify_def(&self, def: &ScopeDef) -> (SemanticTokenKind, Vec<SemanticTokenModifier>) {
+        let mut modifiers = Vec::new();
+        if let ScopeDef::Parameter(_) = def {
+            return (SemanticTokenKind::Parameter, modifiers);
+        }
+
+        let ty = def.ty(self.sema.db);
+        let kind = if ty.is_rule() {
+            SemanticTokenKind::Rule
+        } else if ty.is_provider() {
+            SemanticTokenKind::Provider
+        } else if ty.is_macro() {
+            SemanticTokenKind::Macro
+        } else if ty.is_function() {
+            SemanticTokenKind::Function
+        } else {
+            SemanticTokenKind::Variable
+        };
+
+        if !def.is_user_defined() {
+            modifiers.push(SemanticTokenModifier::Builtin);
+        }
+        if ty
+            .doc(self.sema.db)
+            .map_or(false, |doc| is_deprecated(&doc))
+        {
+            modifiers.push(SemanticTokenModifier::Deprecated);
+        }
+        if matches!(def, ScopeDef::LoadItem(_)) || !def.is_user_defined() {
+            modifiers.push(SemanticTokenModifier::Readonly);
+        }
+
+        (kind, modifiers)
+    }
+
+    fn add_token(
+        &mut self,
+        range: TextRange,
+        kind: SemanticTokenKind,
+        mut modifiers: Vec<SemanticTokenModifier>,
+    ) {
+        if self.unused_ranges.contains(&range) {
+            modifiers.push(SemanticTokenModifier::Unused);
+        }
+        self.tokens.push(SemanticToken {
+            range,
+            kind,
+            modifiers,
+        });
+    }
+}
+
+/// Bazel builtins don't carry structured deprecation information, so rely on the convention
+/// of mentioning it at the start of the symbol's documentation.
+fn is_deprecated(doc: &str) -> bool {
+    doc.trim_start()
+        .get(..10)
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case("deprecated"))
+}
+
+pub(crate) fn semantic_tokens(
+    db: &Database,
+    file_id: FileId,
+    range: Option<TextRange>,
+) -> Option<Vec<SemanticToken>> {
+    let file = db.get_file(file_id)?;
+    let sema = Semantics::new(db);
+    let unused_ranges = sema.unused_definitions(file).into_iter().collect();
+
+    Some(
+        SemanticTokensHandler {
+            sema,
+            file,
+            unused_ranges,
+            tokens: Vec::new(),
+        }
+        .handle(range),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_common::Db;
+    use starpls_syntax::TextRange;
+
+    use crate::Analysis;
+
+    fn check_semantic_tokens(fixture: &str, expect: Expect) {
+        check_semantic_tokens_in_range(fixture, None, expect)
+    }
+
+    fn check_semantic_tokens_in_range(fixture: &str, range: Option<TextRange>, expect: Expect) {
+        let (analysis, fixture) = Analysis::from_single_file_fixture(fixture);
+        let file_id = *fixture.path_to_file_id.values().next().unwrap();
+        let contents = analysis
+            .db
+            .get_file(file_id)
+            .unwrap()
+            .contents(&analysis.db)
+            .clone();
+        let tokens = analysis
+            .snapshot()
+            .semantic_tokens(file_id, range)
+            .unwrap()
+            .unwrap();
+        let actual = tokens
+            .into_iter()
+            .map(|token| {
+                format!(
+                    "{} {:?} {:?}\n",
+                    &contents[token.range], token.kind, token.modifiers
+                )
+            })
+            .collect::<String>();
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn test_function_and_parameters() {
+        check_semantic_tokens(
+            r#"
+def foo(x):
+    return x
+
+foo(1)
+"#,
+            expect![[r#"
+                foo Function [Declaration]
+                x Parameter [Declaration]
+                x Parameter []
+                foo Function []
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        check_semantic_tokens_in_range(
+            r#"
+def foo(x):
+    return x
+
+def bar(y):
+    return y
+"#,
+            Some(TextRange::new(27.into(), 51.into())),
+            expect![[r#"
+                bar Function [Declaration]
+                y Parameter [Declaration]
+                y Parameter []
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_rules_and_providers() {
+        check_semantic_tokens(
+            r#"
+FooInfo = provider()
+foo = rule()
+FooInfo
+foo
+"#,
+            expect![[r#"
+                FooInfo Provider [Declaration]
+                provider Function [Builtin, Readonly]
+                foo Rule [Declaration]
+                rule Function [Builtin, Readonly]
+                FooInfo Provider []
+                foo Rule []
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_unused_definitions() {
+        check_semantic_tokens(
+            r#"
+load("//:foo.bzl", "bar")
+
+def foo():
+    x = 1
+    return 2
+"#,
+            expect![[r#"
+                bar Variable [Readonly]
+                foo Function [Declaration]
+                x Variable [Declaration, Unused]
+            "#]],
+        );
+    }
+}
@@ -1,5 +1,9 @@
+use starpls_syntax::ast::AstToken;
+use starpls_syntax::ast::{self};
 use starpls_syntax::SyntaxKind;
 use starpls_syntax::SyntaxToken;
+use starpls_syntax::TextRange;
+use starpls_syntax::TextSize;
 use starpls_syntax::TokenAtOffset;
 
 pub(crate) fn pick_best_token(
@@ -9,6 +13,22 @@ pub(crate) fn pick_best_token(
     tokens.max_by_key(|token| f(token.kind()))
 }
 
+/// Returns the range of a string literal's contents, excluding its prefix and quotes.
+pub(crate) fn string_contents_range(token: &SyntaxToken) -> Option<TextRange> {
+    let (_, offset) = ast::String::cast(token.clone())?.value_and_offset()?;
+    let opening = &token.text()[..offset as usize];
+    let quote_len = if opening.ends_with("\"\"\"") || opening.ends_with("'''") {
+        3
+    } else {
+        1
+    };
+    let range = token.text_range();
+    Some(TextRange::new(
+        range.start() + TextSize::from(offset),
+        range.end() - TextSize::from(quote_len),
+    ))
+}
+
 // TODO(withered-magic): This logic should probably be more sophisticated, but it works well
 // enough for now.
 pub(crate) fn unindent_doc(doc: &str) -> String {
@@ -0,0 +1,338 @@
+use rustc_hash::FxHashMap;
+use starpls_bazel::APIContext;
+use starpls_common::FileId;
+use starpls_syntax::ast::AstNode;
+use starpls_syntax::ast::{self};
+use starpls_syntax::line_index;
+use starpls_syntax::parse_module;
+use starpls_syntax::LineIndex;
+use starpls_syntax::SyntaxToken;
+use starpls_syntax::TextRange;
+
+use crate::document_symbols::target_symbols;
+use crate::SymbolKind;
+
+/// The maximum number of symbols returned for a single query.
+const MAX_WORKSPACE_SYMBOLS: usize = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkspaceSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub container_name: Option<String>,
+    pub file_id: FileId,
+    pub range: TextRange,
+}
+
+/// The symbols declared by a single file. Indexed files don't need to be loaded into the
+/// database, so a line index is kept alongside the symbols for reporting their locations.
+pub struct FileSymbols {
+    line_index: LineIndex,
+    symbols: Vec<WorkspaceSymbol>,
+}
+
+impl FileSymbols {
+    /// Collects the targets declared by a BUILD file, or the public macros, rules, providers and
+    /// variables declared by a `.bzl` file. `label` is the label of the file itself, e.g.
+    /// `//foo:defs.bzl`, and is used to qualify target names.
+    pub fn new(file_id: FileId, contents: &str, api_context: APIContext, label: &str) -> Self {
+        let tree = parse_module(contents, &mut |_| {}).tree();
+        let symbols = match api_context {
+            APIContext::Build => {
+                let package = label.split_once(':').map_or(label, |(package, _)| package);
+                target_symbols(tree.syntax())
+                    .map(|symbol| WorkspaceSymbol {
+                        name: format!("{}{}", package, symbol.name),
+                        kind: symbol.kind,
+                        container_name: None,
+                        file_id,
+                        range: symbol.selection_range,
+                    })
+                    .collect()
+            }
+            APIContext::Bzl => bzl_symbols(&tree)
+                .into_iter()
+                .map(|(name, kind, range)| WorkspaceSymbol {
+                    name,
+                    kind,
+                    container_name: Some(label.to_string()),
+                    file_id,
+                    range,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            line_index: line_index(contents),
+            symbols,
+        }
+    }
+}
+
+/// An index of the symbols declared across the workspace, used for `workspace/symbol`.
+#[derive(Default)]
+pub struct SymbolIndex {
+    files: FxHashMap<FileId, FileSymbols>,
+}
+
+impl SymbolIndex {
+    pub fn insert(&mut self, file_id: FileId, symbols: FileSymbols) {
+        self.files.insert(file_id, symbols);
+    }
+
+    pub fn remove(&mut self, file_id: FileId) {
+        self.files.remove(&file_id);
+    }
+
+    pub fn contains(&self, file_id: FileId) -> bool {
+        self.files.contains_key(&file_id)
+    }
+
+    pub fn line_index(&self, file_id: FileId) -> Option<&LineIndex> {
+        self.files.get(&file_id).map(|file| &file.line_index)
+    }
+
+    /// Returns the symbols whose names fuzzily match `query`, with the best matches first.
+    pub fn search(&self, query: &str) -> Vec<WorkspaceSymbol> {
+        self.search_by(query, |_| true)
+    }
+
+    /// Like [`SymbolIndex::search`], but only returns the symbols exported by `.bzl` files, i.e.
+    /// those that can be loaded by other files.
+    pub fn search_exports(&self, query: &str) -> Vec<WorkspaceSymbol> {
+        self.search_by(query, |symbol| symbol.container_name.is_some())
+    }
+
+    fn search_by(
+        &self,
+        query: &str,
+        filter: impl Fn(&WorkspaceSymbol) -> bool,
+    ) -> Vec<WorkspaceSymbol> {
+        let mut matches = self
+            .files
+            .values()
+            .flat_map(|file| file.symbols.iter())
+            .filter(|symbol| filter(symbol))
+            .filter_map(|symbol| Some((fuzzy_match(query, &symbol.name)?, symbol)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(score1, symbol1), (score2, symbol2)| {
+            score2
+                .cmp(score1)
+                .then_with(|| symbol1.name.len().cmp(&symbol2.name.len()))
+                .then_with(|| symbol1.name.cmp(&symbol2.name))
+        });
+        matches
+            .into_iter()
+            .take(MAX_WORKSPACE_SYMBOLS)
+            .map(|(_, symbol)| symbol.clone())
+            .collect()
+    }
+}
+
+fn bzl_symbols(module: &ast::Module) -> Vec<(String, SymbolKind, TextRange)> {
+    let mut symbols = Vec::new();
+    let mut add_symbol = |token: Option<SyntaxToken>, kind: SymbolKind| {
+        if let Some(token) = token.filter(|token| !token.text().starts_with('_')) {
+            symbols.push((token.text().to_string(), kind, token.text_range()));
+        }
+    };
+
+    for stmt in module.statements() {
+        match stmt {
+            ast::Statement::Def(stmt) => add_symbol(
+                stmt.name().and_then(|name| name.name()),
+                SymbolKind::Function,
+            ),
+            ast::Statement::Assign(stmt) => {
+                let kind = match stmt.rhs() {
+                    Some(ast::Expression::Call(expr)) => match expr.callee() {
+                        Some(ast::Expression::Name(name_ref)) => {
+                            match name_ref.name().as_ref().map(|name| name.text()) {
+                                Some("rule" | "repository_rule") => SymbolKind::Class,
+                                Some("provider") => SymbolKind::Struct,
+                                _ => SymbolKind::Variable,
+                            }
+                        }
+                        _ => SymbolKind::Variable,
+                    },
+                    _ => SymbolKind::Variable,
+                };
+
+                // `provider()` may return a tuple of the provider and its raw constructor.
+                let targets = match stmt.lhs() {
+                    Some(ast::Expression::Name(name_ref)) => vec![name_ref],
+                    Some(ast::Expression::Tuple(expr)) => expr
+                        .elements()
+                        .filter_map(|expr| match expr {
+                            ast::Expression::Name(name_ref) => Some(name_ref),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => continue,
+                };
+                for (index, name_ref) in targets.into_iter().enumerate() {
+                    let kind = if index == 0 {
+                        kind.clone()
+                    } else {
+                        SymbolKind::Variable
+                    };
+                    add_symbol(name_ref.name(), kind);
+                }
+            }
+            _ => {}
+        }
+    }
+    symbols
+}
+
+/// Scores how well `query` matches `candidate`, ignoring case. The characters of `query` must
+/// appear in order within `candidate`, and matches at the start of words and runs of consecutive
+/// matching characters are scored higher.
+fn fuzzy_match(query: &str, candidate: &str) -> Option<i64> {
+    let mut query_chars = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .peekable();
+    let mut score = 0;
+    let mut prev_char = None;
+    let mut prev_matched = false;
+    for c in candidate.chars() {
+        let Some(&query_char) = query_chars.peek() else {
+            break;
+        };
+        if c.to_ascii_lowercase() == query_char {
+            query_chars.next();
+            score += 1;
+            if prev_matched {
+                score += 4;
+            }
+            if is_word_start(prev_char, c) {
+                score += 8;
+            }
+            prev_matched = true;
+        } else {
+            prev_matched = false;
+        }
+        prev_char = Some(c);
+    }
+
+    if query_chars.peek().is_some() {
+        return None;
+    }
+    if candidate.eq_ignore_ascii_case(query) {
+        score += 100;
+    }
+    Some(score)
+}
+
+fn is_word_start(prev_char: Option<char>, c: char) -> bool {
+    match prev_char {
+        Some(prev_char) => {
+            !prev_char.is_alphanumeric() || (prev_char.is_lowercase() && c.is_uppercase())
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use expect_test::Expect;
+    use starpls_bazel::APIContext;
+    use starpls_common::FileId;
+
+    use super::FileSymbols;
+    use super::SymbolIndex;
+
+    fn check_search(query: &str, expect: Expect) {
+        let mut index = SymbolIndex::default();
+        index.insert(
+            FileId(0),
+            FileSymbols::new(
+                FileId(0),
+                r#"
+def cc_binary_macro(name):
+    pass
+
+def _impl(ctx):
+    pass
+
+my_rule = rule(implementation = _impl)
+FooInfo, _new_fooinfo = provider(init = _foo_init)
+DEFAULT_COPTS = ["-O2"]
+"#,
+                APIContext::Bzl,
+                "//foo:defs.bzl",
+            ),
+        );
+        index.insert(
+            FileId(1),
+            FileSymbols::new(
+                FileId(1),
+                r#"
+load(":defs.bzl", "my_rule")
+
+my_rule(name = "foo_lib")
+cc_binary(
+    name = "main",
+    deps = [":foo_lib"],
+)
+"#,
+                APIContext::Build,
+                "//foo:BUILD.bazel",
+            ),
+        );
+
+        let actual = index
+            .search(query)
+            .into_iter()
+            .map(|symbol| {
+                format!(
+                    "{} {:?} {:?}\n",
+                    symbol.name, symbol.kind, symbol.container_name
+                )
+            })
+            .collect::<String>();
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn test_search_bzl_symbols() {
+        check_search(
+            "rule",
+            expect![[r#"
+                my_rule Class Some("//foo:defs.bzl")
+            "#]],
+        );
+        check_search(
+            "fooinfo",
+            expect![[r#"
+                FooInfo Struct Some("//foo:defs.bzl")
+            "#]],
+        );
+        check_search(
+            "cbm",
+            expect![[r#"
+                cc_binary_macro Function Some("//foo:defs.bzl")
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_search_targets() {
+        check_search(
+            "foo:main",
+            expect![[r#"
+                //foo:main Variable None
+            "#]],
+        );
+        check_search(
+            "foolib",
+            expect![[r#"
+                //foo:foo_lib Variable None
+            "#]],
+        );
+    }
+}
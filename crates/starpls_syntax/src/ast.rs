@@ -987,6 +987,16 @@ impl AstNode for LoadItem {
     }
 }
 
+impl LoadItem {
+    /// The string literal naming the symbol being loaded.
+    pub fn name(&self) -> Option<SyntaxToken> {
+        match self {
+            LoadItem::Direct(item) => item.name(),
+            LoadItem::Aliased(item) => item.name(),
+        }
+    }
+}
+
 ast_node! {
     DirectLoadItem => DIRECT_LOAD_ITEM
     child_token name -> STRING;